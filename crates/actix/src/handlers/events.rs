@@ -0,0 +1,129 @@
+use crate::handlers::error::ErrorResponse;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use actix_web_lab::sse;
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use components::events::UserEvents;
+use components::state;
+use core::time::Duration;
+use log::{debug, warn};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_stream::Stream;
+
+/*
+ Events
+*/
+
+// [get] /users/{user_id}/events
+// WebSocket is used when upgrade is requested, otherwise Server-Sent Events
+// Note: not documented by apistos because `web::Payload` can not be described
+pub async fn get_user_events(
+    req: HttpRequest,
+    body: web::Payload,
+    app_data: web::Data<state::AppState>,
+    path: web::Path<i32>,
+) -> HttpResponse {
+    let user_id = path.into_inner();
+    let events = UserEvents::new(app_data.app_usecase.subscribe_todo_events(), user_id);
+
+    if !is_websocket(&req) {
+        return sse::Sse::from_stream(event_stream(events, user_id))
+            .with_keep_alive(Duration::from_secs(15))
+            .respond_to(&req)
+            .map_into_boxed_body();
+    }
+
+    match actix_ws::handle(&req, body) {
+        Ok((response, session, messages)) => {
+            actix_web::rt::spawn(send_events(session, messages, events, user_id));
+            response
+        }
+        Err(e) => HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("websocket handshake is failed: {}", e),
+        }),
+    }
+}
+
+fn is_websocket(req: &HttpRequest) -> bool {
+    req.headers()
+        .get("upgrade")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+// event_stream() converts events into SSE
+// lagged subscriber gets `error` event and should list todos again before reconnecting
+fn event_stream(
+    mut events: UserEvents,
+    user_id: i32,
+) -> impl Stream<Item = Result<sse::Event, serde_json::Error>> {
+    async_stream::stream! {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    yield sse::Data::new_json(&event)
+                        .map(|data| sse::Event::Data(data.event(event.kind.name())));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("{} todo events are dropped for user {}", skipped, user_id);
+                    yield sse::Data::new_json(ErrorResponse { error: lagged_message(skipped) })
+                        .map(|data| sse::Event::Data(data.event("error")));
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+// send_events() sends events as text messages until client closes
+// lagged subscriber gets close frame with 1011
+async fn send_events(
+    mut session: Session,
+    mut messages: MessageStream,
+    mut events: UserEvents,
+    user_id: i32,
+) {
+    let reason = loop {
+        tokio::select! {
+            received = events.recv() => match received {
+                Ok(event) => {
+                    let text = match serde_json::to_string(&event) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            warn!("todo event can not be serialized: {:?}", e);
+                            continue;
+                        }
+                    };
+                    if session.text(text).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("{} todo events are dropped for user {}", skipped, user_id);
+                    break Some(CloseReason {
+                        code: CloseCode::Error,
+                        description: Some(lagged_message(skipped)),
+                    });
+                }
+                Err(RecvError::Closed) => break None,
+            },
+            // messages from client are ignored except ping and close
+            message = messages.recv() => match message {
+                Some(Ok(Message::Ping(bytes))) => {
+                    if session.pong(&bytes).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(Message::Close(reason))) => break reason,
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => break None,
+            },
+        }
+    };
+    let _ = session.close(reason).await;
+    debug!("websocket of todo events is closed for user {}", user_id);
+}
+
+fn lagged_message(skipped: u64) -> String {
+    format!("{} events are dropped", skipped)
+}
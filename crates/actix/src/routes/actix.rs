@@ -47,6 +47,7 @@ pub fn api_admin_users_id_config(cfg: &mut web::ServiceConfig) {
 // - Add Todo: [POST] `/app/users/{user_id}/todos`
 // - Update Todo for Specific User: [PUT] `/app/users/{user_id}/todos/{id}`
 // - Remove Todo for Specific User: [DELETE] `/app/users/{user_id}/todos/{id}`
+// - Watch Todo Events for Specific User: [GET] `/app/users/{user_id}/events` (SSE or WebSocket)
 
 pub fn api_app_login_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -74,3 +75,11 @@ pub fn api_app_users_todo_id_config(cfg: &mut web::ServiceConfig) {
             .wrap(from_fn(auth_jwt::mw_app_auth_jwt)),
     );
 }
+
+pub fn api_app_users_events_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/users/{user_id}/events")
+            .route(web::get().to(handlers::events::get_user_events))
+            .wrap(from_fn(auth_jwt::mw_app_auth_jwt)),
+    );
+}
@@ -53,6 +53,12 @@ cfg_if! {
         ) -> std::io::Result<Server> {
             let server = HttpServer::new(move || {
                 App::new()
+                    // events route is registered before documented `api/v1` scope to take precedence
+                    .service(
+                        actix_web::web::scope("api/v1/app/users/{user_id}/events")
+                            .app_data(Data::new(app_state.clone()))
+                            .configure(route::api_app_users_events_config),
+                    )
                     .document(create_api_spec()) // requires build() as well
                     .wrap(create_cors())
                     .wrap(Logger::default())
@@ -109,7 +115,8 @@ cfg_if! {
                                     .app_data(Data::new(app_state.clone())) // app state // maybe divide it into each configuration level
                                     .configure(route::api_app_login_config)
                                     .configure(route::api_app_users_todo_config)
-                                    .configure(route::api_app_users_todo_id_config), //.wrap(from_fn(auth_jwt::mw_app_auth_jwt)),
+                                    .configure(route::api_app_users_todo_id_config)
+                                    .configure(route::api_app_users_events_config), //.wrap(from_fn(auth_jwt::mw_app_auth_jwt)),
                             ),
                     )
             })
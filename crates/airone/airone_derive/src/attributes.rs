@@ -0,0 +1,110 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use syn::{Attribute, Expr, LitStr};
+
+/// Value of a field missing in an older file
+pub enum FieldDefault {
+    /// `#[airone(default)]`, uses the `Default` of the field type
+    Trait,
+    /// `#[airone(default = expr)]`
+    Expr(Expr),
+}
+
+/// `#[airone(...)]` options of a field
+#[derive(Default)]
+pub struct FieldAttributes {
+    pub default: Option<FieldDefault>,
+    /// `#[airone(id)]`, the field is an id assigned by the database
+    pub id: bool,
+    /// `#[airone(index)]`, lookups by value are indexed
+    pub index: bool,
+    /// `#[airone(unique)]`, an indexed field whose values
+    /// can't be repeated
+    pub unique: bool,
+    /// Previous names of the field, from `#[airone(rename_from = "old")]`
+    pub rename_from: Vec<String>,
+}
+
+/// `#[airone(...)]` options of the struct
+#[derive(Default)]
+pub struct StructAttributes {
+    /// `#[airone(default)]`, missing fields are taken
+    /// from the `Default` of the struct
+    pub default: bool,
+    /// Columns of older files to be dropped, from `#[airone(drop = "old")]`
+    pub drop: Vec<String>,
+}
+
+pub fn parse_field_attributes(attrs: &[Attribute]) -> syn::Result<FieldAttributes> {
+    let mut parsed = FieldAttributes::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("airone")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                parsed.id = true;
+                Ok(())
+            } else if meta.path.is_ident("index") {
+                parsed.index = true;
+                Ok(())
+            } else if meta.path.is_ident("unique") {
+                parsed.unique = true;
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                parsed.default = Some(if meta.input.peek(syn::Token![=]) {
+                    FieldDefault::Expr(meta.value()?.parse()?)
+                } else {
+                    FieldDefault::Trait
+                });
+                Ok(())
+            } else if meta.path.is_ident("rename_from") {
+                let old: LitStr = meta.value()?.parse()?;
+                parsed.rename_from.push(old.value());
+                Ok(())
+            } else {
+                Err(meta.error(
+                    "expected `id`, `index`, `unique`, `default`, `default = ...` or `rename_from = \"...\"`",
+                ))
+            }
+        })?;
+    }
+    Ok(parsed)
+}
+
+pub fn parse_struct_attributes(attrs: &[Attribute]) -> syn::Result<StructAttributes> {
+    let mut parsed = StructAttributes::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("airone")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                parsed.default = true;
+                Ok(())
+            } else if meta.path.is_ident("drop") {
+                let column: LitStr = meta.value()?.parse()?;
+                parsed.drop.push(column.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `default` or `drop = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(parsed)
+}
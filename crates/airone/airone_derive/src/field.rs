@@ -0,0 +1,190 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, Ident};
+
+/// Implements `SerializableField` for a nested struct or an enum.
+///
+/// Structs pack their fields with `SerializedFieldValue::join`.
+/// Enums without data are written as the variant name, other
+/// enums pack the variant name followed by its fields.
+pub fn build_field(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let mut generics = input.generics.clone();
+    let type_params: Vec<Ident> = generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(syn::parse_quote! { #param: airone::serde::SerializableField });
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (serialize, deserialize) = match &input.data {
+        Data::Struct(data) => {
+            let count = data.fields.len();
+            let (pattern, values) = bind_fields(&data.fields);
+            let constructor = construct(quote! { Self }, &data.fields);
+            (
+                // let Self { a, b } = self;
+                // SerializedFieldValue::join([a.serialize_field(), b.serialize_field()])
+                quote! {
+                    let Self #pattern = self;
+                    airone::serde::SerializedFieldValue::join([#(#values),*])
+                },
+                // let mut parts = v.split_exact(2)?.into_iter();
+                // Ok(Self { a: deserialize_field(parts.next().unwrap())?, ... })
+                quote! {
+                    let mut parts = v.split_exact(#count)?.into_iter();
+                    Ok(#constructor)
+                },
+            )
+        }
+        Data::Enum(data) if data.variants.iter().all(|v| v.fields.is_empty()) => {
+            let variants: Vec<&Ident> = data.variants.iter().map(|v| &v.ident).collect();
+            let variants_str: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+            (
+                quote! {
+                    airone::serde::SerializedFieldValue::new(match self {
+                        #(Self::#variants => #variants_str),*
+                    }.to_string())
+                },
+                quote! {
+                    match v.get() {
+                        #(#variants_str => Ok(Self::#variants),)*
+                        _ => Err(airone::error::Error::ParseError(airone::error::ParseError::new(
+                            format!("variant of `{}`", #name_str),
+                            v.get().to_string(),
+                        ))),
+                    }
+                },
+            )
+        }
+        Data::Enum(data) => {
+            let mut serialize_arms = Vec::new();
+            let mut deserialize_arms = Vec::new();
+            for variant in data.variants.iter() {
+                let variant_name = &variant.ident;
+                let variant_str = variant_name.to_string();
+                let count = variant.fields.len() + 1;
+                let (pattern, values) = bind_fields(&variant.fields);
+                let constructor = construct(quote! { Self::#variant_name }, &variant.fields);
+                // Self::Circle(f0) => join([new("Circle"), f0.serialize_field()])
+                serialize_arms.push(quote! {
+                    Self::#variant_name #pattern => airone::serde::SerializedFieldValue::join([
+                        airone::serde::SerializedFieldValue::new(#variant_str.to_string()),
+                        #(#values),*
+                    ])
+                });
+                deserialize_arms.push(quote! {
+                    Some(#variant_str) if parts.len() == #count => {
+                        let mut parts = parts.into_iter().skip(1);
+                        Ok(#constructor)
+                    }
+                });
+            }
+            (
+                quote! {
+                    match self {
+                        #(#serialize_arms),*
+                    }
+                },
+                quote! {
+                    let parts = v.split()?;
+                    match parts.first().map(|p| p.get()) {
+                        #(#deserialize_arms)*
+                        _ => Err(airone::error::Error::ParseError(airone::error::ParseError::new(
+                            format!("variant of `{}`", #name_str),
+                            v.get().to_string(),
+                        ))),
+                    }
+                },
+            )
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "AironeField can't be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics airone::serde::SerializableField for #name #ty_generics #where_clause
+        {
+            fn serialize_field(&self) -> airone::serde::SerializedFieldValue
+            {
+                use airone::serde::SerializableField;
+                #serialize
+            }
+            fn deserialize_field(
+                v: airone::serde::SerializedFieldValue,
+            ) -> Result<Self, airone::error::Error>
+            {
+                #deserialize
+            }
+        }
+    })
+}
+
+/// A pattern binding every field, as `{ a, b }` or `(f0, f1)`,
+/// and the serialized value of each binding
+fn bind_fields(fields: &Fields) -> (TokenStream, Vec<TokenStream>) {
+    let bindings: Vec<Ident> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| f.ident.clone().unwrap_or_else(|| format_ident!("f{}", i)))
+        .collect();
+    let values = bindings
+        .iter()
+        .map(|b| quote! { #b.serialize_field() })
+        .collect();
+    let pattern = match fields {
+        Fields::Named(_) => quote! { { #(#bindings),* } },
+        Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+        Fields::Unit => TokenStream::new(),
+    };
+    (pattern, values)
+}
+
+/// Builds `path` taking each field from the `parts` iterator
+fn construct(path: TokenStream, fields: &Fields) -> TokenStream {
+    let value = quote! {
+        airone::serde::SerializableField::deserialize_field(parts.next().unwrap())?
+    };
+    match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| f.ident.as_ref().unwrap());
+            quote! { #path { #(#names: #value),* } }
+        }
+        Fields::Unnamed(unnamed) => {
+            let values = unnamed.unnamed.iter().map(|_| &value);
+            quote! { #path ( #(#values),* ) }
+        }
+        Fields::Unit => path,
+    }
+}
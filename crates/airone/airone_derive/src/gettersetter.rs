@@ -23,7 +23,9 @@
 
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Data, DataStruct, DeriveInput, Fields};
+use syn::{Data, DataStruct, DeriveInput, Field, Fields};
+
+use crate::attributes::parse_field_attributes;
 
 pub fn build_getset(input: DeriveInput) -> TokenStream {
     let struct_name = input.ident;
@@ -39,6 +41,14 @@ pub fn build_getset(input: DeriveInput) -> TokenStream {
         }
     };
 
+    // The id is assigned by the database, so it has no setter.
+    // Invalid attributes are reported by build_serde
+    let settable_fields: Vec<Field> = fields
+        .iter()
+        .filter(|f| !parse_field_attributes(&f.attrs).is_ok_and(|a| a.id))
+        .cloned()
+        .collect();
+
     // -------------------------------------------------
     // Implemento quelle sul singolo elemento T
     // e sul writeproxy
@@ -51,10 +61,6 @@ pub fn build_getset(input: DeriveInput) -> TokenStream {
         &format!("AironeInterfaceSettersFallible{}", struct_name),
         proc_macro2::Span::call_site(),
     );
-    let trait_name_single_object_setters_infallible = syn::Ident::new(
-        &format!("AironeInterfaceSettersInfallible{}", struct_name),
-        proc_macro2::Span::call_site(),
-    );
 
     let gettersetter_singleelement_trait = {
         let trait_functions_getter = fields.iter().map(|f| {
@@ -69,7 +75,7 @@ pub fn build_getset(input: DeriveInput) -> TokenStream {
             }
         });
 
-        let trait_functions_setter_fallible = fields.iter().map(|f|
+        let trait_functions_setter_fallible = settable_fields.iter().map(|f|
             {
                 let field_name = f.ident.as_ref().unwrap();
                 let set_function_name = syn::Ident::new(
@@ -82,17 +88,6 @@ pub fn build_getset(input: DeriveInput) -> TokenStream {
                 }
             }
         );
-        let trait_functions_setter_infallible = fields.iter().map(|f| {
-            let field_name = f.ident.as_ref().unwrap();
-            let set_function_name = syn::Ident::new(
-                &format!("set_{}", field_name),
-                proc_macro2::Span::call_site(),
-            );
-            let field_type = &f.ty;
-            quote! {
-                fn #set_function_name(&mut self, value: #field_type);
-            }
-        });
 
         quote! {
             pub trait #impl_generics #trait_name_single_object_getters
@@ -105,11 +100,6 @@ pub fn build_getset(input: DeriveInput) -> TokenStream {
             {
                 #(#trait_functions_setter_fallible)*
             }
-            pub trait #impl_generics #trait_name_single_object_setters_infallible
-            #ty_generics #where_clause
-            {
-                #(#trait_functions_setter_infallible)*
-            }
         }
     };
 
@@ -158,23 +148,7 @@ pub fn build_getset(input: DeriveInput) -> TokenStream {
             }
         });
 
-        let functions_set_infallible = fields.clone().into_iter().map(|f| {
-            let field_name = f.ident.unwrap();
-            let set_function_name = syn::Ident::new(
-                &format!("set_{}", field_name),
-                proc_macro2::Span::call_site(),
-            );
-            let field_type = f.ty;
-
-            quote! {
-                fn #set_function_name(&mut self, new_value: #field_type)
-                {
-                    self.set(stringify!(#field_name), new_value);
-                }
-            }
-        });
-
-        let functions_set_fallible = fields.clone().into_iter().map(|f|
+        let functions_set_fallible = settable_fields.clone().into_iter().map(|f|
             {
                 let field_name = f.ident.unwrap();
                 let set_function_name = syn::Ident::new(
@@ -190,7 +164,8 @@ pub fn build_getset(input: DeriveInput) -> TokenStream {
                     }
                 }
             }
-        );
+        )
+        .collect::<Vec<_>>();
 
         //
 
@@ -209,9 +184,9 @@ pub fn build_getset(input: DeriveInput) -> TokenStream {
                 #(#functions_set_fallible)*
             }
             #[automatically_derived]
-            impl <'prox> #trait_name_single_object_setters_infallible  for airone::database::WriteProxy<'prox, #struct_name, airone::database::settings::save_mode::ManualSave>
+            impl <'prox> #trait_name_single_object_setters_fallible  for airone::database::WriteProxy<'prox, #struct_name, airone::database::settings::save_mode::ManualSave>
             {
-                #(#functions_set_infallible)*
+                #(#functions_set_fallible)*
             }
         }
     };
@@ -0,0 +1,146 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataStruct, DeriveInput, Fields};
+
+use crate::attributes::parse_field_attributes;
+
+/// Builds the indexes of the `#[airone(index)]` and
+/// `#[airone(unique)]` fields, as the body of `InnerStruct::indexes`,
+/// and a trait with `find_by_<field>` and `range_by_<field>`
+/// lookups implemented on `AironeDb`
+pub fn build_indexes(input: &DeriveInput) -> syn::Result<(TokenStream, TokenStream)> {
+    let struct_name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            panic!("this derive macro only works on structs with named fields")
+        }
+    };
+
+    let mut new_indexes = Vec::new();
+    let mut trait_functions = Vec::new();
+    let mut impl_functions = Vec::new();
+    for field in fields.iter() {
+        let attributes = parse_field_attributes(&field.attrs)?;
+        if !attributes.index && !attributes.unique {
+            continue;
+        }
+        let unique = attributes.unique;
+        let field_name = field.ident.as_ref().unwrap();
+        let field_str = field_name.to_string();
+        let field_type = &field.ty;
+        let find_function_name = syn::Ident::new(
+            &format!("find_by_{}", field_name),
+            proc_macro2::Span::call_site(),
+        );
+        let range_function_name = syn::Ident::new(
+            &format!("range_by_{}", field_name),
+            proc_macro2::Span::call_site(),
+        );
+
+        new_indexes.push(quote! {
+            Box::new(airone::database::index::BTreeIndex::new(
+                #field_str,
+                #unique,
+                |element: &Self| &element.#field_name,
+            ))
+        });
+
+        let (find_result, find_body) = if unique {
+            (
+                quote! { Option<&#struct_name> },
+                quote! { self.find_by::<#field_type, Q>(#field_str, value).next() },
+            )
+        } else {
+            (
+                quote! { Vec<&#struct_name> },
+                quote! { self.find_by::<#field_type, Q>(#field_str, value).collect() },
+            )
+        };
+        trait_functions.push(quote! {
+            fn #find_function_name<Q>(&self, value: &Q) -> #find_result
+            where
+                #field_type: std::borrow::Borrow<Q>,
+                Q: Ord + ?Sized;
+            fn #range_function_name<Q, R>(&self, range: R) -> Vec<&#struct_name>
+            where
+                #field_type: std::borrow::Borrow<Q>,
+                Q: Ord + ?Sized,
+                R: std::ops::RangeBounds<Q>;
+        });
+        impl_functions.push(quote! {
+            fn #find_function_name<Q>(&self, value: &Q) -> #find_result
+            where
+                #field_type: std::borrow::Borrow<Q>,
+                Q: Ord + ?Sized,
+            {
+                #find_body
+            }
+            fn #range_function_name<Q, R>(&self, range: R) -> Vec<&#struct_name>
+            where
+                #field_type: std::borrow::Borrow<Q>,
+                Q: Ord + ?Sized,
+                R: std::ops::RangeBounds<Q>,
+            {
+                self.range_by::<#field_type, Q, R>(#field_str, range).collect()
+            }
+        });
+    }
+
+    if new_indexes.is_empty() {
+        return Ok((TokenStream::new(), TokenStream::new()));
+    }
+
+    let impl_indexes = quote! {
+        fn indexes() -> Vec<Box<dyn airone::database::index::FieldIndex<Self>>> {
+            vec![
+                #(#new_indexes),*
+            ]
+        }
+    };
+
+    let trait_name = syn::Ident::new(
+        &format!("AironeIndexes{}", struct_name),
+        proc_macro2::Span::call_site(),
+    );
+    let lookups = quote! {
+        pub trait #trait_name
+        {
+            #(#trait_functions)*
+        }
+        #[automatically_derived]
+        impl<SaveMode> #trait_name for airone::database::AironeDb<#struct_name, SaveMode>
+            where
+            SaveMode: airone::database::settings::save_mode::SaveModeExt
+        {
+            #(#impl_functions)*
+        }
+    };
+    Ok((impl_indexes, lookups))
+}
@@ -26,15 +26,45 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, DeriveInput};
 
+mod attributes;
+
+mod field;
+use field::build_field;
+
 mod gettersetter;
 use gettersetter::build_getset;
 
+mod indexes;
+
+mod query;
+use query::build_query;
+
 mod serde;
 use serde::build_serde;
 
 /// A macro needed to generate methods to use your
 /// struct with [AironeDb](https://gitlab.com/MassiminoilTrace/airone)
-#[proc_macro_derive(AironeDbDerive)]
+///
+/// Older files are loaded by column name. The `airone` attribute
+/// describes how the struct changed since they were written:
+/// - `#[airone(default)]` or `#[airone(default = expr)]` on a new field
+/// - `#[airone(rename_from = "old")]` on a renamed field
+/// - `#[airone(drop = "old")]` on the struct for a removed field
+/// - `#[airone(default)]` on the struct to take every
+///   missing field from its `Default`
+///
+/// `#[airone(id)]` on an `u64` field makes it a stable id,
+/// assigned by the database when the element is added.
+///
+/// `#[airone(index)]` and `#[airone(unique)]` index a field,
+/// whose type must implement `Ord`, and generate the
+/// `find_by_<field>` and `range_by_<field>` lookups
+/// in the `AironeIndexes<Struct>` trait.
+///
+/// It also generates the `<Struct>Field` enum and a typed
+/// `Struct::<field>()` for each field, to build queries such as
+/// `db.query().filter(Struct::a().gt(3)).order_by(Struct::b()).all()`.
+#[proc_macro_derive(AironeDbDerive, attributes(airone))]
 pub fn getters(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -42,10 +72,28 @@ pub fn getters(input: TokenStream) -> TokenStream {
 
     let serde_data = build_serde(&input, crate_name.clone());
     let access_functions = build_getset(input.clone());
+    let query_fields = build_query(&input);
 
     quote! {
         #serde_data
         #access_functions
+        #query_fields
+    }
+    .into()
+}
+
+/// Implements `SerializableField` for an enum or a struct,
+/// to use it as a field of an [AironeDb](https://gitlab.com/MassiminoilTrace/airone)
+/// struct. The type must implement `Clone`.
+///
+/// Enums without data are written as the variant name.
+/// Other enums and structs pack their values in a single field.
+#[proc_macro_derive(AironeField)]
+pub fn field(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match build_field(&input) {
+        Ok(tokens) => tokens,
+        Err(e) => e.to_compile_error(),
     }
     .into()
 }
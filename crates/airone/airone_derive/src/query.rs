@@ -0,0 +1,107 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataStruct, DeriveInput, Fields};
+
+/// Builds the `<Struct>Field` enum, listing the fields,
+/// and a typed `Struct::<field>()` for each field,
+/// to write queries such as `Struct::a().gt(3)`
+pub fn build_query(input: &DeriveInput) -> TokenStream {
+    let struct_name = &input.ident;
+    let vis = &input.vis;
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            panic!("this derive macro only works on structs with named fields")
+        }
+    };
+
+    let enum_name = syn::Ident::new(
+        &format!("{}Field", struct_name),
+        proc_macro2::Span::call_site(),
+    );
+    let mut variants = Vec::new();
+    let mut columns = Vec::new();
+    let mut field_functions = Vec::new();
+    for field in fields.iter() {
+        let field_name = field.ident.as_ref().unwrap();
+        let field_str = field_name.to_string();
+        let field_type = &field.ty;
+        variants.push(syn::Ident::new(
+            &to_camel_case(&field_str),
+            proc_macro2::Span::call_site(),
+        ));
+        columns.push(field_str.clone());
+
+        // Expanded example:
+        // pub fn a() -> airone::database::query::Field<Animal, i32> {
+        //     airone::database::query::Field::new("a", |element| &element.a)
+        // }
+        field_functions.push(quote! {
+            #vis fn #field_name() -> airone::database::query::Field<#struct_name, #field_type> {
+                airone::database::query::Field::new(#field_str, |element: &Self| &element.#field_name)
+            }
+        });
+    }
+
+    quote! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #vis enum #enum_name {
+            #(#variants),*
+        }
+        #[automatically_derived]
+        impl #enum_name {
+            /// Every field, in declaration order
+            pub const ALL: &'static [Self] = &[#(Self::#variants),*];
+
+            /// Name of the column of the field
+            pub fn column(&self) -> &'static str {
+                match self {
+                    #(Self::#variants => #columns),*
+                }
+            }
+        }
+        #[automatically_derived]
+        impl #struct_name {
+            #(#field_functions)*
+        }
+    }
+}
+
+fn to_camel_case(snake_case: &str) -> String {
+    snake_case
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
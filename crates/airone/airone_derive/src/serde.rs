@@ -26,6 +26,9 @@ use quote::quote;
 use syn::Ident;
 use syn::{punctuated::Punctuated, token::Comma, Data, DataStruct, DeriveInput, Field, Fields};
 
+use crate::attributes::{parse_field_attributes, parse_struct_attributes, FieldDefault};
+use crate::indexes::build_indexes;
+
 pub fn build_serde(input: &DeriveInput, _cur_crate: TokenStream) -> TokenStream {
     let struct_name = &input.ident;
     let (_impl_generics, _ty_generics, _where_clause) = input.generics.split_for_impl();
@@ -45,13 +48,36 @@ pub fn build_serde(input: &DeriveInput, _cur_crate: TokenStream) -> TokenStream
     //         for #cur_crate::database::AironeDb<#struct_name> #ty_generics #where_clause
 
     let impl_inner_struct = impl_innerstruct_trait(struct_name, &fields);
+    let impl_named_fields = impl_named_fields_trait(struct_name, &fields);
+    let impl_schema = match impl_schema(input, &fields) {
+        Ok(impl_schema) => impl_schema,
+        Err(e) => return e.to_compile_error(),
+    };
+    let (impl_id, impl_identified) = match impl_id(struct_name, &fields) {
+        Ok(impl_id) => impl_id,
+        Err(e) => return e.to_compile_error(),
+    };
+    let (impl_indexes, index_lookups) = match build_indexes(input) {
+        Ok(indexes) => indexes,
+        Err(e) => return e.to_compile_error(),
+    };
     let impl_from_into = impl_serialize_deserialize(struct_name, &fields);
     quote! {
         #[automatically_derived]
         impl airone::serde::InnerStruct for #struct_name
         {
             #impl_inner_struct
+            #impl_schema
+            #impl_id
+            #impl_indexes
+        }
+        #[automatically_derived]
+        impl airone::serde::NamedFields for #struct_name
+        {
+            #impl_named_fields
         }
+        #impl_identified
+        #index_lookups
         #impl_from_into
     }
 }
@@ -60,6 +86,57 @@ fn impl_innerstruct_trait(struct_name: &Ident, fields: &Punctuated<Field, Comma>
     let column_names_str = fields.iter().map(|f| f.ident.as_ref().unwrap().to_string());
     let struct_name_str = struct_name.to_string();
 
+    let match_for_setstr = fields.iter().map(|field| {
+        let field_name = &field.ident;
+        let field_str = field_name.as_ref().unwrap().to_string();
+        quote! {
+            #field_str => {
+                self.#field_name = SerializableField::deserialize_field(value)?;
+            }
+        }
+    });
+
+    quote! {
+        const COLUMNS: &'static [&'static str] = &[
+            #(#column_names_str),*
+        ];
+        const STRUCT_NAME: &'static str = #struct_name_str;
+
+        // fn set_str(&mut self, key: &str, value: SerializedFieldValue) -> Result<(), Error>
+        // {
+        //     match key
+        //     {
+        //         "a" => {
+        //             self.a = value.clone().try_into()?;
+        //         },
+        //         "b" => {
+        //             self.b = value.clone().try_into()?;
+        //         },
+        //         _ => return Err(Error::SchemaMismatch { .. })
+        //     }
+        //     Ok(())
+        // }
+        fn set_str(&mut self, key: &str, value: airone::serde::SerializedFieldValue) -> Result<(), airone::error::Error>
+        {
+            use airone::serde::SerializableField;
+            match key
+            {
+                #(#match_for_setstr),*
+                _ => {
+                    return Err(airone::error::Error::SchemaMismatch {
+                        struct_name: Self::STRUCT_NAME.to_string(),
+                        expected: Self::COLUMNS.iter().map(|c| c.to_string()).collect(),
+                        found: vec![key.to_string()],
+                    });
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Access to the fields by name, see `NamedFields`
+fn impl_named_fields_trait(struct_name: &Ident, fields: &Punctuated<Field, Comma>) -> TokenStream {
     let match_for_get = fields.iter().map(|field| {
         let field_name = &field.ident.as_ref().unwrap();
         let field_str = field_name.to_string();
@@ -99,22 +176,12 @@ fn impl_innerstruct_trait(struct_name: &Ident, fields: &Punctuated<Field, Comma>
         }
     });
 
-    let match_for_setstr = fields.iter().map(|field| {
-        let field_name = &field.ident;
-        let field_str = field_name.as_ref().unwrap().to_string();
-        quote! {
-            #field_str => {
-                self.#field_name = SerializableField::deserialize_field(value)?;
-            }
-        }
-    });
-
     let setting_unknown_field_panic_handler = quote! {
         panic!(
             "Error trying to set field `{}`, but it doesn't exist in type \"{}\". \nAvailable fields are:\n{}",
             key,
             stringify!(#struct_name),
-            Self::COLUMNS.iter().map(|c|
+            <Self as airone::serde::InnerStruct>::COLUMNS.iter().map(|c|
             {
                 format!(" -`{}`", c)
             }).collect::<Vec<_>>().join("\n")
@@ -122,11 +189,6 @@ fn impl_innerstruct_trait(struct_name: &Ident, fields: &Punctuated<Field, Comma>
     };
 
     quote! {
-        const COLUMNS: &'static [&'static str] = &[
-            #(#column_names_str),*
-        ];
-        const STRUCT_NAME: &'static str = #struct_name_str;
-
         // fn get<V: SerializableField>(&self, key: &str) -> &V
         // {
         //     use std::any::Any;
@@ -149,7 +211,7 @@ fn impl_innerstruct_trait(struct_name: &Ident, fields: &Punctuated<Field, Comma>
                         "Error trying to get field `{}`, but it doesn't exist in type \"{}\". \nAvailable fields are:\n{}",
                         key,
                         stringify!(#struct_name),
-                        Self::COLUMNS.iter().map(|c|
+                        <Self as airone::serde::InnerStruct>::COLUMNS.iter().map(|c|
                         {
                             format!(" -`{}`", c)
                         }).collect::<Vec<_>>().join("\n")
@@ -181,33 +243,113 @@ fn impl_innerstruct_trait(struct_name: &Ident, fields: &Punctuated<Field, Comma>
                 _ => {#setting_unknown_field_panic_handler}
             }
         }
+    }
+}
+
+/// Describes how to load files written
+/// by older versions of the struct
+fn impl_schema(input: &DeriveInput, fields: &Punctuated<Field, Comma>) -> syn::Result<TokenStream> {
+    let struct_attributes = parse_struct_attributes(&input.attrs)?;
+    let dropped_columns = &struct_attributes.drop;
+
+    let mut renamed_columns = Vec::new();
+    let mut match_for_default = Vec::new();
+    for field in fields.iter() {
+        let field_name = field.ident.as_ref().unwrap();
+        let field_str = field_name.to_string();
+        let field_type = &field.ty;
+        let field_attributes = parse_field_attributes(&field.attrs)?;
+
+        for old in field_attributes.rename_from.iter() {
+            renamed_columns.push(quote! {(#old, #field_str)});
+        }
 
+        let default_value = match field_attributes.default {
+            Some(FieldDefault::Expr(expr)) => quote! {
+                {
+                    let value: #field_type = #expr;
+                    value
+                }
+            },
+            Some(FieldDefault::Trait) => quote! {
+                <#field_type as Default>::default()
+            },
+            None if struct_attributes.default => quote! {
+                <Self as Default>::default().#field_name
+            },
+            None => continue,
+        };
+        match_for_default.push(quote! {
+            #field_str => Some(SerializableField::serialize_field(&#default_value))
+        });
+    }
 
-        // fn set_str(&mut self, key: &str, value: SerializedFieldValue) -> Result<(), Error>
+    Ok(quote! {
+        const RENAMED_COLUMNS: &'static [(&'static str, &'static str)] = &[
+            #(#renamed_columns),*
+        ];
+        const DROPPED_COLUMNS: &'static [&'static str] = &[
+            #(#dropped_columns),*
+        ];
+
+        // fn default_column(key: &str) -> Option<SerializedFieldValue>
         // {
         //     match key
         //     {
-        //         "a" => {
-        //             self.a = value.clone().try_into()?;
-        //         },
-        //         "b" => {
-        //             self.b = value.clone().try_into()?;
-        //         },
-        //         _ => {unreachable!()}
+        //         "c" => Some(SerializableField::serialize_field(&<i32 as Default>::default())),
+        //         _ => None
         //     }
-        //     Ok(())
         // }
-        fn set_str(&mut self, key: &str, value: airone::serde::SerializedFieldValue) -> Result<(), airone::error::Error>
+        fn default_column(key: &str) -> Option<airone::serde::SerializedFieldValue>
         {
             use airone::serde::SerializableField;
             match key
             {
-                #(#match_for_setstr),*
-                _ => {#setting_unknown_field_panic_handler}
+                #(#match_for_default,)*
+                _ => None
             }
-            Ok(())
+        }
+    })
+}
+
+/// Implements the id methods for the `#[airone(id)]` field,
+/// which must be an `u64`
+fn impl_id(
+    struct_name: &Ident,
+    fields: &Punctuated<Field, Comma>,
+) -> syn::Result<(TokenStream, TokenStream)> {
+    let mut id_fields = Vec::new();
+    for field in fields.iter() {
+        if parse_field_attributes(&field.attrs)?.id {
+            id_fields.push(field);
         }
     }
+    let field_name = match id_fields.as_slice() {
+        [] => return Ok((TokenStream::new(), TokenStream::new())),
+        [field] => field.ident.as_ref().unwrap(),
+        [_, second, ..] => {
+            return Err(syn::Error::new_spanned(
+                second,
+                "only one field can be marked with `#[airone(id)]`",
+            ))
+        }
+    };
+    let field_str = field_name.to_string();
+
+    let impl_id = quote! {
+        const ID_COLUMN: Option<&'static str> = Some(#field_str);
+        fn id(&self) -> Option<u64> {
+            Some(self.#field_name)
+        }
+        fn set_id(&mut self, id: u64) {
+            self.#field_name = id;
+        }
+    };
+    let impl_identified = quote! {
+        #[automatically_derived]
+        impl airone::serde::Identified for #struct_name {}
+    };
+    Ok((impl_id, impl_identified))
 }
 
 fn impl_serialize_deserialize(
@@ -247,7 +389,12 @@ fn impl_serialize_deserialize(
             {
                 use airone::serde::InnerStruct;
                 let values = value.get_values();
-                assert!(values.len()==Self::COLUMNS.len());
+                if values.len() != Self::COLUMNS.len() {
+                    return Err(airone::error::Error::ParseError(airone::error::ParseError::new(
+                        format!("{} values", Self::COLUMNS.len()),
+                        format!("{} values", values.len()),
+                    )));
+                }
                 let mut values = values.iter().cloned();
                 Ok(
                     Self {
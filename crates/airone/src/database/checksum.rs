@@ -0,0 +1,90 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use crate::error::{Error, ParseError};
+
+/// CRC-32 (IEEE) lookup table
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc = TABLE[((crc ^ *b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// Appends the checksum of the line as the last column
+pub(crate) fn append(line: &str) -> String {
+    format!("{}\t{:08x}", line, crc32(line.as_bytes()))
+}
+
+/// Checks and strips the checksum appended by [append]
+pub(crate) fn verify(line: &str) -> Result<&str, Error> {
+    let valid = line.rsplit_once('\t').filter(|(content, checksum)| {
+        u32::from_str_radix(checksum, 16).ok() == Some(crc32(content.as_bytes()))
+    });
+    match valid {
+        Some((content, _)) => Ok(content),
+        None => Err(Error::ParseError(ParseError::new(
+            "a line ending with a valid checksum".to_string(),
+            line.to_string(),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let line = append("E\t0\tfield\tvalue");
+        assert_eq!(verify(&line).unwrap(), "E\t0\tfield\tvalue");
+        assert!(verify(&line[..line.len() - 1]).is_err());
+        assert!(verify(&line.replace("value", "valve")).is_err());
+        assert!(verify("E\t0\tfield\tvalue").is_err());
+    }
+}
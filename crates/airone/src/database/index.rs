@@ -0,0 +1,239 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use std::any::Any;
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
+
+use crate::error::Error;
+use crate::serde::{InnerStruct, SerializableField};
+
+/// An index over a field of `T`, stored as trait object
+/// to keep indexes of different field types together
+pub trait FieldIndex<T> {
+    /// The indexed field
+    fn column(&self) -> &'static str;
+    /// Fails if `element` violates a unique index
+    fn check(&self, element: &T) -> Result<(), Error>;
+    /// Adds the element at `position`
+    fn add(&mut self, position: usize, element: &T);
+    /// Removes the element at `position`
+    fn remove(&mut self, position: usize, element: &T);
+    /// Positions of the elements with a value within the bounds,
+    /// in ascending order. `None` if the bounds are not of the field type.
+    fn positions_in(&self, lower: Bound<&dyn Any>, upper: Bound<&dyn Any>) -> Option<Vec<usize>>;
+    /// Moves by `delta` the positions from `from` on,
+    /// after an element is inserted or removed
+    fn shift(&mut self, from: usize, delta: isize);
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// An index ordered by the value of the field
+pub struct BTreeIndex<T, K> {
+    column: &'static str,
+    unique: bool,
+    key: fn(&T) -> &K,
+    /// Positions of the elements, in ascending order
+    entries: BTreeMap<K, Vec<usize>>,
+}
+impl<T, K: Ord + Clone> BTreeIndex<T, K> {
+    pub fn new(column: &'static str, unique: bool, key: fn(&T) -> &K) -> Self {
+        Self {
+            column,
+            unique,
+            key,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Positions of the elements with the given value
+    pub(crate) fn find<Q>(&self, value: &Q) -> &[usize]
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.entries.get(value).map_or(&[], |positions| positions)
+    }
+
+    /// Positions of the elements with a value in `range`,
+    /// ordered by value
+    pub(crate) fn range<Q, R>(&self, range: R) -> impl Iterator<Item = usize> + '_
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let empty = is_empty_range(range.start_bound(), range.end_bound());
+        // BTreeMap::range panics on inverted bounds
+        (!empty)
+            .then(|| self.entries.range::<Q, R>(range))
+            .into_iter()
+            .flatten()
+            .flat_map(|(_, positions)| positions.iter().copied())
+    }
+}
+
+fn is_empty_range<Q: Ord + ?Sized>(lower: Bound<&Q>, upper: Bound<&Q>) -> bool {
+    match (lower, upper) {
+        (Bound::Included(l), Bound::Included(u)) => l > u,
+        (Bound::Included(l) | Bound::Excluded(l), Bound::Included(u) | Bound::Excluded(u)) => {
+            l >= u
+        }
+        _ => false,
+    }
+}
+
+fn downcast_bound<K: 'static>(bound: Bound<&dyn Any>) -> Option<Bound<&K>> {
+    match bound {
+        Bound::Included(value) => value.downcast_ref().map(Bound::Included),
+        Bound::Excluded(value) => value.downcast_ref().map(Bound::Excluded),
+        Bound::Unbounded => Some(Bound::Unbounded),
+    }
+}
+impl<T, K> FieldIndex<T> for BTreeIndex<T, K>
+where
+    T: InnerStruct + 'static,
+    K: SerializableField + Ord,
+{
+    fn column(&self) -> &'static str {
+        self.column
+    }
+
+    fn check(&self, element: &T) -> Result<(), Error> {
+        let key = (self.key)(element);
+        if self.unique && self.entries.contains_key(key) {
+            return Err(Error::UniqueViolation {
+                struct_name: T::struct_name().to_string(),
+                column: self.column.to_string(),
+                value: key.serialize_field().get().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, position: usize, element: &T) {
+        let positions = self.entries.entry((self.key)(element).clone()).or_default();
+        if let Err(i) = positions.binary_search(&position) {
+            positions.insert(i, position);
+        }
+    }
+
+    fn remove(&mut self, position: usize, element: &T) {
+        let key = (self.key)(element);
+        if let Some(positions) = self.entries.get_mut(key) {
+            positions.retain(|p| *p != position);
+            if positions.is_empty() {
+                self.entries.remove(key);
+            }
+        }
+    }
+
+    fn positions_in(&self, lower: Bound<&dyn Any>, upper: Bound<&dyn Any>) -> Option<Vec<usize>> {
+        let bounds = (downcast_bound::<K>(lower)?, downcast_bound::<K>(upper)?);
+        let mut positions: Vec<usize> = self.range::<K, _>(bounds).collect();
+        positions.sort_unstable();
+        Some(positions)
+    }
+
+    fn shift(&mut self, from: usize, delta: isize) {
+        for positions in self.entries.values_mut() {
+            for position in positions.iter_mut().filter(|p| **p >= from) {
+                *position = position.wrapping_add_signed(delta);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Positions of the elements by their id,
+/// empty if `T` has no `#[airone(id)]` field
+#[derive(Default)]
+pub(crate) struct IdIndex {
+    positions: BTreeMap<u64, usize>,
+}
+impl IdIndex {
+    pub fn build<T: InnerStruct>(elements: &[T]) -> Self {
+        let mut ids = Self::default();
+        for (position, element) in elements.iter().enumerate() {
+            ids.add(position, element.id());
+        }
+        ids
+    }
+
+    /// Position of the element with the given id
+    pub fn get(&self, id: u64) -> Option<usize> {
+        self.positions.get(&id).copied()
+    }
+
+    /// Records the element just inserted at `position` of `elements`
+    pub fn insert<T: InnerStruct>(&mut self, elements: &[T], position: usize) {
+        // Appending moves no element
+        if position + 1 < elements.len() {
+            self.shift(position, 1);
+        }
+        self.add(position, elements[position].id());
+    }
+
+    /// Forgets the element with `id` just removed from `position`,
+    /// `len` is the number of elements left
+    pub fn remove(&mut self, position: usize, id: Option<u64>, len: usize) {
+        if let Some(id) = id {
+            self.positions.remove(&id);
+        }
+        if position < len {
+            self.shift(position + 1, -1);
+        }
+    }
+
+    /// Moves the element at `position` from `old_id` to `new_id`
+    pub fn change_id(&mut self, position: usize, old_id: Option<u64>, new_id: Option<u64>) {
+        if old_id != new_id {
+            if let Some(id) = old_id {
+                self.positions.remove(&id);
+            }
+            self.add(position, new_id);
+        }
+    }
+
+    /// Elements without an id yet have id 0
+    fn add(&mut self, position: usize, id: Option<u64>) {
+        if let Some(id) = id.filter(|id| *id != 0) {
+            self.positions.insert(id, position);
+        }
+    }
+
+    fn shift(&mut self, from: usize, delta: isize) {
+        for position in self.positions.values_mut().filter(|p| **p >= from) {
+            *position = position.wrapping_add_signed(delta);
+        }
+    }
+}
@@ -22,6 +22,7 @@
 //  ------------------------------------------------------------------
 
 use std::fs::File;
+use std::fs::OpenOptions;
 use std::io;
 use std::io::BufRead;
 use std::io::BufReader;
@@ -30,106 +31,413 @@ use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 
+use super::checksum;
+use super::index::IdIndex;
 use super::operations;
+use super::schema::ColumnMapping;
 use super::InnerStruct;
-use crate::error::Error;
+use crate::error::{Error, ParseError};
 use crate::serde::Deserialize;
+use crate::serde::FormatVersion;
 use crate::serde::SerializedStruct;
 
-/// Returns the changes file and the collected elements
-pub fn full_load<T: InnerStruct>(filename: &str) -> Result<(BufWriter<File>, Vec<T>), Error> {
-    os_check();
+/// First line of both files, followed by the format version
+/// and the generation of the base file.
+/// Files without it were written by older versions
+/// and are read as [FormatVersion::V1]
+const VERSION_MARKER: &str = "#airone-format";
+
+/// Parsed version marker line
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Marker {
+    version: FormatVersion,
+    /// Incremented at each compaction. The changes file
+    /// only applies to the base file of the same generation.
+    generation: u64,
+    /// The id of the next added element, only
+    /// written for structs with an `#[airone(id)]` field.
+    /// It survives the removal of the last elements,
+    /// so their ids are never given again.
+    next_id: Option<u64>,
+}
+impl Marker {
+    /// Marker of files written before versioning
+    const LEGACY: Self = Self {
+        version: FormatVersion::V1,
+        generation: 0,
+        next_id: None,
+    };
+}
 
-    let basefile = PathBuf::from(&format!("{}{}", filename, ".csv"));
-    let changes_file = PathBuf::from(&format!("{}{}", filename, ".changes.csv"));
+/// Steps of the compaction, in the order they happen.
+///
+/// A crash can happen after any of them, so loading must
+/// never lose or apply twice the persisted changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CompactionStep {
+    WriteBase,
+    SyncBase,
+    RenameBase,
+    SyncBaseDir,
+    WriteChanges,
+    SyncChanges,
+    RenameChanges,
+    SyncChangesDir,
+}
+#[cfg(test)]
+impl CompactionStep {
+    pub(crate) const ALL: [Self; 8] = [
+        Self::WriteBase,
+        Self::SyncBase,
+        Self::RenameBase,
+        Self::SyncBaseDir,
+        Self::WriteChanges,
+        Self::SyncChanges,
+        Self::RenameChanges,
+        Self::SyncChangesDir,
+    ];
+}
+
+/// Result of [full_load]
+pub struct Loaded<T> {
+    /// The changes file, opened to append changes
+    pub changes_writer: BufWriter<File>,
+    pub elements: Vec<T>,
+    /// The next id, if the struct has an id field
+    pub next_id: Option<u64>,
+    /// The last line of the changes file, if it was torn and skipped
+    pub skipped_torn_line: Option<Error>,
+}
+
+pub fn full_load<T: InnerStruct>(basefile: &Path, changes_file: &Path) -> Result<Loaded<T>, Error> {
+    os_check();
 
     let mut elements: Vec<T> = Vec::new();
 
-    load_base_file(&mut elements, &basefile)?;
-    load_changes_file(&mut elements, &changes_file)?;
-    dump_compacted_file(&elements, &basefile)?;
-
-    // Delete changes file
-    let file = std::fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(changes_file)
-        .unwrap();
+    let (base_marker, mapping) = load_base_file(&mut elements, basefile)?;
+    let (last_added_id, skipped_torn_line) = load_changes_file(
+        &mut elements,
+        changes_file,
+        base_marker.generation,
+        &mapping,
+    )?;
+    let next_id = base_marker.next_id.unwrap_or(1).max(last_added_id + 1);
+    let next_id = assign_ids(&mut elements, next_id)?;
+    let marker = Marker {
+        version: FormatVersion::CURRENT,
+        generation: base_marker.generation + 1,
+        next_id,
+    };
+    // Also rewrites files of older struct versions in the new layout
+    compact(&elements, basefile, changes_file, marker)?;
+
+    let file = OpenOptions::new().append(true).open(changes_file)?;
     let filewriter = BufWriter::new(file);
 
-    Ok((filewriter, elements))
+    Ok(Loaded {
+        changes_writer: filewriter,
+        elements,
+        next_id,
+        skipped_torn_line,
+    })
 }
 
-fn load_base_file<T: InnerStruct>(elements: &mut Vec<T>, basefile: &Path) -> Result<(), Error> {
-    if let Ok(file) = std::fs::OpenOptions::new().read(true).open(basefile) {
-        let buf = BufReader::new(file);
-        let mut lines = buf.lines();
-        {
-            let header_line = lines.next().unwrap().unwrap();
-            check_field_consistency(&header_line, T::COLUMNS);
-        }
-        for line in lines {
-            let ser = SerializedStruct::from_escaped_line(&line?);
-            elements.push(Deserialize::deserialize(&ser)?);
+/// Loads the base file and returns its marker
+/// and the mapping of its columns, which the changes file shares
+fn load_base_file<T: InnerStruct>(
+    elements: &mut Vec<T>,
+    basefile: &Path,
+) -> Result<(Marker, ColumnMapping), Error> {
+    let file = match OpenOptions::new().read(true).open(basefile) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok((Marker::LEGACY, ColumnMapping::identity::<T>()))
         }
+        Err(e) => return Err(e.into()),
+    };
+    let mut lines = BufReader::new(file)
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line));
+    let mut next_header_line = || match lines.next() {
+        Some((_, line)) => Ok(line?),
+        None => Err(Error::ParseError(ParseError::new(
+            "column header".to_string(),
+            "end of file".to_string(),
+        ))),
+    };
+
+    let first_line = next_header_line().map_err(|e| corrupt_line(basefile, 1, e))?;
+    let (marker, mapping) =
+        match parse_marker(&first_line).map_err(|e| corrupt_line(basefile, 1, e))? {
+            Some(marker) => {
+                let header_line = next_header_line().map_err(|e| corrupt_line(basefile, 2, e))?;
+                (marker, ColumnMapping::from_header::<T>(&header_line)?)
+            }
+            None => (
+                Marker::LEGACY,
+                ColumnMapping::from_header::<T>(&first_line)?,
+            ),
+        };
+    for (n, line) in lines {
+        let line = line?;
+        let element = SerializedStruct::from_escaped_line(&line, marker.version)
+            .and_then(|ser| mapping.map_struct(ser))
+            .and_then(|ser| Deserialize::deserialize(&ser))
+            .map_err(|e| corrupt_line(basefile, n, e))?;
+        elements.push(element);
     }
-    Ok(())
+    Ok((marker, mapping))
 }
 
 /// Apply modifications contained
-/// in the transaction log file
+/// in the transaction log file and returns
+/// the highest id of the added elements, or 0
+///
+/// The last line may have been torn by a crash while appending it:
+/// if it can't be read, it is skipped and returned as the error
+/// it would have raised.
 fn load_changes_file<T: InnerStruct>(
     elements: &mut Vec<T>,
     changes_file: &Path,
-) -> Result<(), Error> {
-    let file = match std::fs::OpenOptions::new().read(true).open(changes_file) {
-        Ok(e) => e,
-        Err(e) if e.kind() == io::ErrorKind::NotFound => {
-            File::create(changes_file)?;
-            return Ok(());
-        }
+    base_generation: u64,
+    mapping: &ColumnMapping,
+) -> Result<(u64, Option<Error>), Error> {
+    let content = match std::fs::read(changes_file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, None)),
         Err(e) => return Err(e.into()),
     };
 
-    let reader = BufReader::new(file);
+    let mut lines: Vec<&[u8]> = content.split(|b| *b == b'\n').collect();
+    // A complete file ends with a newline, otherwise the last line may be torn
+    let complete = content.is_empty() || content.ends_with(b"\n");
+    if complete {
+        lines.pop();
+    }
 
-    for line in reader.lines() {
-        let op = operations::Operation::from_line(&line?)?;
-        op.apply(elements)?;
+    let mut marker = Marker::LEGACY;
+    let mut first_change = 0;
+    if let Some(Ok(first_line)) = lines.first().map(|l| std::str::from_utf8(l)) {
+        if let Some(m) = parse_marker(first_line).map_err(|e| corrupt_line(changes_file, 1, e))? {
+            marker = m;
+            first_change = 1;
+        }
+    }
+    if marker.generation < base_generation {
+        // A crash happened after the base file was compacted,
+        // these changes are already included in it
+        return Ok((0, None));
+    }
+    if marker.generation > base_generation {
+        let e = Error::ParseError(ParseError::new(
+            format!("changes for generation {}", base_generation),
+            format!("changes for generation {}", marker.generation),
+        ));
+        return Err(corrupt_line(changes_file, 1, e));
     }
-    Ok(())
-}
 
-fn dump_compacted_file<T: InnerStruct>(elements: &[T], basefile: &Path) -> Result<(), Error> {
-    let f = File::create(basefile)?;
-    let mut writer = BufWriter::new(f);
+    let mut last_added_id = 0;
+    let mut skipped_torn_line = None;
+    let mut ids = IdIndex::build(elements);
+    for (i, line) in lines.iter().enumerate().skip(first_change) {
+        match parse_change_line(line, marker.version) {
+            Ok(op) => {
+                if let Some(op) = mapping
+                    .map_operation::<T>(op)
+                    .map_err(|e| corrupt_line(changes_file, i + 1, e))?
+                {
+                    op.apply(elements, &mut ids)
+                        .map_err(|e| corrupt_line(changes_file, i + 1, e))?;
+                    if let operations::Operation::Add { index, .. } = op {
+                        let id = elements[index].id().unwrap_or_default();
+                        last_added_id = last_added_id.max(id);
+                    }
+                }
+            }
+            Err(e) if !complete && i + 1 == lines.len() => {
+                skipped_torn_line = Some(corrupt_line(changes_file, i + 1, e));
+            }
+            Err(e) => return Err(corrupt_line(changes_file, i + 1, e)),
+        }
+    }
+    Ok((last_added_id, skipped_torn_line))
+}
 
-    writeln!(writer, "{}", T::COLUMNS.join("\t")).unwrap();
+/// Checks that ids are unique and gives a new one to the
+/// elements with id 0, like those written before the id field
+/// was added. Returns the next id, `None` without an id field.
+fn assign_ids<T: InnerStruct>(elements: &mut [T], mut next_id: u64) -> Result<Option<u64>, Error> {
+    if T::ID_COLUMN.is_none() {
+        return Ok(None);
+    }
+    let mut ids = std::collections::HashSet::new();
+    for id in elements.iter().filter_map(|e| e.id()).filter(|id| *id != 0) {
+        if !ids.insert(id) {
+            return Err(Error::DuplicateId { id });
+        }
+        next_id = next_id.max(id + 1);
+    }
+    for element in elements.iter_mut().filter(|e| e.id() == Some(0)) {
+        element.set_id(next_id);
+        next_id += 1;
+    }
+    Ok(Some(next_id))
+}
 
-    for element in elements.iter() {
-        let serialized_object: SerializedStruct = element.serialize();
-        writeln!(writer, "{}", serialized_object.to_escaped_line())?;
+fn corrupt_line(path: &Path, line: usize, source: Error) -> Error {
+    Error::CorruptLine {
+        path: path.to_path_buf(),
+        line,
+        source: Box::new(source),
     }
+}
+
+fn parse_change_line(line: &[u8], version: FormatVersion) -> Result<operations::Operation, Error> {
+    let line = std::str::from_utf8(line).map_err(|e| {
+        Error::ParseError(ParseError::new("an utf-8 line".to_string(), e.to_string()))
+    })?;
+    let line = if version >= FormatVersion::V3 {
+        checksum::verify(line)?
+    } else {
+        line
+    };
+    operations::Operation::from_line(line, version)
+}
+
+/// Writes the compacted base file and an empty changes file
+/// of the next generation.
+///
+/// Each file is written to a temporary file, synced and atomically
+/// renamed, so a crash leaves either the old or the new file.
+/// Until the changes file is replaced, its old generation tells
+/// that its changes are already in the new base file.
+fn compact<T: InnerStruct>(
+    elements: &[T],
+    basefile: &Path,
+    changes_file: &Path,
+    marker: Marker,
+) -> Result<(), Error> {
+    write_atomically(
+        basefile,
+        [
+            CompactionStep::WriteBase,
+            CompactionStep::SyncBase,
+            CompactionStep::RenameBase,
+            CompactionStep::SyncBaseDir,
+        ],
+        |writer| {
+            writeln!(writer, "{}", marker_line(marker))?;
+            writeln!(writer, "{}", T::columns().join("\t"))?;
+            for element in elements.iter() {
+                let serialized_object: SerializedStruct = element.serialize();
+                writeln!(writer, "{}", serialized_object.to_escaped_line())?;
+            }
+            Ok(())
+        },
+    )?;
+    write_atomically(
+        changes_file,
+        [
+            CompactionStep::WriteChanges,
+            CompactionStep::SyncChanges,
+            CompactionStep::RenameChanges,
+            CompactionStep::SyncChangesDir,
+        ],
+        |writer| writeln!(writer, "{}", marker_line(marker)),
+    )?;
+    Ok(())
+}
+
+/// Replaces `path` with the written content
+/// through a temporary file in the same directory
+fn write_atomically(
+    path: &Path,
+    steps: [CompactionStep; 4],
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> Result<(), Error> {
+    let [write_step, sync_step, rename_step, sync_dir_step] = steps;
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    write(&mut writer)?;
     writer.flush()?;
+    checkpoint(write_step)?;
+
+    writer.get_ref().sync_all()?;
+    checkpoint(sync_step)?;
+
+    std::fs::rename(&tmp_path, path)?;
+    checkpoint(rename_step)?;
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()?;
+    checkpoint(sync_dir_step)?;
     Ok(())
 }
 
-/// Maps struct_field → column order in the file
-fn check_field_consistency(header_line: &str, struct_fields: &[&str]) {
-    let detected_columns: Vec<&str> = header_line.split('\t').collect();
-    assert_eq!(
-        detected_columns.len(),
-        struct_fields.len(),
-        "Column mismatch. Did you add or delete some fields? "
+fn marker_line(marker: Marker) -> String {
+    let mut line = format!(
+        "{}\t{}\t{}",
+        VERSION_MARKER,
+        marker.version.number(),
+        marker.generation
     );
-    for i in 0..detected_columns.len() {
-        assert_eq!(
-            struct_fields[i],
-            detected_columns[i],
-            "Column mismatch. Did you reorder or change some field name? Collapse the CSV, fix column or field ordering and naming, then re-run"
-        );
+    if let Some(next_id) = marker.next_id {
+        line.push_str(&format!("\t{}", next_id));
     }
+    line
+}
+
+/// Returns the version marker,
+/// or `None` if the line is not a marker
+fn parse_marker(line: &str) -> Result<Option<Marker>, Error> {
+    let Some(rest) = line
+        .strip_prefix(VERSION_MARKER)
+        .and_then(|rest| rest.strip_prefix('\t'))
+    else {
+        return Ok(None);
+    };
+    let invalid = || {
+        Error::ParseError(ParseError::new(
+            format!(
+                "a format version up to {} and a generation",
+                FormatVersion::CURRENT.number()
+            ),
+            rest.to_string(),
+        ))
+    };
+    let mut parts = rest.split('\t');
+    let version = parts
+        .next()
+        .and_then(|v| v.parse().ok())
+        .and_then(FormatVersion::from_number)
+        .ok_or_else(invalid)?;
+    // Version 2 has no generation
+    let generation = match parts.next() {
+        Some(g) => g.parse().map_err(|_| invalid())?,
+        None if version < FormatVersion::V3 => 0,
+        None => return Err(invalid()),
+    };
+    // Since version 4, for structs with an id
+    let next_id = match parts.next() {
+        Some(n) if version >= FormatVersion::V4 => Some(n.parse().map_err(|_| invalid())?),
+        Some(_) => return Err(invalid()),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(Some(Marker {
+        version,
+        generation,
+        next_id,
+    }))
 }
 
 fn os_check() {
@@ -144,15 +452,49 @@ fn os_check() {
     }
     {
         // WSL
-        let output = std::process::Command::new("uname")
-            .arg("-a")
-            .output()
-            .expect("Failed to execute command");
-        let st = String::from_utf8(output.stdout).unwrap();
-        if st.to_ascii_lowercase().contains("microsoft") {
+        let output = std::process::Command::new("uname").arg("-a").output();
+        let st = output
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_ascii_lowercase())
+            .unwrap_or_default();
+        if st.contains("microsoft") {
             eprintln!("You're running airone inside of WSL, which is a compatibility layer and may unexpectedly break or introduce bugs. Airone has never been tested on WSL. \n\
                 Anyway, to get the best from this library I highly encourage you to switch to a traditionally installed operating system such as a full Gnu/Linux installation instead.
                 ");
         }
     }
 }
+
+#[cfg(not(test))]
+fn checkpoint(_step: CompactionStep) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+use fault::checkpoint;
+
+/// Simulates a crash by failing the compaction
+/// right after the given step
+#[cfg(test)]
+pub(crate) mod fault {
+    use super::CompactionStep;
+    use std::cell::Cell;
+    use std::io;
+
+    thread_local! {
+        static CRASH_AFTER: Cell<Option<CompactionStep>> = const { Cell::new(None) };
+    }
+
+    pub(crate) fn crash_after(step: Option<CompactionStep>) {
+        CRASH_AFTER.with(|c| c.set(step));
+    }
+
+    pub(super) fn checkpoint(step: CompactionStep) -> io::Result<()> {
+        if CRASH_AFTER.with(|c| c.get()) == Some(step) {
+            return Err(io::Error::other(format!(
+                "simulated crash after {:?}",
+                step
+            )));
+        }
+        Ok(())
+    }
+}
@@ -21,6 +21,7 @@
 //  along with this program. If not, see <https://www.gnu.org/licenses/>.
 //  ------------------------------------------------------------------
 
+use std::borrow::Borrow;
 use std::io::Write;
 use std::ops::{Bound, Index, RangeBounds};
 use std::{fs::File, io::BufWriter, marker::PhantomData};
@@ -28,16 +29,32 @@ use std::{fs::File, io::BufWriter, marker::PhantomData};
 use crate::error::Error;
 pub mod settings;
 
-use crate::serde::{InnerStruct, SerializableField, SerializedFieldValue, SerializedStruct};
+use crate::serde::{
+    Identified, InnerStruct, NamedFields, SerializableField, SerializedFieldValue, SerializedStruct,
+};
 
+mod checksum;
+/// Indexes of the `#[airone(index)]` and `#[airone(unique)]` fields
+///
+/// They map the value of a field to the positions of the
+/// elements in the list, and are kept in sync at each change.
+pub mod index;
 mod operations;
+/// Typed queries, built from the fields
+/// generated by `AironeDbDerive`
+pub mod query;
+mod schema;
 mod write_proxy;
 use settings::save_mode::{AutoSave, SaveModeExt};
 pub use write_proxy::WriteProxy;
 
+use self::index::{BTreeIndex, FieldIndex, IdIndex};
+use self::query::Query;
 use self::settings::save_mode::ManualSave;
-use self::settings::BufferMode;
+use self::settings::{BufferMode, OpenOptions};
 mod loading;
+#[cfg(test)]
+pub(crate) use loading::{fault, CompactionStep};
 
 pub struct AironeDb<T, SaveMode = AutoSave>
 where
@@ -49,21 +66,66 @@ where
     pending_changes: Vec<operations::RevertableChange>,
     mode: PhantomData<SaveMode>,
     write_mode: BufferMode,
+    /// Id of the next added element, if `T` has an id field
+    next_id: Option<u64>,
+    indexes: Vec<Box<dyn FieldIndex<T>>>,
+    /// Positions of the elements by id
+    ids: IdIndex,
+    /// Torn last line of the changes file skipped while opening
+    skipped_torn_line: Option<Error>,
 }
 // Common public methods
 impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
     pub fn new() -> Result<Self, Error> {
-        Self::new_with_custom_name(T::STRUCT_NAME)
+        Self::new_with_custom_name(T::struct_name())
     }
     pub fn new_with_custom_name(custom_name: &str) -> Result<Self, Error> {
-        let (filewriter, data) = loading::full_load(custom_name)?;
-        Ok(Self {
-            buf_writer: filewriter,
-            elements: data,
+        Self::open(OpenOptions::default().name(custom_name))
+    }
+
+    /// Opens the database in a directory, or with the given options.
+    ///
+    /// Returns [Error::DirectoryNotFound] if the directory doesn't exist.
+    ///
+    /// ```no_run
+    /// # use airone::prelude::*;
+    /// # #[derive(AironeDbDerive)]
+    /// # struct Foo { a: i32 }
+    /// let db: AironeDb<Foo> = AironeDb::open("data").unwrap();
+    /// let db: AironeDb<Foo, save_mode::ManualSave> = AironeDb::open(
+    ///     OpenOptions::new()
+    ///         .directory("data")
+    ///         .name("foos")
+    ///         .extension("tsv")
+    ///         .buffer_mode(BufferMode::AlwaysFlush)
+    ///         .save_mode::<save_mode::ManualSave>(),
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn open(options: impl Into<OpenOptions<SaveMode>>) -> Result<Self, Error> {
+        T::check_shape()?;
+        let options = options.into();
+        let directory = options.get_directory();
+        if !directory.as_os_str().is_empty() && !directory.is_dir() {
+            return Err(Error::DirectoryNotFound {
+                path: directory.to_path_buf(),
+            });
+        }
+        let (basefile, changes_file) = options.paths(T::struct_name());
+        let loaded = loading::full_load(&basefile, &changes_file)?;
+        let mut db = Self {
+            buf_writer: loaded.changes_writer,
+            elements: loaded.elements,
             pending_changes: Vec::new(),
             mode: PhantomData,
-            write_mode: BufferMode::Buffered,
-        })
+            write_mode: options.get_buffer_mode(),
+            next_id: loaded.next_id,
+            indexes: T::indexes(),
+            ids: IdIndex::default(),
+            skipped_torn_line: loaded.skipped_torn_line,
+        };
+        db.rebuild_indexes()?;
+        Ok(db)
     }
 
     pub fn get_all(&self) -> &[T] {
@@ -76,7 +138,7 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
 
     /// Mutably access the element
     /// at the specified index
-    pub fn get_mut(&mut self, index: usize) -> Option<WriteProxy<T, SaveMode>> {
+    pub fn get_mut(&mut self, index: usize) -> Option<WriteProxy<'_, T, SaveMode>> {
         if index < self.len() {
             Some(WriteProxy::new(self, index))
         } else {
@@ -99,15 +161,90 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
             self.write_mode = mode;
             if self.write_mode == BufferMode::AlwaysFlush {
                 self.buf_writer.flush()?;
+                self.buf_writer.get_ref().sync_data()?;
             }
         }
         Ok(())
     }
 
+    /// Starts a query on the elements
+    pub fn query(&self) -> Query<T, &Self>
+    where
+        T: 'static,
+    {
+        Query::new(self)
+    }
+
+    /// Starts a query to update or delete
+    /// the elements it selects
+    pub fn query_mut(&mut self) -> Query<T, &mut Self>
+    where
+        T: 'static,
+    {
+        Query::new(self)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.elements.iter()
     }
 
+    /// Returns the elements whose indexed field `column`
+    /// is equal to `value`, in list order
+    ///
+    /// `K` is the type of the field. The `find_by_<field>` methods
+    /// generated by [AironeDbDerive](airone_derive::AironeDbDerive) call it with the right types.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `column` has no index of type `K`.
+    pub fn find_by<K, Q>(&self, column: &str, value: &Q) -> impl Iterator<Item = &T>
+    where
+        T: 'static,
+        K: Ord + Clone + Borrow<Q> + 'static,
+        Q: Ord + ?Sized,
+    {
+        self.field_index::<K>(column)
+            .find(value)
+            .iter()
+            .map(|position| &self.elements[*position])
+    }
+
+    /// Returns the elements whose indexed field `column`
+    /// is within `range`, ordered by value
+    ///
+    /// # Panics
+    ///
+    /// Panics if `column` has no index of type `K`.
+    pub fn range_by<K, Q, R>(&self, column: &str, range: R) -> impl Iterator<Item = &T>
+    where
+        T: 'static,
+        K: Ord + Clone + Borrow<Q> + 'static,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.field_index::<K>(column)
+            .range(range)
+            .map(|position| &self.elements[position])
+    }
+
+    fn field_index<K: 'static>(&self, column: &str) -> &BTreeIndex<T, K>
+    where
+        T: 'static,
+    {
+        self.indexes
+            .iter()
+            .find(|field_index| field_index.column() == column)
+            .and_then(|field_index| field_index.as_any().downcast_ref())
+            .unwrap_or_else(|| {
+                panic!(
+                    "Error trying to use the index of field `{}`, but type \"{}\" has no such index of type `{}`",
+                    column,
+                    T::struct_name(),
+                    std::any::type_name::<K>()
+                )
+            })
+    }
+
     /// Change the SaveMode to the provided value
     ///
     /// For example, you can convert a db to [ManualSave] or [AutoSave] mode.
@@ -123,8 +260,48 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
             pending_changes: self.pending_changes,
             mode: PhantomData,
             write_mode: self.write_mode,
+            next_id: self.next_id,
+            indexes: self.indexes,
+            ids: self.ids,
+            skipped_torn_line: self.skipped_torn_line,
         })
     }
+
+    /// Returns the last line of the changes file skipped while opening,
+    /// as the [Error::CorruptLine] it would have raised.
+    ///
+    /// A crash while appending a change can leave its line incomplete:
+    /// the change is lost and the line is dropped by the compaction.
+    pub fn skipped_torn_line(&self) -> Option<&Error> {
+        self.skipped_torn_line.as_ref()
+    }
+}
+
+// Id based methods, for structs with an `#[airone(id)]` field
+impl<T: Identified, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
+    /// Returns the position of the element with the given id
+    pub fn position_by_id(&self, id: u64) -> Option<usize> {
+        self.ids.get(id)
+    }
+
+    pub fn get_by_id(&self, id: u64) -> Option<&T> {
+        self.position_by_id(id).map(|index| &self.elements[index])
+    }
+
+    /// Mutably access the element
+    /// with the given id
+    pub fn get_mut_by_id(&mut self, id: u64) -> Option<WriteProxy<'_, T, SaveMode>> {
+        self.position_by_id(id)
+            .map(|index| WriteProxy::new(self, index))
+    }
+
+    /// Returns the id that the next added element will get.
+    ///
+    /// Ids start from 1 and are never given again,
+    /// even after the element is removed.
+    pub fn next_id(&self) -> u64 {
+        self.next_id.unwrap_or(1)
+    }
 }
 
 // Common private methods:
@@ -140,13 +317,25 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
 // _set_field()
 // _save()
 impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
-    fn _insert(&mut self, index: usize, element: T) {
+    fn _insert(&mut self, index: usize, mut element: T) -> Result<(), Error> {
+        self.check_unique(&element)?;
+        if let Some(next_id) = self.next_id.as_mut() {
+            element.set_id(*next_id);
+            *next_id += 1;
+        }
         let change = operations::RevertableChange::new_add(index, element.serialize());
-        change.apply_forward(&mut self.elements).unwrap();
+        change
+            .apply_forward(&mut self.elements, &mut self.ids)
+            .unwrap();
+        for field_index in self.indexes.iter_mut() {
+            field_index.shift(index, 1);
+        }
+        self.index_element(index);
         self.pending_changes.push(change);
+        Ok(())
     }
     /// Adds a new element to the end of the list
-    fn _push(&mut self, element: T) {
+    fn _push(&mut self, element: T) -> Result<(), Error> {
         self._insert(self.len(), element)
     }
     fn _pop(&mut self) -> Option<T> {
@@ -156,15 +345,24 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
             None
         }
     }
-    fn _extend(&mut self, iter: impl Iterator<Item = T>) {
+    fn _extend(&mut self, iter: impl Iterator<Item = T>) -> Result<(), Error> {
         for el in iter {
-            self._push(el);
+            self._push(el)?;
         }
+        Ok(())
     }
-    fn _append(&mut self, other: &mut Vec<T>) {
-        for el in other.drain(..) {
-            self._push(el);
+    /// On a unique violation, the remaining elements are left in `other`
+    fn _append(&mut self, other: &mut Vec<T>) -> Result<(), Error> {
+        let mut rest = std::mem::take(other).into_iter();
+        while let Some(el) = rest.next() {
+            if let Err(e) = self.check_unique(&el) {
+                other.push(el);
+                other.extend(rest);
+                return Err(e);
+            }
+            self._push(el)?;
         }
+        Ok(())
     }
 
     fn _dedup(&mut self)
@@ -182,9 +380,17 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
     }
 
     fn _remove(&mut self, index: usize) -> T {
-        let change =
-            operations::RevertableChange::new_delete(index, self.elements[index].serialize());
+        let change = operations::RevertableChange::new_delete(
+            index,
+            self.target(index),
+            self.elements[index].serialize(),
+        );
+        self.unindex_element(index);
+        for field_index in self.indexes.iter_mut() {
+            field_index.shift(index + 1, -1);
+        }
         let element = self.elements.remove(index);
+        self.ids.remove(index, element.id(), self.elements.len());
         self.pending_changes.push(change);
         element
     }
@@ -229,15 +435,111 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
         }
     }
 
-    fn _set<V: SerializableField>(&mut self, index: usize, fieldname: &'static str, value: V) {
-        let change = operations::RevertableChange::new_edit(
-            index,
-            fieldname,
-            self.elements[index].get::<V>(fieldname).serialize_field(),
-            value.serialize_field(),
-        );
-        change.apply_forward(&mut self.elements).unwrap();
-        self.pending_changes.push(change);
+    /// Replaces the element with the given id, logging
+    /// an edit for each changed field, and returns the old one
+    fn _update_by_id(&mut self, id: u64, mut element: T) -> Result<Option<T>, Error>
+    where
+        T: Identified,
+    {
+        let Some(index) = self.position_by_id(id) else {
+            return Ok(None);
+        };
+        element.set_id(id);
+        self.unindex_element(index);
+        if let Err(e) = self.check_unique(&element) {
+            self.index_element(index);
+            return Err(e);
+        }
+        let old_values = self.elements[index].serialize();
+        let old = std::mem::replace(&mut self.elements[index], element);
+        self.log_edits(index, &old_values);
+        self.index_element(index);
+        Ok(Some(old))
+    }
+
+    /// Changes the element in place, logging
+    /// an edit for each changed field.
+    /// The id, if any, is kept.
+    fn _modify(&mut self, index: usize, modify: impl FnOnce(&mut T)) -> Result<(), Error> {
+        let old_values = self.elements[index].serialize();
+        let id = self.elements[index].id();
+        self.unindex_element(index);
+        modify(&mut self.elements[index]);
+        if let Some(id) = id {
+            self.elements[index].set_id(id);
+        }
+        if let Err(e) = self.check_unique(&self.elements[index]) {
+            self.elements[index] = T::deserialize(&old_values).unwrap();
+            self.index_element(index);
+            return Err(e);
+        }
+        self.log_edits(index, &old_values);
+        self.index_element(index);
+        Ok(())
+    }
+
+    /// Logs an edit for each field of the element at `index`
+    /// which differs from `old_values`
+    fn log_edits(&mut self, index: usize, old_values: &SerializedStruct) {
+        let target = self.target(index);
+        let new_values = self.elements[index].serialize();
+        let changed = T::columns()
+            .iter()
+            .zip(old_values.get_values().iter().zip(new_values.get_values()))
+            .filter(|(_, (old, new))| old != new);
+        for (fieldname, (old, new)) in changed {
+            self.pending_changes
+                .push(operations::RevertableChange::new_edit(
+                    target.clone(),
+                    fieldname,
+                    old.clone(),
+                    new.clone(),
+                ));
+        }
+    }
+    fn _remove_by_id(&mut self, id: u64) -> Option<T>
+    where
+        T: Identified,
+    {
+        let index = self.position_by_id(id)?;
+        Some(self._remove(index))
+    }
+
+    /// Fails if `element` has the value of a unique field
+    /// of another element
+    fn check_unique(&self, element: &T) -> Result<(), Error> {
+        self.indexes
+            .iter()
+            .try_for_each(|field_index| field_index.check(element))
+    }
+    fn index_element(&mut self, index: usize) {
+        for field_index in self.indexes.iter_mut() {
+            field_index.add(index, &self.elements[index]);
+        }
+    }
+    fn unindex_element(&mut self, index: usize) {
+        for field_index in self.indexes.iter_mut() {
+            field_index.remove(index, &self.elements[index]);
+        }
+    }
+    fn rebuild_indexes(&mut self) -> Result<(), Error> {
+        self.ids = IdIndex::build(&self.elements);
+        for field_index in self.indexes.iter_mut() {
+            field_index.clear();
+            for (position, element) in self.elements.iter().enumerate() {
+                field_index.check(element)?;
+                field_index.add(position, element);
+            }
+        }
+        Ok(())
+    }
+
+    /// How the log addresses the element at `index`
+    fn target(&self, index: usize) -> operations::Target {
+        match self.elements[index].id() {
+            Some(id) => operations::Target::Id(id),
+            None => operations::Target::Index(index),
+        }
     }
 
     fn _save(&mut self) -> Result<(), Error> {
@@ -246,7 +548,44 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
         }
         if self.write_mode == BufferMode::AlwaysFlush {
             self.buf_writer.flush()?;
+            self.buf_writer.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: NamedFields, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
+    fn _set<V: SerializableField>(
+        &mut self,
+        index: usize,
+        fieldname: &'static str,
+        value: V,
+    ) -> Result<(), Error> {
+        if T::ID_COLUMN == Some(fieldname) {
+            return Err(Error::IdNotWritable {
+                struct_name: T::struct_name().to_string(),
+                column: fieldname.to_string(),
+            });
+        }
+        let change = operations::RevertableChange::new_edit(
+            self.target(index),
+            fieldname,
+            self.elements[index].get::<V>(fieldname).serialize_field(),
+            value.serialize_field(),
+        );
+        self.unindex_element(index);
+        change
+            .apply_forward(&mut self.elements, &mut self.ids)
+            .unwrap();
+        if let Err(e) = self.check_unique(&self.elements[index]) {
+            change
+                .apply_backward(&mut self.elements, &mut self.ids)
+                .unwrap();
+            self.index_element(index);
+            return Err(e);
         }
+        self.index_element(index);
+        self.pending_changes.push(change);
         Ok(())
     }
 }
@@ -271,22 +610,45 @@ impl<T: InnerStruct, SaveMode: SaveModeExt> AironeDb<T, SaveMode> {
 // rollback()
 
 // Autosave methods
+impl<T: Identified> AironeDb<T, AutoSave> {
+    /// Replaces the element with the given id and returns
+    /// the old one, or `None` if no element has this id.
+    ///
+    /// The id of `element` is ignored.
+    pub fn update_by_id(&mut self, id: u64, element: T) -> Result<Option<T>, Error> {
+        let old = self._update_by_id(id, element)?;
+        self._save()?;
+        Ok(old)
+    }
+
+    /// Removes and returns the element with the given id,
+    /// or `None` if no element has this id
+    pub fn remove_by_id(&mut self, id: u64) -> Result<Option<T>, Error> {
+        let el = self._remove_by_id(id);
+        self._save()?;
+        Ok(el)
+    }
+}
 impl<T: InnerStruct> AironeDb<T, AutoSave> {
     /// Inserts an element at position `index` within the vector, shifting all
     /// elements after it to the right.
     ///
+    /// Returns an error if `element` violates a unique index.
+    ///
     /// # Panics
     ///
     /// Panics if `index > len`.
     pub fn insert(&mut self, index: usize, element: T) -> Result<(), Error> {
-        self._insert(index, element);
+        self._insert(index, element)?;
         self._save()?;
         Ok(())
     }
 
     /// Adds a new element to the end of the list
+    ///
+    /// Returns an error if `element` violates a unique index.
     pub fn push(&mut self, element: T) -> Result<(), Error> {
-        self._push(element);
+        self._push(element)?;
         self._save()?;
         Ok(())
     }
@@ -299,10 +661,13 @@ impl<T: InnerStruct> AironeDb<T, AutoSave> {
     }
 
     /// Adds the elements of an iterator to the end of the list.
+    ///
+    /// Stops at the first element violating a unique index
+    /// and returns the error, the previous ones are kept.
     pub fn extend(&mut self, iter: impl Iterator<Item = T>) -> Result<(), Error> {
-        self._extend(iter);
+        let res = self._extend(iter);
         self._save()?;
-        Ok(())
+        res
     }
 
     /// Adds multiple elements to the end of the list,
@@ -311,10 +676,13 @@ impl<T: InnerStruct> AironeDb<T, AutoSave> {
     ///
     /// Works similar to
     /// <https://doc.rust-lang.org/std/vec/struct.Vec.html#method.append>
+    ///
+    /// Stops at the first element violating a unique index
+    /// and returns the error, leaving it and the next ones in `other`.
     pub fn append(&mut self, other: &mut Vec<T>) -> Result<(), Error> {
-        self._append(other);
+        let res = self._append(other);
         self._save()?;
-        Ok(())
+        res
     }
 
     /// Removes consecutive duplicates
@@ -360,32 +728,69 @@ impl<T: InnerStruct> AironeDb<T, AutoSave> {
         self._save()?;
         Ok(())
     }
+    /// Changes the element at `index` through `modify`,
+    /// logging an edit for each changed field.
+    /// The id, if any, can't be changed.
+    ///
+    /// Returns an error, leaving the element unchanged,
+    /// if the new values violate a unique index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`.
+    pub fn modify(&mut self, index: usize, modify: impl FnOnce(&mut T)) -> Result<(), Error> {
+        self._modify(index, modify)?;
+        self._save()?;
+        Ok(())
+    }
+}
+impl<T: NamedFields> AironeDb<T, AutoSave> {
     pub fn set<V: SerializableField>(
         &mut self,
         index: usize,
         fieldname: &'static str,
         value: V,
     ) -> Result<(), Error> {
-        self._set(index, fieldname, value);
+        self._set(index, fieldname, value)?;
         self._save()?;
         Ok(())
     }
 }
 
+impl<T: Identified> AironeDb<T, ManualSave> {
+    /// Replaces the element with the given id and returns
+    /// the old one, or `None` if no element has this id.
+    ///
+    /// The id of `element` is ignored. Returns an error
+    /// if `element` violates a unique index.
+    pub fn update_by_id(&mut self, id: u64, element: T) -> Result<Option<T>, Error> {
+        self._update_by_id(id, element)
+    }
+
+    /// Removes and returns the element with the given id,
+    /// or `None` if no element has this id
+    pub fn remove_by_id(&mut self, id: u64) -> Option<T> {
+        self._remove_by_id(id)
+    }
+}
 impl<T: InnerStruct> AironeDb<T, ManualSave> {
     /// Inserts an element at position `index` within the vector, shifting all
     /// elements after it to the right.
     ///
+    /// Returns an error if `element` violates a unique index.
+    ///
     /// # Panics
     ///
     /// Panics if `index > len`.
-    pub fn insert(&mut self, index: usize, element: T) {
-        self._insert(index, element);
+    pub fn insert(&mut self, index: usize, element: T) -> Result<(), Error> {
+        self._insert(index, element)
     }
 
     /// Adds a new element to the end of the list
-    pub fn push(&mut self, element: T) {
-        self._push(element);
+    ///
+    /// Returns an error if `element` violates a unique index.
+    pub fn push(&mut self, element: T) -> Result<(), Error> {
+        self._push(element)
     }
 
     /// Removes the last element of the list and returns it if existing
@@ -394,8 +799,11 @@ impl<T: InnerStruct> AironeDb<T, ManualSave> {
     }
 
     /// Adds the elements of an iterator to the end of the list.
-    pub fn extend(&mut self, iter: impl Iterator<Item = T>) {
-        self._extend(iter);
+    ///
+    /// Stops at the first element violating a unique index
+    /// and returns the error, the previous ones are kept.
+    pub fn extend(&mut self, iter: impl Iterator<Item = T>) -> Result<(), Error> {
+        self._extend(iter)
     }
 
     /// Adds multiple elements to the end of the list,
@@ -404,8 +812,11 @@ impl<T: InnerStruct> AironeDb<T, ManualSave> {
     ///
     /// Works similar to
     /// <https://doc.rust-lang.org/std/vec/struct.Vec.html#method.append>
-    pub fn append(&mut self, other: &mut Vec<T>) {
-        self._append(other);
+    ///
+    /// Stops at the first element violating a unique index
+    /// and returns the error, leaving it and the next ones in `other`.
+    pub fn append(&mut self, other: &mut Vec<T>) -> Result<(), Error> {
+        self._append(other)
     }
 
     /// Removes consecutive duplicates
@@ -438,8 +849,18 @@ impl<T: InnerStruct> AironeDb<T, ManualSave> {
     pub fn clear(&mut self) {
         self._clear();
     }
-    pub fn set<V: SerializableField>(&mut self, index: usize, fieldname: &'static str, value: V) {
-        self._set(index, fieldname, value);
+    /// Changes the element at `index` through `modify`,
+    /// logging an edit for each changed field.
+    /// The id, if any, can't be changed.
+    ///
+    /// Returns an error, leaving the element unchanged,
+    /// if the new values violate a unique index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`.
+    pub fn modify(&mut self, index: usize, modify: impl FnOnce(&mut T)) -> Result<(), Error> {
+        self._modify(index, modify)
     }
 
     /// Saves to file all in-memory transactions, to persist the current db state.
@@ -451,11 +872,27 @@ impl<T: InnerStruct> AironeDb<T, ManualSave> {
     /// known state which was persisted to disk
     pub fn rollback(&mut self) {
         for change in self.pending_changes.drain(..).rev() {
-            change.apply_backward(&mut self.elements).expect(
-                "We're deserializing data that was just serialized in-memory only,
+            change
+                .apply_backward(&mut self.elements, &mut self.ids)
+                .expect(
+                    "We're deserializing data that was just serialized in-memory only,
                 this should not crash",
-            );
+                );
         }
+        self.rebuild_indexes()
+            .expect("The persisted state respected the unique indexes");
+    }
+}
+
+impl<T: NamedFields> AironeDb<T, ManualSave> {
+    /// Returns an error if the new value violates a unique index
+    pub fn set<V: SerializableField>(
+        &mut self,
+        index: usize,
+        fieldname: &'static str,
+        value: V,
+    ) -> Result<(), Error> {
+        self._set(index, fieldname, value)
     }
 }
 
@@ -21,10 +21,12 @@
 //  along with this program. If not, see <https://www.gnu.org/licenses/>.
 //  ------------------------------------------------------------------
 
+use super::checksum;
+use super::index::IdIndex;
 use super::InnerStruct;
 use super::{SerializedFieldValue, SerializedStruct};
-use crate::error::Error;
-use crate::serde::Deserialize;
+use crate::error::{Error, ParseError};
+use crate::serde::{Deserialize, FormatVersion};
 use std::io::{self, Write};
 
 #[derive(Debug)]
@@ -36,38 +38,84 @@ impl RevertableChange {
     pub fn new_add(index: usize, element: SerializedStruct) -> Self {
         RevertableChange {
             forward_op: Operation::new_insert(index, element),
-            backward_op: Operation::new_delete(index),
+            backward_op: Operation::new_delete(Target::Index(index)),
         }
     }
     pub fn new_edit(
-        index: usize,
+        target: Target,
         fieldname: &str,
         old_value: SerializedFieldValue,
         new_value: SerializedFieldValue,
     ) -> Self {
         RevertableChange {
-            forward_op: Operation::new_edit(index, fieldname.to_string(), new_value),
-            backward_op: Operation::new_edit(index, fieldname.to_string(), old_value),
+            forward_op: Operation::new_edit(target.clone(), fieldname.to_string(), new_value),
+            backward_op: Operation::new_edit(target, fieldname.to_string(), old_value),
         }
     }
-    pub fn new_delete(index: usize, old_element: SerializedStruct) -> Self {
+    /// `index` is the current position of the element,
+    /// where it's inserted back on rollback
+    pub fn new_delete(index: usize, target: Target, old_element: SerializedStruct) -> Self {
         RevertableChange {
-            forward_op: Operation::new_delete(index),
+            forward_op: Operation::new_delete(target),
             backward_op: Operation::new_insert(index, old_element),
         }
     }
 
-    pub fn apply_forward<T: InnerStruct>(&self, data: &mut Vec<T>) -> Result<(), Error> {
-        self.forward_op.apply(data)
+    pub fn apply_forward<T: InnerStruct>(
+        &self,
+        data: &mut Vec<T>,
+        ids: &mut IdIndex,
+    ) -> Result<(), Error> {
+        self.forward_op.apply(data, ids)
     }
-    pub fn apply_backward<T: InnerStruct>(self, data: &mut Vec<T>) -> Result<(), Error> {
-        self.backward_op.apply(data)
+    pub fn apply_backward<T: InnerStruct>(
+        self,
+        data: &mut Vec<T>,
+        ids: &mut IdIndex,
+    ) -> Result<(), Error> {
+        self.backward_op.apply(data, ids)
     }
     pub fn persist(self, w: &mut impl Write) -> Result<(), io::Error> {
         self.forward_op.persist(w)
     }
 }
 
+/// The element changed by an operation
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    /// Position in the list
+    Index(usize),
+    /// Value of the `#[airone(id)]` field, written as `#<id>`
+    Id(u64),
+}
+impl Target {
+    fn parse(s: &str) -> Result<Self, Error> {
+        match s.strip_prefix('#') {
+            Some(id) => Ok(Self::Id(id.parse()?)),
+            None => Ok(Self::Index(s.parse()?)),
+        }
+    }
+
+    /// Current position of the element
+    fn position<T: InnerStruct>(&self, data: &[T], ids: &IdIndex) -> Result<usize, Error> {
+        match *self {
+            Self::Index(index) => {
+                check_index(index, data.len(), false)?;
+                Ok(index)
+            }
+            Self::Id(id) => ids.get(id).ok_or(Error::UnknownId { id }),
+        }
+    }
+}
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{index}"),
+            Self::Id(id) => write!(f, "#{id}"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Operation {
     Add {
@@ -75,12 +123,12 @@ pub enum Operation {
         serialized_object: SerializedStruct,
     },
     Edit {
-        index: usize,
+        target: Target,
         fieldname: String,
         serialized_new_value: SerializedFieldValue,
     },
     Delete {
-        index: usize,
+        target: Target,
     },
 }
 impl Operation {
@@ -90,15 +138,15 @@ impl Operation {
             serialized_object: object,
         }
     }
-    pub fn new_edit(index: usize, fieldname: String, new_value: SerializedFieldValue) -> Self {
+    pub fn new_edit(target: Target, fieldname: String, new_value: SerializedFieldValue) -> Self {
         Self::Edit {
-            index,
+            target,
             fieldname,
             serialized_new_value: new_value,
         }
     }
-    pub fn new_delete(index: usize) -> Self {
-        Self::Delete { index }
+    pub fn new_delete(target: Target) -> Self {
+        Self::Delete { target }
     }
 
     fn to_line(&self) -> String {
@@ -110,71 +158,116 @@ impl Operation {
                 format!("A\t{index}\t{}", serialized_object.to_escaped_line())
             }
             Self::Edit {
-                index,
+                target,
                 fieldname,
                 serialized_new_value,
             } => {
                 format!(
-                    "E\t{index}\t{fieldname}\t{}",
+                    "E\t{target}\t{fieldname}\t{}",
                     serialized_new_value.to_escaped_string()
                 )
             }
-            Self::Delete { index } => {
-                format!("D\t{index}")
+            Self::Delete { target } => {
+                format!("D\t{target}")
             }
         }
     }
 
-    pub fn from_line(line: &str) -> Result<Self, crate::error::Error> {
+    pub fn from_line(line: &str, version: FormatVersion) -> Result<Self, crate::error::Error> {
         let mut parts_iter = line.split('\t');
-        let tipo_op = parts_iter.next().unwrap();
-        if tipo_op == "A" {
+        let tipo_op = parts_iter.next().unwrap_or_default();
+        let op = if tipo_op == "A" {
             // Aggiungi un elemento
-            let index: usize = parts_iter.next().unwrap().parse().unwrap();
-            let obj_string = parts_iter.collect::<Vec<&str>>().join("\t");
-            Ok(Operation::Add {
+            let index: usize = next_part(&mut parts_iter, "index")?.parse()?;
+            let obj_string = parts_iter.by_ref().collect::<Vec<&str>>().join("\t");
+            Operation::Add {
                 index,
-                serialized_object: SerializedStruct::from_escaped_line(&obj_string),
-            })
+                serialized_object: SerializedStruct::from_escaped_line(&obj_string, version)?,
+            }
         } else if tipo_op == "D" {
-            let index: usize = parts_iter.next().unwrap().parse().unwrap();
-            Ok(Operation::Delete { index })
+            let target = Target::parse(next_part(&mut parts_iter, "index or id")?)?;
+            Operation::Delete { target }
         } else if tipo_op == "E" {
             // formato:
             // E indice campo valore
-            let index: usize = parts_iter.next().unwrap().parse().unwrap();
-            let field: String = parts_iter.next().unwrap().to_string();
-            let value_str: String = parts_iter.next().unwrap().to_string();
+            let target = Target::parse(next_part(&mut parts_iter, "index or id")?)?;
+            let field: String = next_part(&mut parts_iter, "field name")?.to_string();
+            let value_str: &str = next_part(&mut parts_iter, "field value")?;
 
-            Ok(Operation::Edit {
-                index,
+            Operation::Edit {
+                target,
                 fieldname: field,
-                serialized_new_value: SerializedFieldValue::from_escaped_string(&value_str),
-            })
+                serialized_new_value: SerializedFieldValue::from_escaped_string(
+                    value_str, version,
+                )?,
+            }
         } else {
-            panic!("Parse line invalid, wrong operation {}", tipo_op)
+            return Err(Error::ParseError(ParseError::new(
+                "operation `A`, `E` or `D`".to_string(),
+                tipo_op.to_string(),
+            )));
+        };
+        if let Some(extra) = parts_iter.next() {
+            return Err(Error::ParseError(ParseError::new(
+                "end of line".to_string(),
+                extra.to_string(),
+            )));
         }
+        Ok(op)
     }
 
-    pub fn apply<T: InnerStruct>(&self, data: &mut Vec<T>) -> Result<(), Error> {
+    /// Applies the operation to `data`, keeping `ids` in sync
+    pub fn apply<T: InnerStruct>(&self, data: &mut Vec<T>, ids: &mut IdIndex) -> Result<(), Error> {
         match self.clone() {
             Self::Add {
                 index,
                 serialized_object,
-            } => data.insert(index, Deserialize::deserialize(&serialized_object)?),
+            } => {
+                check_index(index, data.len(), true)?;
+                data.insert(index, Deserialize::deserialize(&serialized_object)?);
+                ids.insert(data, index);
+            }
             Self::Edit {
-                index,
+                target,
                 fieldname,
                 serialized_new_value,
-            } => data[index].set_str(&fieldname, serialized_new_value)?,
-            Self::Delete { index } => {
-                data.remove(index);
+            } => {
+                let index = target.position(data, ids)?;
+                let old_id = data[index].id();
+                data[index].set_str(&fieldname, serialized_new_value)?;
+                ids.change_id(index, old_id, data[index].id());
+            }
+            Self::Delete { target } => {
+                let index = target.position(data, ids)?;
+                let removed = data.remove(index);
+                ids.remove(index, removed.id(), data.len());
             }
         };
         Ok(())
     }
 
     pub fn persist<W: Write>(self, w: &mut W) -> Result<(), io::Error> {
-        writeln!(w, "{}", self.to_line())
+        writeln!(w, "{}", checksum::append(&self.to_line()))
+    }
+}
+
+fn next_part<'a>(
+    parts_iter: &mut impl Iterator<Item = &'a str>,
+    expectation: &str,
+) -> Result<&'a str, Error> {
+    parts_iter.next().ok_or_else(|| {
+        Error::ParseError(ParseError::new(
+            expectation.to_string(),
+            "end of line".to_string(),
+        ))
+    })
+}
+
+/// Inserting accepts the length of the list as index too
+fn check_index(index: usize, len: usize, inserting: bool) -> Result<(), Error> {
+    if index < len || (inserting && index == len) {
+        Ok(())
+    } else {
+        Err(Error::IndexOutOfRange { index, len })
     }
 }
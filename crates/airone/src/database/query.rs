@@ -0,0 +1,399 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use std::any::Any;
+use std::cmp::Ordering;
+use std::ops::{Bound, Not};
+
+use super::settings::save_mode::{AutoSave, ManualSave, SaveModeExt};
+use super::{AironeDb, InnerStruct, WriteProxy};
+use crate::error::Error;
+
+/// A typed field of `T`, generated by `AironeDbDerive`
+/// as `Struct::field()`, to build predicates and orderings
+pub struct Field<T, V> {
+    column: &'static str,
+    get: fn(&T) -> &V,
+}
+impl<T, V> Clone for Field<T, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T, V> Copy for Field<T, V> {}
+impl<T: 'static, V: 'static> Field<T, V> {
+    #[doc(hidden)]
+    pub const fn new(column: &'static str, get: fn(&T) -> &V) -> Self {
+        Self { column, get }
+    }
+
+    /// Name of the column of the field
+    pub fn column(&self) -> &'static str {
+        self.column
+    }
+
+    /// Value of the field in `element`
+    pub fn get<'a>(&self, element: &'a T) -> &'a V {
+        (self.get)(element)
+    }
+
+    /// The field is equal to `value`
+    pub fn eq(self, value: impl Into<V>) -> Predicate<T>
+    where
+        V: PartialEq + Clone,
+    {
+        let value = value.into();
+        self.compare(
+            Bound::Included(value.clone()),
+            Bound::Included(value.clone()),
+            move |v| *v == value,
+        )
+    }
+
+    /// The field is different from `value`
+    pub fn ne(self, value: impl Into<V>) -> Predicate<T>
+    where
+        V: PartialEq,
+    {
+        let value = value.into();
+        self.matches(move |v| *v != value)
+    }
+
+    /// The field is greater than `value`
+    pub fn gt(self, value: impl Into<V>) -> Predicate<T>
+    where
+        V: PartialOrd + Clone,
+    {
+        let value = value.into();
+        self.compare(Bound::Excluded(value.clone()), Bound::Unbounded, move |v| {
+            *v > value
+        })
+    }
+
+    /// The field is greater than or equal to `value`
+    pub fn ge(self, value: impl Into<V>) -> Predicate<T>
+    where
+        V: PartialOrd + Clone,
+    {
+        let value = value.into();
+        self.compare(Bound::Included(value.clone()), Bound::Unbounded, move |v| {
+            *v >= value
+        })
+    }
+
+    /// The field is less than `value`
+    pub fn lt(self, value: impl Into<V>) -> Predicate<T>
+    where
+        V: PartialOrd + Clone,
+    {
+        let value = value.into();
+        self.compare(Bound::Unbounded, Bound::Excluded(value.clone()), move |v| {
+            *v < value
+        })
+    }
+
+    /// The field is less than or equal to `value`
+    pub fn le(self, value: impl Into<V>) -> Predicate<T>
+    where
+        V: PartialOrd + Clone,
+    {
+        let value = value.into();
+        self.compare(Bound::Unbounded, Bound::Included(value.clone()), move |v| {
+            *v <= value
+        })
+    }
+
+    /// The field satisfies `test`.
+    ///
+    /// Indexes can't speed up this predicate.
+    pub fn matches(self, test: impl Fn(&V) -> bool + 'static) -> Predicate<T> {
+        Predicate::new(move |element| test(self.get(element)))
+    }
+
+    fn compare(
+        self,
+        lower: Bound<V>,
+        upper: Bound<V>,
+        test: impl Fn(&V) -> bool + 'static,
+    ) -> Predicate<T> {
+        let boxed = |bound: Bound<V>| bound.map(|v| Box::new(v) as Box<dyn Any>);
+        Predicate {
+            range: Some(IndexRange {
+                column: self.column,
+                lower: boxed(lower),
+                upper: boxed(upper),
+            }),
+            ..self.matches(test)
+        }
+    }
+}
+
+/// A condition on the elements of a query
+pub struct Predicate<T> {
+    test: Box<dyn Fn(&T) -> bool>,
+    /// Bounds of a field, to take the candidates
+    /// from its index instead of scanning the list
+    range: Option<IndexRange>,
+}
+struct IndexRange {
+    column: &'static str,
+    lower: Bound<Box<dyn Any>>,
+    upper: Bound<Box<dyn Any>>,
+}
+impl<T: 'static> Predicate<T> {
+    /// A predicate from any function.
+    ///
+    /// Indexes can't speed up this predicate.
+    pub fn new(test: impl Fn(&T) -> bool + 'static) -> Self {
+        Self {
+            test: Box::new(test),
+            range: None,
+        }
+    }
+
+    /// Both predicates hold
+    pub fn and(self, other: Predicate<T>) -> Self {
+        let (test, other_test) = (self.test, other.test);
+        Self {
+            test: Box::new(move |element| test(element) && other_test(element)),
+            range: self.range.or(other.range),
+        }
+    }
+
+    /// At least one of the predicates holds
+    pub fn or(self, other: Predicate<T>) -> Self {
+        let (test, other_test) = (self.test, other.test);
+        Self::new(move |element| test(element) || other_test(element))
+    }
+
+    /// Checks the predicate on `element`
+    pub fn test(&self, element: &T) -> bool {
+        (self.test)(element)
+    }
+}
+impl<T: 'static> Not for Predicate<T> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        let test = self.test;
+        Self::new(move |element| !test(element))
+    }
+}
+
+type Comparator<T> = Box<dyn Fn(&T, &T) -> Ordering>;
+
+/// A query on the elements of an [AironeDb],
+/// built by [AironeDb::query] or [AironeDb::query_mut].
+///
+/// Predicates on an indexed field take their
+/// candidates from the index instead of scanning the list.
+pub struct Query<T, Db> {
+    db: Db,
+    filters: Vec<Predicate<T>>,
+    order: Vec<Comparator<T>>,
+    offset: usize,
+    limit: Option<usize>,
+}
+impl<T: 'static, Db> Query<T, Db> {
+    pub(super) fn new(db: Db) -> Self {
+        Self {
+            db,
+            filters: Vec::new(),
+            order: Vec::new(),
+            offset: 0,
+            limit: None,
+        }
+    }
+
+    /// Keeps only the elements satisfying `predicate`,
+    /// together with the previous filters
+    pub fn filter(mut self, predicate: Predicate<T>) -> Self {
+        self.filters.push(predicate);
+        self
+    }
+
+    /// Sorts the results by `field`, ascending.
+    ///
+    /// Further orderings break the ties of the previous ones,
+    /// and elements still equal keep their order in the list.
+    pub fn order_by<V: PartialOrd + 'static>(mut self, field: Field<T, V>) -> Self {
+        self.order.push(Box::new(move |a, b| {
+            field
+                .get(a)
+                .partial_cmp(field.get(b))
+                .unwrap_or(Ordering::Equal)
+        }));
+        self
+    }
+
+    /// Sorts the results by `field`, descending
+    pub fn order_by_desc<V: PartialOrd + 'static>(mut self, field: Field<T, V>) -> Self {
+        self.order.push(Box::new(move |a, b| {
+            field
+                .get(b)
+                .partial_cmp(field.get(a))
+                .unwrap_or(Ordering::Equal)
+        }));
+        self
+    }
+
+    /// Skips the first `offset` results
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Returns at most `limit` results
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+impl<T, Db, SaveMode> Query<T, Db>
+where
+    T: InnerStruct + 'static,
+    SaveMode: SaveModeExt,
+    Db: std::ops::Deref<Target = AironeDb<T, SaveMode>>,
+{
+    /// Positions of the results, in the order of the query
+    pub fn positions(&self) -> Vec<usize> {
+        let elements = &self.db.elements;
+        let candidates = self
+            .filters
+            .iter()
+            .filter_map(|predicate| predicate.range.as_ref())
+            .find_map(|range| {
+                self.db
+                    .indexes
+                    .iter()
+                    .find(|index| index.column() == range.column)?
+                    .positions_in(
+                        range.lower.as_ref().map(|v| v.as_ref()),
+                        range.upper.as_ref().map(|v| v.as_ref()),
+                    )
+            })
+            .unwrap_or_else(|| (0..elements.len()).collect());
+
+        let mut positions: Vec<usize> = candidates
+            .into_iter()
+            .filter(|&i| self.filters.iter().all(|p| p.test(&elements[i])))
+            .collect();
+        if !self.order.is_empty() {
+            positions.sort_by(|&a, &b| {
+                self.order
+                    .iter()
+                    .map(|compare| compare(&elements[a], &elements[b]))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        positions
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Number of results
+    pub fn count(&self) -> usize {
+        self.positions().len()
+    }
+}
+impl<'a, T, SaveMode> Query<T, &'a AironeDb<T, SaveMode>>
+where
+    T: InnerStruct + 'static,
+    SaveMode: SaveModeExt,
+{
+    /// The results, borrowed from the database
+    pub fn all(self) -> Vec<&'a T> {
+        let db = self.db;
+        self.positions()
+            .into_iter()
+            .map(|i| &db.elements[i])
+            .collect()
+    }
+
+    /// The first result
+    pub fn first(self) -> Option<&'a T> {
+        let db = self.db;
+        self.limit(1).positions().first().map(|&i| &db.elements[i])
+    }
+}
+impl<T: InnerStruct + 'static> Query<T, &mut AironeDb<T, AutoSave>> {
+    /// Calls `update` on each result, whose changes
+    /// are logged as ordinary edits, and returns
+    /// the number of updated elements.
+    ///
+    /// Stops at the first error.
+    pub fn update(
+        self,
+        mut update: impl FnMut(&mut WriteProxy<T, AutoSave>) -> Result<(), Error>,
+    ) -> Result<usize, Error> {
+        let positions = self.positions();
+        for &i in positions.iter() {
+            update(&mut WriteProxy::new(self.db, i))?;
+        }
+        Ok(positions.len())
+    }
+
+    /// Removes and returns the results, in list order.
+    /// Each removal is logged as an ordinary delete.
+    pub fn delete(self) -> Result<Vec<T>, Error> {
+        let removed = remove_positions(self.db, self.positions());
+        self.db._save()?;
+        Ok(removed)
+    }
+}
+impl<T: InnerStruct + 'static> Query<T, &mut AironeDb<T, ManualSave>> {
+    /// Calls `update` on each result, whose changes
+    /// are logged as ordinary edits, and returns
+    /// the number of updated elements.
+    ///
+    /// Stops at the first error.
+    pub fn update(
+        self,
+        mut update: impl FnMut(&mut WriteProxy<T, ManualSave>) -> Result<(), Error>,
+    ) -> Result<usize, Error> {
+        let positions = self.positions();
+        for &i in positions.iter() {
+            update(&mut WriteProxy::new(self.db, i))?;
+        }
+        Ok(positions.len())
+    }
+
+    /// Removes and returns the results, in list order.
+    /// Each removal is logged as an ordinary delete.
+    pub fn delete(self) -> Vec<T> {
+        remove_positions(self.db, self.positions())
+    }
+}
+
+fn remove_positions<T: InnerStruct, SaveMode: SaveModeExt>(
+    db: &mut AironeDb<T, SaveMode>,
+    mut positions: Vec<usize>,
+) -> Vec<T> {
+    positions.sort_unstable();
+    let mut removed: Vec<T> = positions.into_iter().rev().map(|i| db._remove(i)).collect();
+    removed.reverse();
+    removed
+}
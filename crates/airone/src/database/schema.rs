@@ -0,0 +1,158 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use super::operations::Operation;
+use super::InnerStruct;
+use crate::error::{Error, ParseError};
+use crate::serde::{SerializedFieldValue, SerializedStruct};
+
+/// Maps the columns of a file, possibly written by an
+/// older version of the struct, to the current fields.
+///
+/// Columns are matched by name, then by
+/// [InnerStruct::RENAMED_COLUMNS]. Columns listed in
+/// [InnerStruct::DROPPED_COLUMNS] are ignored and fields
+/// missing in the file get [InnerStruct::default_column].
+#[derive(Debug)]
+pub(crate) struct ColumnMapping {
+    /// For each column of the file, the index of its field,
+    /// or `None` if the column is dropped
+    fields: Vec<Option<usize>>,
+    /// For each field, the value to use
+    /// when the file doesn't have it
+    defaults: Vec<Option<SerializedFieldValue>>,
+    /// The file has the same columns of the struct
+    identity: bool,
+}
+impl ColumnMapping {
+    pub(crate) fn identity<T: InnerStruct>() -> Self {
+        Self {
+            fields: (0..T::columns().len()).map(Some).collect(),
+            defaults: vec![None; T::columns().len()],
+            identity: true,
+        }
+    }
+
+    pub(crate) fn from_header<T: InnerStruct>(header_line: &str) -> Result<Self, Error> {
+        let found: Vec<&str> = header_line.split('\t').collect();
+        if found == T::columns() {
+            return Ok(Self::identity::<T>());
+        }
+        let mismatch = || Error::SchemaMismatch {
+            struct_name: T::struct_name().to_string(),
+            expected: T::columns().iter().map(|c| c.to_string()).collect(),
+            found: found.iter().map(|c| c.to_string()).collect(),
+        };
+
+        let mut present = vec![false; T::columns().len()];
+        let mut fields = Vec::with_capacity(found.len());
+        for column in found.iter() {
+            let field = field_index::<T>(column);
+            match field {
+                // Both the old and the new name are in the file
+                Some(i) if present[i] => return Err(mismatch()),
+                Some(i) => present[i] = true,
+                None if T::DROPPED_COLUMNS.contains(column) => {}
+                None => return Err(mismatch()),
+            }
+            fields.push(field);
+        }
+
+        let mut defaults = vec![None; T::columns().len()];
+        for (i, column) in T::columns().iter().enumerate() {
+            if !present[i] {
+                defaults[i] = Some(T::default_column(column).ok_or_else(mismatch)?);
+            }
+        }
+        Ok(Self {
+            fields,
+            defaults,
+            identity: false,
+        })
+    }
+
+    /// Orders the values of a line of the file as the struct fields
+    pub(crate) fn map_struct(&self, s: SerializedStruct) -> Result<SerializedStruct, Error> {
+        if self.identity {
+            return Ok(s);
+        }
+        let values = s.get_values();
+        if values.len() != self.fields.len() {
+            return Err(Error::ParseError(ParseError::new(
+                format!("{} values", self.fields.len()),
+                format!("{} values", values.len()),
+            )));
+        }
+        let mut mapped = self.defaults.clone();
+        for (value, field) in values.iter().zip(self.fields.iter()) {
+            if let Some(i) = field {
+                mapped[*i] = Some(value.clone());
+            }
+        }
+        Ok(SerializedStruct::new(
+            mapped.into_iter().flatten().collect(),
+        ))
+    }
+
+    /// Maps an operation of the changes file,
+    /// `None` if it edits a dropped column
+    pub(crate) fn map_operation<T: InnerStruct>(
+        &self,
+        op: Operation,
+    ) -> Result<Option<Operation>, Error> {
+        if self.identity {
+            return Ok(Some(op));
+        }
+        let op = match op {
+            Operation::Add {
+                index,
+                serialized_object,
+            } => Operation::new_insert(index, self.map_struct(serialized_object)?),
+            Operation::Edit {
+                target,
+                fieldname,
+                serialized_new_value,
+            } => match field_index::<T>(&fieldname) {
+                Some(i) => {
+                    Operation::new_edit(target, T::columns()[i].to_string(), serialized_new_value)
+                }
+                None if T::DROPPED_COLUMNS.contains(&fieldname.as_str()) => return Ok(None),
+                // Reported when applied
+                None => Operation::new_edit(target, fieldname, serialized_new_value),
+            },
+            Operation::Delete { target } => Operation::new_delete(target),
+        };
+        Ok(Some(op))
+    }
+}
+
+/// Index of the field stored in `column`
+fn field_index<T: InnerStruct>(column: &str) -> Option<usize> {
+    let position = |name: &str| T::columns().iter().position(|c| *c == name);
+    position(column).or_else(|| {
+        T::RENAMED_COLUMNS
+            .iter()
+            .find(|(old, _)| *old == column)
+            .and_then(|(_, new)| position(new))
+    })
+}
@@ -21,6 +21,11 @@
 //  along with this program. If not, see <https://www.gnu.org/licenses/>.
 //  ------------------------------------------------------------------
 
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use save_mode::SaveModeExt;
+
 pub mod save_mode {
     /// A trait that represents all available save modes
     pub trait SaveModeExt {}
@@ -37,16 +42,107 @@ pub mod save_mode {
     impl SaveModeExt for ManualSave {}
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum BufferMode {
     /// When saving, uses a buffered reader to optimize writing speed
     ///
     /// This is useful for bulk modifications.
     Buffered,
-    /// When saving, always flushes the write buffer to disk
+    /// When saving, always flushes the write buffer and syncs the file to disk
     ///
     /// This is useful to ensure small saves have been written to disk.
     /// Good for small changes, but degrades performance when
     /// writing a lot of data modifications in a very short time
     AlwaysFlush,
 }
+
+/// Where and how to open an [AironeDb](super::AironeDb),
+/// see [AironeDb::open](super::AironeDb::open)
+///
+/// By default the files are `<StructName>.csv` and
+/// `<StructName>.changes.csv` in the current directory,
+/// in [Buffered](BufferMode::Buffered) mode.
+#[derive(Debug)]
+pub struct OpenOptions<SaveMode = save_mode::AutoSave> {
+    directory: PathBuf,
+    name: Option<String>,
+    extension: String,
+    buffer_mode: BufferMode,
+    mode: PhantomData<SaveMode>,
+}
+impl OpenOptions {
+    /// Options in [AutoSave](save_mode::AutoSave) mode,
+    /// see [Self::save_mode] to change it
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl<SaveMode: SaveModeExt> OpenOptions<SaveMode> {
+    /// Directory of the files, which must exist
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = directory.into();
+        self
+    }
+
+    /// Name of the files, instead of the struct name
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Extension of the files, without the dot
+    pub fn extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = extension.into();
+        self
+    }
+
+    pub fn buffer_mode(mut self, buffer_mode: BufferMode) -> Self {
+        self.buffer_mode = buffer_mode;
+        self
+    }
+
+    /// Opens the database in another save mode
+    pub fn save_mode<NewSaveMode: SaveModeExt>(self) -> OpenOptions<NewSaveMode> {
+        OpenOptions {
+            directory: self.directory,
+            name: self.name,
+            extension: self.extension,
+            buffer_mode: self.buffer_mode,
+            mode: PhantomData,
+        }
+    }
+
+    pub(super) fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+    pub(super) fn get_buffer_mode(&self) -> BufferMode {
+        self.buffer_mode
+    }
+
+    /// Paths of the base file and of the changes file
+    pub(super) fn paths(&self, struct_name: &str) -> (PathBuf, PathBuf) {
+        let name = self.name.as_deref().unwrap_or(struct_name);
+        (
+            self.directory.join(format!("{}.{}", name, self.extension)),
+            self.directory
+                .join(format!("{}.changes.{}", name, self.extension)),
+        )
+    }
+}
+impl<SaveMode: SaveModeExt> Default for OpenOptions<SaveMode> {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::new(),
+            name: None,
+            extension: "csv".to_string(),
+            buffer_mode: BufferMode::Buffered,
+            mode: PhantomData,
+        }
+    }
+}
+/// Opens the files in the given directory
+impl<SaveMode: SaveModeExt, P: AsRef<Path>> From<P> for OpenOptions<SaveMode> {
+    fn from(directory: P) -> Self {
+        Self::default().directory(directory.as_ref())
+    }
+}
@@ -27,6 +27,7 @@ use super::settings;
 use super::settings::save_mode::*;
 use super::AironeDb;
 use super::InnerStruct;
+use super::NamedFields;
 use super::SerializableField;
 
 // WriteProxy
@@ -49,7 +50,13 @@ where
     pub(super) fn new(db: &'a mut AironeDb<T, SaveMode>, index: usize) -> Self {
         WriteProxy { db, index }
     }
+}
 
+impl<'a, T, SaveMode> WriteProxy<'a, T, SaveMode>
+where
+    T: NamedFields,
+    SaveMode: settings::save_mode::SaveModeExt,
+{
     #[doc(hidden)]
     pub fn get<V: SerializableField>(&self, fieldname: &'static str) -> &V {
         // use crate::serde::InnerStruct;
@@ -59,7 +66,7 @@ where
 
 impl<'a, T> WriteProxy<'a, T, AutoSave>
 where
-    T: InnerStruct,
+    T: NamedFields,
 {
     #[doc(hidden)]
     pub fn set<V: SerializableField>(
@@ -74,10 +81,14 @@ where
 
 impl<'a, T> WriteProxy<'a, T, ManualSave>
 where
-    T: InnerStruct,
+    T: NamedFields,
 {
     #[doc(hidden)]
-    pub fn set<V: SerializableField>(&mut self, fieldname: &'static str, value: V) {
-        self.db.set(self.index, fieldname, value);
+    pub fn set<V: SerializableField>(
+        &mut self,
+        fieldname: &'static str,
+        value: V,
+    ) -> Result<(), Error> {
+        self.db.set(self.index, fieldname, value)
     }
 }
@@ -23,6 +23,7 @@
 
 use std::{
     num::{ParseFloatError, ParseIntError},
+    path::PathBuf,
     str::ParseBoolError,
 };
 
@@ -30,6 +31,57 @@ use std::{
 pub enum Error {
     IoError(std::io::Error),
     ParseError(ParseError),
+    /// A line of a data file can't be loaded.
+    /// `line` starts from 1 and `source` tells why.
+    CorruptLine {
+        path: PathBuf,
+        line: usize,
+        source: Box<Error>,
+    },
+    /// The columns found in a data file
+    /// don't match the fields of the struct
+    SchemaMismatch {
+        struct_name: String,
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    /// An operation addresses an element
+    /// out of the list
+    IndexOutOfRange {
+        index: usize,
+        len: usize,
+    },
+    /// No element has this id
+    UnknownId {
+        id: u64,
+    },
+    /// More elements have the same id
+    DuplicateId {
+        id: u64,
+    },
+    /// The data directory doesn't exist
+    /// or isn't a directory
+    DirectoryNotFound {
+        path: PathBuf,
+    },
+    /// Another element has the same value
+    /// of a `#[airone(unique)]` field
+    UniqueViolation {
+        struct_name: String,
+        column: String,
+        value: String,
+    },
+    /// The `#[airone(id)]` field can't be set,
+    /// as ids are assigned by the database
+    IdNotWritable {
+        struct_name: String,
+        column: String,
+    },
+    /// The type can't be stored, as it isn't
+    /// a struct with named fields
+    UnsupportedShape {
+        type_name: String,
+    },
 }
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
@@ -60,7 +112,15 @@ impl From<ParseFloatError> for Error {
         })
     }
 }
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IoError(io_error) => Some(io_error),
+            Self::CorruptLine { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -70,6 +130,56 @@ impl std::fmt::Display for Error {
                 "parse error. Expected: \"{}\", found:\"{}\"",
                 e.expectation, e.found
             ),
+            Self::CorruptLine { path, line, source } => write!(
+                f,
+                "corrupt line {} of \"{}\": {}",
+                line,
+                path.display(),
+                source
+            ),
+            Self::SchemaMismatch {
+                struct_name,
+                expected,
+                found,
+            } => writeln!(
+                f,
+                "schema mismatch for \"{}\". Expected columns: {:?}, found: {:?}",
+                struct_name, expected, found
+            ),
+            Self::IndexOutOfRange { index, len } => writeln!(
+                f,
+                "index {} is out of range for a list of {} elements",
+                index, len
+            ),
+            Self::UnknownId { id } => writeln!(f, "no element has id {}", id),
+            Self::DuplicateId { id } => writeln!(f, "more elements have id {}", id),
+            Self::DirectoryNotFound { path } => writeln!(
+                f,
+                "data directory \"{}\" doesn't exist or isn't a directory",
+                path.display()
+            ),
+            Self::UniqueViolation {
+                struct_name,
+                column,
+                value,
+            } => writeln!(
+                f,
+                "another \"{}\" has `{}` equal to \"{}\"",
+                struct_name, column, value
+            ),
+            Self::IdNotWritable {
+                struct_name,
+                column,
+            } => writeln!(
+                f,
+                "`{}` of \"{}\" is assigned by the database and can't be set",
+                column, struct_name
+            ),
+            Self::UnsupportedShape { type_name } => writeln!(
+                f,
+                "\"{}\" can't be stored, as it isn't a struct with named fields",
+                type_name
+            ),
         }
     }
 }
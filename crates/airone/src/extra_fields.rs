@@ -0,0 +1,103 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use crate::error::{Error, ParseError};
+use crate::serde::{SerializableField, SerializedFieldValue};
+
+fn parse_error(expectation: &str, v: &SerializedFieldValue) -> Error {
+    Error::ParseError(ParseError::new(
+        expectation.to_string(),
+        v.get().to_string(),
+    ))
+}
+
+/// Dates and times are written in ISO 8601, as `2024-05-31`,
+/// `13:45:00.5`, `2024-05-31T13:45:00` and `2024-05-31T13:45:00+02:00`
+#[cfg(feature = "chrono")]
+mod chrono_fields {
+    use super::*;
+    use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+    const DATE: &str = "%Y-%m-%d";
+    const TIME: &str = "%H:%M:%S%.f";
+    const DATE_TIME: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+    impl SerializableField for NaiveDate {
+        fn serialize_field(&self) -> SerializedFieldValue {
+            SerializedFieldValue::new(self.format(DATE).to_string())
+        }
+        fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+            NaiveDate::parse_from_str(v.get(), DATE).map_err(|_| parse_error("date", &v))
+        }
+    }
+
+    impl SerializableField for NaiveTime {
+        fn serialize_field(&self) -> SerializedFieldValue {
+            SerializedFieldValue::new(self.format(TIME).to_string())
+        }
+        fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+            NaiveTime::parse_from_str(v.get(), TIME).map_err(|_| parse_error("time", &v))
+        }
+    }
+
+    impl SerializableField for NaiveDateTime {
+        fn serialize_field(&self) -> SerializedFieldValue {
+            SerializedFieldValue::new(self.format(DATE_TIME).to_string())
+        }
+        fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+            NaiveDateTime::parse_from_str(v.get(), DATE_TIME)
+                .map_err(|_| parse_error("date and time", &v))
+        }
+    }
+
+    impl SerializableField for DateTime<FixedOffset> {
+        fn serialize_field(&self) -> SerializedFieldValue {
+            SerializedFieldValue::new(self.to_rfc3339())
+        }
+        fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+            DateTime::parse_from_rfc3339(v.get())
+                .map_err(|_| parse_error("RFC 3339 date and time", &v))
+        }
+    }
+
+    impl SerializableField for DateTime<Utc> {
+        fn serialize_field(&self) -> SerializedFieldValue {
+            SerializedFieldValue::new(self.to_rfc3339())
+        }
+        fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+            Ok(DateTime::<FixedOffset>::deserialize_field(v)?.with_timezone(&Utc))
+        }
+    }
+}
+
+/// Written in the hyphenated form,
+/// as `67e55044-10b1-426f-9247-bb680e5fe0c8`
+#[cfg(feature = "uuid")]
+impl SerializableField for uuid::Uuid {
+    fn serialize_field(&self) -> SerializedFieldValue {
+        SerializedFieldValue::new(self.hyphenated().to_string())
+    }
+    fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+        uuid::Uuid::parse_str(v.get()).map_err(|_| parse_error("uuid", &v))
+    }
+}
@@ -26,10 +26,17 @@
 
 pub mod database;
 pub mod error;
+/// Fields of types from other crates, behind the
+/// `chrono` and `uuid` features
+#[cfg(any(feature = "chrono", feature = "uuid"))]
+mod extra_fields;
 pub mod prelude;
 pub mod serde;
+/// Storage of `serde` types, behind the `serde` feature
+#[cfg(feature = "serde")]
+pub mod serde_record;
 
-pub use airone_derive::AironeDbDerive;
+pub use airone_derive::{AironeDbDerive, AironeField};
 
 #[cfg(test)]
 mod tests;
@@ -21,7 +21,10 @@
 //  along with this program. If not, see <https://www.gnu.org/licenses/>.
 //  ------------------------------------------------------------------
 
+pub use crate::database::query::Predicate;
 pub use crate::database::settings::save_mode;
-pub use crate::database::settings::BufferMode;
+pub use crate::database::settings::{BufferMode, OpenOptions};
 pub use crate::database::AironeDb;
-pub use airone_derive::AironeDbDerive;
+#[cfg(feature = "serde")]
+pub use crate::serde_record::SerdeRecord;
+pub use airone_derive::{AironeDbDerive, AironeField};
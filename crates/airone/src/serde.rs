@@ -22,15 +22,65 @@
 //  ------------------------------------------------------------------
 
 use std::any::Any;
+use std::collections::HashMap;
+use std::hash::Hash;
 
-use crate::error::Error;
+use crate::database::index::FieldIndex;
+use crate::error::{Error, ParseError};
 
 /// This has methods to get and set the value
 /// by a string key
 pub trait InnerStruct: Serialize + Deserialize {
     const COLUMNS: &'static [&'static str];
     const STRUCT_NAME: &'static str;
+    /// Names of the fields, in the order they are serialized,
+    /// [Self::COLUMNS] unless they are only known at runtime
+    fn columns() -> &'static [&'static str] {
+        Self::COLUMNS
+    }
+    /// [Self::STRUCT_NAME] unless it is only known at runtime
+    fn struct_name() -> &'static str {
+        Self::STRUCT_NAME
+    }
+    /// Checks that the struct can be stored,
+    /// before anything else when opening the database
+    fn check_shape() -> Result<(), Error> {
+        Ok(())
+    }
+    /// Previous names of the columns, as `(old, new)` pairs
+    const RENAMED_COLUMNS: &'static [(&'static str, &'static str)] = &[];
+    /// Columns of older files which are dropped when loading
+    const DROPPED_COLUMNS: &'static [&'static str] = &[];
+    /// Value of a column missing in older files,
+    /// `None` if it has no default
+    fn default_column(_key: &str) -> Option<SerializedFieldValue> {
+        None
+    }
+    /// Field holding the id, from `#[airone(id)]`
+    const ID_COLUMN: Option<&'static str> = None;
+    /// The id of the element, `None` without an id field
+    fn id(&self) -> Option<u64> {
+        None
+    }
+    /// Called when the element is added to the database
+    fn set_id(&mut self, _id: u64) {}
+    /// Indexes of the `#[airone(index)]` and `#[airone(unique)]` fields
+    fn indexes() -> Vec<Box<dyn FieldIndex<Self>>> {
+        Vec::new()
+    }
     fn set_str(&mut self, key: &str, value: SerializedFieldValue) -> Result<(), Error>;
+}
+
+/// Implemented by structs with an `#[airone(id)]` field,
+/// which enables the id based methods of
+/// [AironeDb](crate::database::AironeDb)
+pub trait Identified: InnerStruct {}
+
+/// Implemented by structs with a field for each column,
+/// which can be read and written by name through
+/// [AironeDb::set](crate::database::AironeDb::set)
+/// and [WriteProxy](crate::database::WriteProxy)
+pub trait NamedFields: InnerStruct {
     fn set<V: SerializableField>(&mut self, key: &str, value: V);
     fn get<V: SerializableField>(&self, key: &str) -> &V;
 }
@@ -53,7 +103,42 @@ where
     fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error>;
 }
 
-#[derive(Clone, Debug)]
+/// Version of the on-disk text encoding
+///
+/// - [FormatVersion::V1]: legacy files without a version marker.
+///   Only `\n`, `\r` and `\t` are escaped and empty values are read as absent.
+/// - [FormatVersion::V2]: the backslash is escaped as well and
+///   absent values are written as `\N`, so every string round-trips.
+/// - [FormatVersion::V3]: same values as V2, lines of the changes file
+///   end with a checksum and files carry a generation number.
+/// - [FormatVersion::V4]: same as V3, the changes file may address
+///   elements by id and the marker may carry the next id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum FormatVersion {
+    V1 = 1,
+    V2 = 2,
+    V3 = 3,
+    V4 = 4,
+}
+impl FormatVersion {
+    /// The version used to write new files
+    pub(crate) const CURRENT: Self = Self::V4;
+
+    pub(crate) fn from_number(n: u32) -> Option<Self> {
+        match n {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2),
+            3 => Some(Self::V3),
+            4 => Some(Self::V4),
+            _ => None,
+        }
+    }
+    pub(crate) fn number(&self) -> u32 {
+        *self as u32
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct SerializedStruct(Vec<SerializedFieldValue>);
 impl SerializedStruct {
     pub fn new(v: Vec<SerializedFieldValue>) -> Self {
@@ -69,37 +154,158 @@ impl SerializedStruct {
             .collect::<Vec<String>>()
             .join("\t")
     }
-    pub(crate) fn from_escaped_line(l: &str) -> Self {
-        Self(
+    pub(crate) fn from_escaped_line(l: &str, version: FormatVersion) -> Result<Self, Error> {
+        Ok(Self(
             l.split('\t')
-                .map(SerializedFieldValue::from_escaped_string)
-                .collect(),
-        )
+                .map(|s| SerializedFieldValue::from_escaped_string(s, version))
+                .collect::<Result<_, _>>()?,
+        ))
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct SerializedFieldValue(String);
+/// A single serialized value
+///
+/// A value is either a string or absent, as written by `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerializedFieldValue(Option<String>);
 impl SerializedFieldValue {
     pub fn new(s: String) -> Self {
-        Self(s)
+        Self(Some(s))
     }
+    /// An absent value
+    pub fn null() -> Self {
+        Self(None)
+    }
+    pub fn is_null(&self) -> bool {
+        self.0.is_none()
+    }
+    /// Returns the string value, or an empty string
+    /// when the value is absent
     pub fn get(&self) -> &str {
-        &self.0
+        self.0.as_deref().unwrap_or_default()
     }
+
+    /// Packs several values into a single one, for fields
+    /// holding more values such as `Vec`, enums or nested structs.
+    ///
+    /// Each part is followed by `;`, with `\` and `;` escaped
+    /// and absent parts written as `\N`. So `[]`, `[""]` and
+    /// `[None]` are written as an empty string, `;` and `\N;`.
+    pub fn join(parts: impl IntoIterator<Item = SerializedFieldValue>) -> Self {
+        let mut joined = String::new();
+        for part in parts {
+            match part.0 {
+                None => joined.push_str("\\N"),
+                Some(s) => {
+                    for c in s.chars() {
+                        if c == '\\' || c == ';' {
+                            joined.push('\\');
+                        }
+                        joined.push(c);
+                    }
+                }
+            }
+            joined.push(';');
+        }
+        Self::new(joined)
+    }
+    /// Unpacks the values packed by [SerializedFieldValue::join]
+    pub fn split(&self) -> Result<Vec<SerializedFieldValue>, Error> {
+        let invalid = || {
+            Error::ParseError(ParseError::new(
+                "values each ending with `;`".to_string(),
+                self.get().to_string(),
+            ))
+        };
+        let mut parts = Vec::new();
+        let mut part = Some(String::new());
+        let mut chars = self.get().chars();
+        while let Some(c) = chars.next() {
+            match c {
+                ';' => parts.push(Self(part.replace(String::new()))),
+                '\\' => match (chars.next(), part.as_mut()) {
+                    (Some('N'), Some(s)) if s.is_empty() => part = None,
+                    (Some(c @ ('\\' | ';')), Some(s)) => s.push(c),
+                    _ => return Err(invalid()),
+                },
+                c => part.as_mut().ok_or_else(invalid)?.push(c),
+            }
+        }
+        if part != Some(String::new()) {
+            return Err(invalid());
+        }
+        Ok(parts)
+    }
+    /// Unpacks exactly `count` values, for types with a fixed number of parts
+    pub fn split_exact(&self, count: usize) -> Result<Vec<SerializedFieldValue>, Error> {
+        let parts = self.split()?;
+        if parts.len() != count {
+            return Err(Error::ParseError(ParseError::new(
+                format!("{} packed values", count),
+                self.get().to_string(),
+            )));
+        }
+        Ok(parts)
+    }
+
     pub(crate) fn to_escaped_string(&self) -> String {
-        self.0
-            .replace('\n', "\\n")
-            .replace('\r', "\\r")
-            .replace('\t', "\\t")
+        let Some(s) = &self.0 else {
+            return "\\N".to_string();
+        };
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+    pub(crate) fn from_escaped_string(s: &str, version: FormatVersion) -> Result<Self, Error> {
+        match version {
+            FormatVersion::V1 => Ok(Self::from_v1_string(s)),
+            FormatVersion::V2 | FormatVersion::V3 | FormatVersion::V4 => Self::from_v2_string(s),
+        }
     }
-    pub(crate) fn from_escaped_string(s: &str) -> Self {
-        SerializedFieldValue(
+    fn from_v1_string(s: &str) -> Self {
+        if s.is_empty() {
+            return Self::null();
+        }
+        Self::new(
             s.replace("\\n", "\n")
                 .replace("\\r", "\r")
                 .replace("\\t", "\t"),
         )
     }
+    fn from_v2_string(s: &str) -> Result<Self, Error> {
+        if s == "\\N" {
+            return Ok(Self::null());
+        }
+        let mut unescaped = String::with_capacity(s.len());
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('\\') => unescaped.push('\\'),
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some('t') => unescaped.push('\t'),
+                _ => {
+                    return Err(Error::ParseError(ParseError::new(
+                        "escaped field value".to_string(),
+                        s.to_string(),
+                    )))
+                }
+            }
+        }
+        Ok(Self::new(unescaped))
+    }
 }
 
 macro_rules! impl_serde_value {
@@ -128,12 +334,11 @@ impl<T: SerializableField> SerializableField for Option<T> {
         if let Some(e) = self {
             e.serialize_field()
         } else {
-            SerializedFieldValue::new(String::new())
+            SerializedFieldValue::null()
         }
     }
     fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
-        let s = v.get();
-        if !s.is_empty() {
+        if !v.is_null() {
             let t: T = SerializableField::deserialize_field(v)?;
             Ok(Some(t))
         } else {
@@ -152,6 +357,77 @@ impl SerializableField for String {
     }
 }
 
+impl<T: SerializableField> SerializableField for Vec<T> {
+    fn serialize_field(&self) -> SerializedFieldValue {
+        SerializedFieldValue::join(self.iter().map(|e| e.serialize_field()))
+    }
+    fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+        v.split()?
+            .into_iter()
+            .map(SerializableField::deserialize_field)
+            .collect()
+    }
+}
+
+/// Written as keys and values alternated,
+/// sorted by key so that the same map is always written the same way
+impl<K, V> SerializableField for HashMap<K, V>
+where
+    K: SerializableField + Eq + Hash,
+    V: SerializableField,
+{
+    fn serialize_field(&self) -> SerializedFieldValue {
+        let mut entries: Vec<(SerializedFieldValue, SerializedFieldValue)> = self
+            .iter()
+            .map(|(k, v)| (k.serialize_field(), v.serialize_field()))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        SerializedFieldValue::join(entries.into_iter().flat_map(|(k, v)| [k, v]))
+    }
+    fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+        let parts = v.split()?;
+        if parts.len() % 2 != 0 {
+            return Err(Error::ParseError(ParseError::new(
+                "keys and values".to_string(),
+                v.get().to_string(),
+            )));
+        }
+        let mut parts = parts.into_iter();
+        let mut map = HashMap::new();
+        while let (Some(k), Some(v)) = (parts.next(), parts.next()) {
+            map.insert(
+                SerializableField::deserialize_field(k)?,
+                SerializableField::deserialize_field(v)?,
+            );
+        }
+        Ok(map)
+    }
+}
+
+/// Written as lowercase hexadecimal
+impl<const N: usize> SerializableField for [u8; N] {
+    fn serialize_field(&self) -> SerializedFieldValue {
+        SerializedFieldValue::new(self.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+    fn deserialize_field(v: SerializedFieldValue) -> Result<Self, Error> {
+        let s = v.get();
+        let invalid = || {
+            Error::ParseError(ParseError::new(
+                format!("{} hexadecimal bytes", N),
+                s.to_string(),
+            ))
+        };
+        if s.len() != 2 * N || !s.is_ascii() {
+            return Err(invalid());
+        }
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(bytes)
+    }
+}
+
 #[cfg(test)]
 mod example {
     use crate::{database::settings::save_mode::AutoSave, prelude::AironeDb};
@@ -166,16 +442,32 @@ mod example {
     impl InnerStruct for Abc {
         const COLUMNS: &'static [&'static str] = &["a", "b"];
         const STRUCT_NAME: &'static str = "Abc";
+        fn set_str(&mut self, key: &str, value: SerializedFieldValue) -> Result<(), Error> {
+            match key {
+                "a" => {
+                    self.a = SerializableField::deserialize_field(value)?;
+                }
+                "b" => {
+                    self.b = SerializableField::deserialize_field(value)?;
+                }
+                _ => {
+                    unreachable!()
+                }
+            }
+            Ok(())
+        }
+    }
+    impl NamedFields for Abc {
         fn get<V: SerializableField>(&self, key: &str) -> &V {
             use std::any::Any;
             match key {
                 "a" => {
                     let v = &self.a as &dyn Any;
-                    return v.downcast_ref::<V>().unwrap();
+                    v.downcast_ref::<V>().unwrap()
                 }
                 "b" => {
                     let v = &self.a as &dyn Any;
-                    return v.downcast_ref::<V>().unwrap();
+                    v.downcast_ref::<V>().unwrap()
                 }
                 _ => panic!(""),
             }
@@ -192,20 +484,6 @@ mod example {
                 }
             }
         }
-        fn set_str(&mut self, key: &str, value: SerializedFieldValue) -> Result<(), Error> {
-            match key {
-                "a" => {
-                    self.a = SerializableField::deserialize_field(value)?;
-                }
-                "b" => {
-                    self.b = SerializableField::deserialize_field(value)?;
-                }
-                _ => {
-                    unreachable!()
-                }
-            }
-            Ok(())
-        }
     }
     impl Deserialize for Abc {
         fn deserialize(value: &SerializedStruct) -> Result<Self, Error> {
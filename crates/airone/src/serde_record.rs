@@ -0,0 +1,505 @@
+//  ------------------------------------------------------------------
+//  Airone
+//  is a Rust library which provides a simple in-memory,
+//  write-on-update database that is persisted
+//  to an append-only transaction file.
+//
+//  Copyright © 2022,2023,2024 Massimo Gismondi
+//
+//  This file is part of Airone.
+//  Airone is free software: you can redistribute it and/or
+//  modify it under the terms of the GNU Affero General Public License
+//  as published by the Free Software Foundation, either version 3
+//  of the License, or (at your option) any later version.
+//
+//  This program is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+//  GNU General Public License for more details.
+
+//  You should have received a copy of the GNU Affero General Public License
+//  along with this program. If not, see <https://www.gnu.org/licenses/>.
+//  ------------------------------------------------------------------
+
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
+
+use ::serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use ::serde::forward_to_deserialize_any;
+use serde_json::Value;
+
+use crate::database::settings::save_mode::{AutoSave, ManualSave, SaveModeExt};
+use crate::database::AironeDb;
+use crate::error::{Error, ParseError};
+use crate::serde::{Deserialize, InnerStruct, Serialize, SerializedFieldValue, SerializedStruct};
+
+/// Stores a struct implementing `serde::Serialize` and
+/// `serde::de::DeserializeOwned` in an [AironeDb],
+/// without deriving `AironeDbDerive`.
+///
+/// Each field is a column. Strings, numbers and booleans are written
+/// as they are, `None` as an absent value and other values as JSON.
+///
+/// `T` must be a struct with named fields, serialized as a map of them.
+/// Opening the database returns [Error::UnsupportedShape] otherwise,
+/// like for tuple structs or `#[serde(flatten)]` fields.
+///
+/// Change a record with [AironeDb::modify],
+/// which logs an edit for each changed field,
+/// since its fields can't be set by name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SerdeRecord<T>(pub T);
+impl<T> Deref for SerdeRecord<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> DerefMut for SerdeRecord<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+impl<T> From<T> for SerdeRecord<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> InnerStruct for SerdeRecord<T>
+where
+    T: ::serde::Serialize + DeserializeOwned + 'static,
+{
+    // The fields of `T` are only known at runtime, see `columns()`
+    const COLUMNS: &'static [&'static str] = &[];
+    const STRUCT_NAME: &'static str = "SerdeRecord";
+    fn columns() -> &'static [&'static str] {
+        describe::<T>().map_or(&[], |(_, fields)| fields)
+    }
+    fn struct_name() -> &'static str {
+        describe::<T>().map_or(Self::STRUCT_NAME, |(name, _)| name)
+    }
+    fn check_shape() -> Result<(), Error> {
+        match describe::<T>() {
+            Some(_) => Ok(()),
+            None => Err(Error::UnsupportedShape {
+                type_name: std::any::type_name::<T>().to_string(),
+            }),
+        }
+    }
+    fn set_str(&mut self, key: &str, value: SerializedFieldValue) -> Result<(), Error> {
+        let Some(position) = Self::columns().iter().position(|c| *c == key) else {
+            return Err(Error::SchemaMismatch {
+                struct_name: Self::struct_name().to_string(),
+                expected: Self::columns().iter().map(|c| c.to_string()).collect(),
+                found: vec![key.to_string()],
+            });
+        };
+        let mut values = self.serialize().get_values().to_vec();
+        values[position] = value;
+        *self = Self::deserialize(&SerializedStruct::new(values))?;
+        Ok(())
+    }
+}
+
+impl<T> Serialize for SerdeRecord<T>
+where
+    T: ::serde::Serialize + DeserializeOwned + 'static,
+{
+    fn serialize(&self) -> SerializedStruct {
+        // A `Serialize` not matching the fields told by `Deserialize`
+        // leaves them absent
+        let mut fields = match serde_json::to_value(&self.0) {
+            Ok(Value::Object(fields)) => fields,
+            _ => Default::default(),
+        };
+        SerializedStruct::new(
+            Self::columns()
+                .iter()
+                .map(|c| match fields.remove(*c).unwrap_or(Value::Null) {
+                    Value::Null => SerializedFieldValue::null(),
+                    Value::String(s) => SerializedFieldValue::new(s),
+                    value => SerializedFieldValue::new(value.to_string()),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<T> Deserialize for SerdeRecord<T>
+where
+    T: ::serde::Serialize + DeserializeOwned + 'static,
+{
+    fn deserialize(s: &SerializedStruct) -> Result<Self, Error> {
+        let cells = Self::columns().iter().zip(s.get_values()).map(|(c, v)| {
+            let text = (!v.is_null()).then(|| v.get());
+            (*c, Cell::new(text, false))
+        });
+        from_cells(cells).map(Self)
+    }
+}
+
+/// Name and fields of the struct `T`, as told by its
+/// `Deserialize` implementation, `None` if it isn't
+/// a struct with named fields
+fn describe<T: DeserializeOwned>() -> Description {
+    let mut described = None;
+    let _ = T::deserialize(StructDescriber(&mut described));
+    described
+}
+
+type Description = Option<(&'static str, &'static [&'static str])>;
+
+struct StructDescriber<'a>(&'a mut Description);
+impl<'de> de::Deserializer<'de> for StructDescriber<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = Some((name, fields));
+        Err(de::Error::custom("described"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+fn from_cells<'a, T: DeserializeOwned>(
+    cells: impl Iterator<Item = (&'a str, Cell<'a>)>,
+) -> Result<T, Error> {
+    T::deserialize(de::value::MapDeserializer::new(cells)).map_err(|e: de::value::Error| {
+        Error::ParseError(ParseError::new(
+            format!("a valid \"{}\"", std::any::type_name::<T>()),
+            e.to_string(),
+        ))
+    })
+}
+
+/// Deserializes a single column, giving the text as it is
+/// to strings and parsing it for the other types
+struct Cell<'a> {
+    text: Option<&'a str>,
+    /// In CSV files an empty cell is `None` too
+    empty_is_none: bool,
+}
+impl<'a> Cell<'a> {
+    fn new(text: Option<&'a str>, empty_is_none: bool) -> Self {
+        Self {
+            text,
+            empty_is_none,
+        }
+    }
+    fn json<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, de::value::Error> {
+        match self.text {
+            None => visitor.visit_none(),
+            Some(text) => {
+                let value: Value = serde_json::from_str(text).map_err(de::Error::custom)?;
+                de::Deserializer::deserialize_any(value, visitor).map_err(de::Error::custom)
+            }
+        }
+    }
+}
+impl<'de> IntoDeserializer<'de, de::value::Error> for Cell<'_> {
+    type Deserializer = Self;
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $t:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.text.and_then(|text| text.parse::<$t>().ok()) {
+                    Some(value) => visitor.$visit(value),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Cell<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.text.map(serde_json::from_str::<Value>) {
+            None => visitor.visit_none(),
+            Some(Ok(Value::String(_))) | Some(Err(_)) => visitor.visit_str(self.text.unwrap()),
+            Some(Ok(_)) => self.json(visitor),
+        }
+    }
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.text {
+            None => visitor.visit_none(),
+            Some("") if self.empty_is_none => visitor.visit_none(),
+            Some(_) => visitor.visit_some(self),
+        }
+    }
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.text {
+            Some(text) => visitor.visit_str(text),
+            None => visitor.visit_none(),
+        }
+    }
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.text {
+            // Unit variants are written as their name,
+            // the others as a JSON object
+            Some(text) if !text.starts_with('{') => {
+                visitor.visit_enum(IntoDeserializer::<Self::Error>::into_deserializer(text))
+            }
+            Some(text) => {
+                let value: Value = serde_json::from_str(text).map_err(de::Error::custom)?;
+                de::Deserializer::deserialize_enum(value, name, variants, visitor)
+                    .map_err(de::Error::custom)
+            }
+            None => visitor.visit_none(),
+        }
+    }
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.json(visitor)
+    }
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.json(visitor)
+    }
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.json(visitor)
+    }
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.json(visitor)
+    }
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.json(visitor)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct identifier ignored_any
+    }
+}
+
+// Export and import
+impl<T, SaveMode> AironeDb<SerdeRecord<T>, SaveMode>
+where
+    T: ::serde::Serialize + DeserializeOwned + 'static,
+    SaveMode: SaveModeExt,
+{
+    /// Writes every record as a JSON array
+    pub fn export_json(&self, writer: impl Write) -> Result<(), Error> {
+        let records: Vec<&T> = self.iter().map(|r| &r.0).collect();
+        serde_json::to_writer_pretty(writer, &records).map_err(json_error::<T>)
+    }
+
+    /// Writes every record as CSV, with a header of the columns.
+    ///
+    /// Absent values are written as empty cells,
+    /// like empty strings.
+    pub fn export_csv(&self, mut writer: impl Write) -> Result<(), Error> {
+        let header: Vec<String> = SerdeRecord::<T>::columns()
+            .iter()
+            .map(|c| csv_quote(c))
+            .collect();
+        writeln!(writer, "{}", header.join(","))?;
+        for record in self.iter() {
+            let cells: Vec<String> = record
+                .serialize()
+                .get_values()
+                .iter()
+                .map(|v| csv_quote(v.get()))
+                .collect();
+            writeln!(writer, "{}", cells.join(","))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+impl<T> AironeDb<SerdeRecord<T>, AutoSave>
+where
+    T: ::serde::Serialize + DeserializeOwned + 'static,
+{
+    /// Appends the records of a JSON array, as written by
+    /// [Self::export_json], and returns how many
+    pub fn import_json(&mut self, reader: impl Read) -> Result<usize, Error> {
+        let records = read_json::<T>(reader)?;
+        let count = records.len();
+        self.extend(records.into_iter().map(SerdeRecord))?;
+        Ok(count)
+    }
+
+    /// Appends the records of a CSV file with a header,
+    /// as written by [Self::export_csv], and returns how many.
+    ///
+    /// Empty cells are read as `None` for optional fields.
+    pub fn import_csv(&mut self, reader: impl Read) -> Result<usize, Error> {
+        let records = read_csv::<T>(reader)?;
+        let count = records.len();
+        self.extend(records.into_iter().map(SerdeRecord))?;
+        Ok(count)
+    }
+}
+impl<T> AironeDb<SerdeRecord<T>, ManualSave>
+where
+    T: ::serde::Serialize + DeserializeOwned + 'static,
+{
+    /// Appends the records of a JSON array, as written by
+    /// [Self::export_json], and returns how many
+    pub fn import_json(&mut self, reader: impl Read) -> Result<usize, Error> {
+        let records = read_json::<T>(reader)?;
+        let count = records.len();
+        self.extend(records.into_iter().map(SerdeRecord))?;
+        Ok(count)
+    }
+
+    /// Appends the records of a CSV file with a header,
+    /// as written by [Self::export_csv], and returns how many.
+    ///
+    /// Empty cells are read as `None` for optional fields.
+    pub fn import_csv(&mut self, reader: impl Read) -> Result<usize, Error> {
+        let records = read_csv::<T>(reader)?;
+        let count = records.len();
+        self.extend(records.into_iter().map(SerdeRecord))?;
+        Ok(count)
+    }
+}
+
+fn json_error<T>(e: serde_json::Error) -> Error {
+    if e.is_io() {
+        Error::IoError(e.into())
+    } else {
+        Error::ParseError(ParseError::new(
+            format!("JSON array of \"{}\"", std::any::type_name::<T>()),
+            e.to_string(),
+        ))
+    }
+}
+
+fn read_json<T: DeserializeOwned>(reader: impl Read) -> Result<Vec<T>, Error> {
+    serde_json::from_reader(reader).map_err(json_error::<T>)
+}
+
+fn read_csv<T: DeserializeOwned>(mut reader: impl Read) -> Result<Vec<T>, Error> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut rows = parse_csv(&text)?.into_iter();
+    let header = rows.next().unwrap_or_default();
+    rows.enumerate()
+        .map(|(i, row)| {
+            if row.len() != header.len() {
+                return Err(Error::ParseError(ParseError::new(
+                    format!("{} cells", header.len()),
+                    format!("{} cells in record {}", row.len(), i + 1),
+                )));
+            }
+            let cells = header
+                .iter()
+                .zip(row.iter())
+                .map(|(c, v)| (c.as_str(), Cell::new(Some(v), true)));
+            from_cells(cells)
+        })
+        .collect()
+}
+
+fn csv_quote(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Splits CSV text in rows of cells. Cells can be quoted
+/// with `"`, doubling the quotes inside, to hold
+/// commas and line breaks.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, Error> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if cell.is_empty() => quoted = true,
+            (',', false) => row.push(std::mem::take(&mut cell)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => cell.push(c),
+        }
+    }
+    if quoted {
+        return Err(Error::ParseError(ParseError::new(
+            "closing quote".to_string(),
+            "end of file".to_string(),
+        )));
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    Ok(rows)
+}
@@ -0,0 +1,132 @@
+extern crate self as airone;
+
+use super::*;
+use crate::database::{fault, CompactionStep};
+use crate::error::Error;
+use std::fs::{read, read_to_string, write, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Writes some changes which would be visible
+/// if they were lost or applied twice
+fn write_changes(dir: &Path) {
+    let mut db: AironeDb<Counter> = AironeDb::open(dir).unwrap();
+    db.push(counter(0, "a")).unwrap();
+    db.push(counter(1, "b")).unwrap();
+    db.push(counter(2, "c")).unwrap();
+    db.get_mut(1).unwrap().set_n(10).unwrap();
+    db.remove(0).unwrap();
+}
+
+fn assert_changes(dir: &Path) {
+    let db: AironeDb<Counter> = AironeDb::open(dir).unwrap();
+    assert_eq!(db.get_all(), &[counter(10, "b"), counter(2, "c")]);
+    assert!(db.skipped_torn_line().is_none());
+}
+
+#[test]
+fn crash_after_each_compaction_step() {
+    for step in CompactionStep::ALL {
+        let dir = tempfile::tempdir().unwrap();
+        write_changes(dir.path());
+
+        fault::crash_after(Some(step));
+        let crashed = AironeDb::<Counter>::open(dir.path());
+        fault::crash_after(None);
+        assert!(crashed.is_err(), "{:?}", step);
+
+        // Recover, then load the compacted files again
+        assert_changes(dir.path());
+        assert_changes(dir.path());
+
+        // The recovered database keeps working
+        {
+            let mut db: AironeDb<Counter> = AironeDb::open(dir.path()).unwrap();
+            db.push(counter(3, "d")).unwrap();
+        }
+        let db: AironeDb<Counter> = AironeDb::open(dir.path()).unwrap();
+        assert_eq!(db.len(), 3, "{:?}", step);
+        assert_eq!(db[2], counter(3, "d"));
+    }
+}
+
+#[test]
+fn torn_last_line_is_skipped() {
+    for torn in [
+        &b"A\t2\t4\tunfinished"[..],
+        &b"E\t0\tlabel\t\xe2\x82"[..],
+        &b"D\t0\t0000"[..],
+    ] {
+        let dir = tempfile::tempdir().unwrap();
+        let changes_file = dir.path().join("Counter.changes.csv");
+        write_changes(dir.path());
+        OpenOptions::new()
+            .append(true)
+            .open(&changes_file)
+            .unwrap()
+            .write_all(torn)
+            .unwrap();
+
+        let db: AironeDb<Counter> = AironeDb::open(dir.path()).unwrap();
+        assert!(matches!(
+            db.skipped_torn_line(),
+            Some(Error::CorruptLine { line: 7, .. })
+        ));
+        drop(db);
+        assert_changes(dir.path());
+        // The torn line is dropped by the compaction
+        let changes = read_to_string(&changes_file).unwrap();
+        assert_eq!(changes.lines().count(), 1);
+    }
+}
+
+#[test]
+fn corrupted_line_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let base_file = dir.path().join("Counter.csv");
+    let changes_file = dir.path().join("Counter.changes.csv");
+    write_changes(dir.path());
+    let base = read(&base_file).unwrap();
+    let changes = read_to_string(&changes_file).unwrap();
+    let corrupted = changes.replacen("\tb\t", "\tB\t", 1);
+    assert_ne!(changes, corrupted);
+    write(&changes_file, &corrupted).unwrap();
+
+    assert!(AironeDb::<Counter>::open(dir.path()).is_err());
+
+    // Nothing is rewritten
+    assert_eq!(read(&base_file).unwrap(), base);
+    assert_eq!(read_to_string(&changes_file).unwrap(), corrupted);
+}
+
+#[test]
+fn corrupted_last_line_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let changes_file = dir.path().join("Counter.changes.csv");
+    write_changes(dir.path());
+    let changes = read_to_string(&changes_file).unwrap();
+    // Only a line without its newline may be torn
+    let corrupted = format!("{}D\t0\t0000\n", changes);
+    write(&changes_file, &corrupted).unwrap();
+
+    assert!(matches!(
+        AironeDb::<Counter>::open(dir.path()),
+        Err(Error::CorruptLine { .. })
+    ));
+    assert_eq!(read_to_string(&changes_file).unwrap(), corrupted);
+}
+
+#[test]
+fn changes_of_a_newer_generation_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let changes_file = dir.path().join("Counter.changes.csv");
+    write_changes(dir.path());
+    let changes = read_to_string(&changes_file).unwrap();
+    write(
+        &changes_file,
+        changes.replacen("#airone-format\t4\t1\n", "#airone-format\t4\t2\n", 1),
+    )
+    .unwrap();
+
+    assert!(AironeDb::<Counter>::open(dir.path()).is_err());
+}
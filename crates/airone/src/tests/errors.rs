@@ -0,0 +1,110 @@
+extern crate self as airone;
+
+use super::Counter;
+use crate::error::Error;
+use crate::prelude::*;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+/// Loads legacy files, which have neither
+/// a format marker nor checksums
+fn load(dir: &Path, base: &str, changes: &str) -> Result<AironeDb<Counter>, Error> {
+    write(dir.join("Counter.csv"), base).unwrap();
+    write(dir.join("Counter.changes.csv"), changes).unwrap();
+    AironeDb::open(dir)
+}
+
+/// Returns the line number and the reason
+/// of a CorruptLine error
+fn corrupt_line(result: Result<AironeDb<Counter>, Error>, file: &Path) -> (usize, Error) {
+    match result {
+        Err(Error::CorruptLine { path, line, source }) => {
+            assert_eq!(path, file);
+            (line, *source)
+        }
+        other => panic!("expected CorruptLine, got {:?}", other.map(|db| db.len())),
+    }
+}
+
+#[test]
+fn empty_base_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let base_file = dir.path().join("Counter.csv");
+    let result = load(dir.path(), "", "");
+    let (line, _) = corrupt_line(result, &base_file);
+    assert_eq!(line, 1);
+
+    // Nothing is rewritten
+    assert_eq!(read_to_string(&base_file).unwrap(), "");
+}
+
+#[test]
+fn invalid_value_in_base_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let base_file = dir.path().join("Counter.csv");
+    let result = load(dir.path(), "n\tlabel\n1\ta\nx\tb\n", "");
+    let (line, source) = corrupt_line(result, &base_file);
+    assert_eq!(line, 3);
+    assert!(matches!(source, Error::ParseError(_)));
+
+    let result = load(dir.path(), "n\tlabel\n1\ta\t2\n", "");
+    let (line, _) = corrupt_line(result, &base_file);
+    assert_eq!(line, 2);
+}
+
+#[test]
+fn schema_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+    for header in ["n\tname", "n", "n\tlabel\tlabel"] {
+        match load(dir.path(), &format!("{}\n", header), "") {
+            Err(Error::SchemaMismatch {
+                struct_name,
+                expected,
+                found,
+            }) => {
+                assert_eq!(struct_name, "Counter");
+                assert_eq!(expected, ["n", "label"]);
+                assert_eq!(found.join("\t"), header);
+            }
+            other => panic!(
+                "expected SchemaMismatch, got {:?}",
+                other.map(|db| db.len())
+            ),
+        }
+    }
+}
+
+#[test]
+fn invalid_change_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let changes_file = dir.path().join("Counter.changes.csv");
+    for (changes, invalid_line) in [
+        ("A\t0\t1\ta\nX\t0\nD\t0\n", 2),
+        ("A\t0\t1\ta\nD\tfirst\nD\t0\n", 2),
+        ("A\t0\t1\ta\nE\t0\nD\t0\n", 2),
+        ("A\t0\t1\ta\nE\t0\tname\tb\nD\t0\n", 2),
+        ("A\t0\t1\ta\nA\t1\t2\tb\nD\t0\t0\nD\t0\n", 3),
+    ] {
+        let result = load(dir.path(), "n\tlabel\n", changes);
+        let (line, _) = corrupt_line(result, &changes_file);
+        assert_eq!(line, invalid_line, "{:?}", changes);
+    }
+}
+
+#[test]
+fn index_out_of_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let changes_file = dir.path().join("Counter.changes.csv");
+    for (changes, index, len) in [
+        ("A\t0\t1\ta\nD\t1\nA\t0\t2\tb\n", 1, 1),
+        ("A\t0\t1\ta\nE\t3\tn\t2\nA\t0\t2\tb\n", 3, 1),
+        ("A\t2\t1\ta\nA\t0\t2\tb\n", 2, 0),
+    ] {
+        let result = load(dir.path(), "n\tlabel\n", changes);
+        let (_, source) = corrupt_line(result, &changes_file);
+        match source {
+            Error::IndexOutOfRange { index: i, len: l } => assert_eq!((i, l), (index, len)),
+            other => panic!("expected IndexOutOfRange, got {:?}", other),
+        }
+    }
+}
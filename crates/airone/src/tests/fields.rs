@@ -0,0 +1,171 @@
+extern crate self as airone;
+
+use crate::error::Error;
+use crate::prelude::*;
+use crate::serde::{SerializableField, SerializedFieldValue};
+use std::collections::HashMap;
+use std::fs::read_to_string;
+
+#[derive(Clone, Debug, PartialEq, AironeField)]
+enum Color {
+    Red,
+    Green,
+}
+
+#[derive(Clone, Debug, PartialEq, AironeField)]
+enum Shape {
+    Point,
+    Circle(f64),
+    Rect { width: f64, height: f64 },
+}
+
+#[derive(Clone, Debug, PartialEq, AironeField)]
+struct Address {
+    street: String,
+    city: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, AironeField)]
+struct Pair<T>(T, T);
+
+#[derive(Debug, PartialEq, AironeDbDerive)]
+struct Drawing {
+    color: Color,
+    shapes: Vec<Shape>,
+    address: Option<Address>,
+    tags: HashMap<String, Vec<String>>,
+    corner: Pair<i32>,
+    hash: [u8; 4],
+}
+
+fn drawing() -> Drawing {
+    Drawing {
+        color: Color::Green,
+        shapes: vec![
+            Shape::Point,
+            Shape::Circle(1.5),
+            Shape::Rect {
+                width: 2.0,
+                height: 3.0,
+            },
+        ],
+        address: Some(Address {
+            street: "Via; Roma\t1\\".to_string(),
+            city: None,
+        }),
+        tags: HashMap::from([
+            ("b".to_string(), vec![]),
+            ("a".to_string(), vec!["".to_string(), "x;y".to_string()]),
+        ]),
+        corner: Pair(-1, 2),
+        hash: [0, 15, 160, 255],
+    }
+}
+
+#[test]
+fn composite_fields_are_persisted() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<Drawing> = AironeDb::open(dir.path()).unwrap();
+    db.push(drawing()).unwrap();
+    db.get_mut(0).unwrap().set_color(Color::Red).unwrap();
+    db.get_mut(0)
+        .unwrap()
+        .set_shapes(vec![Shape::Circle(0.5)])
+        .unwrap();
+    drop(db);
+
+    let changes = read_to_string(dir.path().join("Drawing.changes.csv")).unwrap();
+    assert!(changes.contains("\tcolor\tRed\t"));
+    assert!(changes.contains("\tshapes\tCircle\\\\;0.5\\\\;;\t"));
+
+    // Loading compacts the changes into the base file
+    let db: AironeDb<Drawing> = AironeDb::open(dir.path()).unwrap();
+    let expected = Drawing {
+        color: Color::Red,
+        shapes: vec![Shape::Circle(0.5)],
+        ..drawing()
+    };
+    assert_eq!(db.get_all(), &[expected]);
+}
+
+#[test]
+fn composite_values() {
+    assert_eq!(Color::Green.serialize_field().get(), "Green");
+    assert_eq!(
+        Shape::Rect {
+            width: 2.0,
+            height: 3.0
+        }
+        .serialize_field()
+        .get(),
+        "Rect;2;3;"
+    );
+    assert_eq!(Shape::Point.serialize_field().get(), "Point;");
+    assert_eq!(Vec::<String>::new().serialize_field().get(), "");
+    assert_eq!(vec!["".to_string()].serialize_field().get(), ";");
+    assert_eq!(vec![None, Some(1)].serialize_field().get(), "\\N;1;");
+    assert_eq!([0xab_u8, 1].serialize_field().get(), "ab01");
+    // Maps are written in key order
+    assert_eq!(drawing().tags.serialize_field().get(), r"a;\;x\\\;y\;;b;;");
+
+    for invalid in ["Blue", "Red;", ""] {
+        let v = SerializedFieldValue::new(invalid.to_string());
+        assert!(matches!(
+            Color::deserialize_field(v),
+            Err(Error::ParseError(_))
+        ));
+    }
+    for invalid in ["Circle;", "Circle;1;2;", "Point", "Square;", "Point;\\x;"] {
+        let v = SerializedFieldValue::new(invalid.to_string());
+        assert!(matches!(
+            Shape::deserialize_field(v),
+            Err(Error::ParseError(_))
+        ));
+    }
+    for invalid in ["abc", "abcdef", "zz00"] {
+        let v = SerializedFieldValue::new(invalid.to_string());
+        assert!(matches!(
+            <[u8; 2]>::deserialize_field(v),
+            Err(Error::ParseError(_))
+        ));
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_values() {
+    use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+
+    let date = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+    let date_time: NaiveDateTime = date.and_hms_milli_opt(13, 45, 0, 500).unwrap();
+    assert_eq!(date.serialize_field().get(), "2024-05-31");
+    assert_eq!(date_time.serialize_field().get(), "2024-05-31T13:45:00.500");
+    assert_eq!(
+        NaiveDateTime::deserialize_field(date_time.serialize_field()).unwrap(),
+        date_time
+    );
+    let offset = DateTime::<FixedOffset>::parse_from_rfc3339("2024-05-31T13:45:00+02:00").unwrap();
+    assert_eq!(
+        DateTime::<FixedOffset>::deserialize_field(offset.serialize_field()).unwrap(),
+        offset
+    );
+    let utc = offset.with_timezone(&Utc);
+    assert_eq!(
+        DateTime::<Utc>::deserialize_field(utc.serialize_field()).unwrap(),
+        utc
+    );
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid_values() {
+    let id = uuid::Uuid::from_u128(0x67e5504410b1426f9247bb680e5fe0c8);
+    assert_eq!(
+        id.serialize_field().get(),
+        "67e55044-10b1-426f-9247-bb680e5fe0c8"
+    );
+    assert_eq!(
+        uuid::Uuid::deserialize_field(id.serialize_field()).unwrap(),
+        id
+    );
+}
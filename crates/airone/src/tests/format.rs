@@ -0,0 +1,138 @@
+extern crate self as airone;
+
+use crate::prelude::*;
+use crate::serde::{FormatVersion, SerializedFieldValue, SerializedStruct};
+use proptest::prelude::*;
+use std::fs::{read_to_string, write};
+
+#[derive(Debug, AironeDbDerive)]
+struct Escaping {
+    text: String,
+    opt: Option<String>,
+}
+
+fn field_value() -> impl Strategy<Value = SerializedFieldValue> {
+    prop_oneof![
+        Just(SerializedFieldValue::null()),
+        // Bias towards the characters used by the format itself
+        "[\\\\\\t\\n\\r;Nnrt ]{0,8}".prop_map(SerializedFieldValue::new),
+        any::<String>().prop_map(SerializedFieldValue::new),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn escaped_line_round_trip(values in prop::collection::vec(field_value(), 1..8)) {
+        let original = SerializedStruct::new(values);
+        let line = original.to_escaped_line();
+        prop_assert!(!line.contains('\n') && !line.contains('\r'));
+        prop_assert_eq!(line.split('\t').count(), original.get_values().len());
+
+        let decoded = SerializedStruct::from_escaped_line(&line, FormatVersion::CURRENT).unwrap();
+        prop_assert_eq!(decoded, original);
+    }
+
+    #[test]
+    fn joined_values_round_trip(values in prop::collection::vec(field_value(), 0..8)) {
+        let joined = SerializedFieldValue::join(values.clone());
+        prop_assert_eq!(joined.split().unwrap(), values);
+    }
+
+    #[test]
+    fn nested_joined_values_round_trip(
+        values in prop::collection::vec(prop::collection::vec(field_value(), 0..4), 0..4)
+    ) {
+        let joined = SerializedFieldValue::join(values.iter().cloned().map(SerializedFieldValue::join));
+        let line = SerializedStruct::new(vec![joined]).to_escaped_line();
+        let decoded = SerializedStruct::from_escaped_line(&line, FormatVersion::CURRENT).unwrap();
+        let split: Vec<Vec<SerializedFieldValue>> = decoded.get_values()[0]
+            .split()
+            .unwrap()
+            .iter()
+            .map(|v| v.split().unwrap())
+            .collect();
+        prop_assert_eq!(split, values);
+    }
+}
+
+#[test]
+fn invalid_escape_sequence() {
+    for s in ["\\", "abc\\", "\\x", "\\N\\N"] {
+        assert!(SerializedFieldValue::from_escaped_string(s, FormatVersion::V2).is_err());
+    }
+}
+
+#[test]
+fn backslash_and_absent_values() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let mut db: AironeDb<Escaping> = AironeDb::open(dir.path()).unwrap();
+        db.push(Escaping {
+            text: "a\\nb\\".to_string(),
+            opt: Some(String::new()),
+        })
+        .unwrap();
+        db.push(Escaping {
+            text: "\\N".to_string(),
+            opt: None,
+        })
+        .unwrap();
+        db.get_mut(1)
+            .unwrap()
+            .set_opt(Some("\\N".to_string()))
+            .unwrap();
+    }
+    // Reload twice: from the changes file, then from the compacted file
+    for _ in 0..2 {
+        let db: AironeDb<Escaping> = AironeDb::open(dir.path()).unwrap();
+        assert_eq!(db[0].get_text(), "a\\nb\\");
+        assert_eq!(*db[0].get_opt(), Some(String::new()));
+        assert_eq!(db[1].get_text(), "\\N");
+        assert_eq!(*db[1].get_opt(), Some("\\N".to_string()));
+    }
+}
+
+#[test]
+fn upgrade_legacy_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let base_path = dir.path().join("legacy_escaping.csv");
+    let changes_path = dir.path().join("legacy_escaping.changes.csv");
+    write(&base_path, "text\topt\nfirst\\tline\t\n").unwrap();
+    write(
+        &changes_path,
+        "A\t1\tsecond\\nline\tsome\nE\t0\topt\tx\\ty\n",
+    )
+    .unwrap();
+
+    for _ in 0..2 {
+        let db: AironeDb<Escaping> = AironeDb::open(
+            OpenOptions::new()
+                .directory(dir.path())
+                .name("legacy_escaping"),
+        )
+        .unwrap();
+        assert_eq!(db.len(), 2);
+        assert_eq!(db[0].get_text(), "first\tline");
+        assert_eq!(*db[0].get_opt(), Some("x\ty".to_string()));
+        assert_eq!(db[1].get_text(), "second\nline");
+        assert_eq!(*db[1].get_opt(), Some("some".to_string()));
+    }
+
+    // Both files are rewritten in the current format
+    let base = read_to_string(&base_path).unwrap();
+    assert!(base.starts_with("#airone-format\t4\t2\ntext\topt\n"));
+    let changes = read_to_string(&changes_path).unwrap();
+    assert_eq!(changes, "#airone-format\t4\t2\n");
+}
+
+#[test]
+fn unknown_format_version() {
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path().join("Escaping.csv"),
+        "#airone-format\t99\ntext\topt\n",
+    )
+    .unwrap();
+
+    assert!(AironeDb::<Escaping>::open(dir.path()).is_err());
+}
@@ -0,0 +1,205 @@
+extern crate self as airone;
+
+use crate::error::Error;
+use crate::prelude::*;
+use save_mode::ManualSave;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+#[derive(Debug, PartialEq, AironeDbDerive)]
+struct Note {
+    #[airone(id, default)]
+    id: u64,
+    text: String,
+}
+
+fn note(id: u64, text: &str) -> Note {
+    Note {
+        id,
+        text: text.to_string(),
+    }
+}
+
+/// Lines of the changes file, without the checksum
+fn changes(dir: &Path) -> Vec<String> {
+    read_to_string(dir.join("Note.changes.csv"))
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|l| l.rsplit_once('\t').unwrap().0.to_string())
+        .collect()
+}
+
+#[test]
+fn ids_are_assigned_and_logged() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let mut db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+        db.set_buffer_mode(BufferMode::AlwaysFlush).unwrap();
+        assert_eq!(db.next_id(), 1);
+        // The given id is ignored
+        db.push(note(42, "first")).unwrap();
+        db.push(note(0, "second")).unwrap();
+        db.insert(0, note(0, "third")).unwrap();
+        assert_eq!(db.next_id(), 4);
+        assert_eq!(db.get_by_id(1), Some(&note(1, "first")));
+        assert_eq!(db.position_by_id(3), Some(0));
+        assert_eq!(db.get_by_id(42), None);
+
+        db.get_mut_by_id(2)
+            .unwrap()
+            .set_text("edited".to_string())
+            .unwrap();
+        assert_eq!(
+            db.update_by_id(3, note(0, "updated")).unwrap(),
+            Some(note(3, "third"))
+        );
+        assert_eq!(db.remove_by_id(1).unwrap(), Some(note(1, "first")));
+        assert_eq!(db.remove_by_id(1).unwrap(), None);
+        assert_eq!(db.update_by_id(1, note(0, "missing")).unwrap(), None);
+
+        assert_eq!(
+            changes(dir.path()),
+            [
+                "A\t0\t1\tfirst",
+                "A\t1\t2\tsecond",
+                "A\t0\t3\tthird",
+                "E\t#2\ttext\tedited",
+                "E\t#3\ttext\tupdated",
+                "D\t#1",
+            ]
+        );
+    }
+    let db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.get_all(), &[note(3, "updated"), note(2, "edited")]);
+}
+
+#[test]
+fn ids_are_never_given_again() {
+    let dir = tempfile::tempdir().unwrap();
+    {
+        let mut db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+        db.push(note(0, "a")).unwrap();
+        db.push(note(0, "b")).unwrap();
+        db.pop().unwrap();
+    }
+    // The removed id is only in the changes file
+    let mut db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.next_id(), 3);
+    db.pop().unwrap();
+    drop(db);
+
+    // Then only in the marker of the base file
+    let mut db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+    assert!(db.is_empty());
+    db.push(note(0, "c")).unwrap();
+    assert_eq!(db[0], note(3, "c"));
+}
+
+#[test]
+fn rollback_with_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<Note, ManualSave> = AironeDb::open(dir.path()).unwrap();
+    db.push(note(0, "a")).unwrap();
+    db.push(note(0, "b")).unwrap();
+    db.save().unwrap();
+
+    db.update_by_id(2, note(0, "changed")).unwrap();
+    db.remove_by_id(1);
+    db.rollback();
+    assert_eq!(db.get_all(), &[note(1, "a"), note(2, "b")]);
+}
+
+#[test]
+fn id_lookups_follow_moved_elements() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<Note, ManualSave> = AironeDb::open(dir.path()).unwrap();
+    for text in ["a", "b", "c", "d"] {
+        db.push(note(0, text)).unwrap();
+    }
+    db.save().unwrap();
+
+    db.insert(1, note(0, "e")).unwrap();
+    db.remove(2);
+    db.pop();
+    db.get_mut_by_id(3)
+        .unwrap()
+        .set_text("edited".to_string())
+        .unwrap();
+    let moved = [
+        (1, Some(0)),
+        (2, None),
+        (3, Some(2)),
+        (4, None),
+        (5, Some(1)),
+    ];
+    for (id, position) in moved {
+        assert_eq!(db.position_by_id(id), position, "{}", id);
+    }
+    db.rollback();
+    for (id, position) in [
+        (1, Some(0)),
+        (2, Some(1)),
+        (3, Some(2)),
+        (4, Some(3)),
+        (5, None),
+    ] {
+        assert_eq!(db.position_by_id(id), position, "{}", id);
+    }
+
+    db.insert(1, note(0, "e")).unwrap();
+    db.remove(2);
+    db.pop();
+    db.remove_by_id(1);
+    db.save().unwrap();
+    drop(db);
+
+    // The log addresses the elements by id
+    let db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.get_all(), &[note(6, "e"), note(3, "c")]);
+    assert_eq!(db.position_by_id(3), Some(1));
+}
+
+#[test]
+fn ids_are_added_to_older_files() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path().join("Note.csv"), "text\na\nb\n").unwrap();
+
+    let db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.get_all(), &[note(1, "a"), note(2, "b")]);
+    assert_eq!(db.next_id(), 3);
+    drop(db);
+
+    assert_eq!(
+        read_to_string(dir.path().join("Note.csv")).unwrap(),
+        "#airone-format\t4\t1\t3\nid\ttext\n1\ta\n2\tb\n"
+    );
+}
+
+#[test]
+fn duplicate_ids_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path().join("Note.csv"), "id\ttext\n1\ta\n1\tb\n").unwrap();
+
+    let result = AironeDb::<Note>::open(dir.path());
+    assert!(matches!(result, Err(Error::DuplicateId { id: 1 })));
+}
+
+#[test]
+fn ids_are_not_settable() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+    db.push(note(0, "a")).unwrap();
+    assert!(matches!(
+        db.set(0, "id", 7u64),
+        Err(Error::IdNotWritable { column, .. }) if column == "id"
+    ));
+
+    let mut db = db.set_save_mode::<ManualSave>().unwrap();
+    assert!(db.set(0, "id", 7u64).is_err());
+    db.save().unwrap();
+    drop(db);
+
+    let db: AironeDb<Note> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.get_all(), &[note(1, "a")]);
+}
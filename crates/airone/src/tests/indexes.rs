@@ -0,0 +1,187 @@
+extern crate self as airone;
+
+use crate::error::Error;
+use crate::prelude::*;
+use save_mode::ManualSave;
+use std::fs::write;
+
+#[derive(Debug, PartialEq, AironeDbDerive)]
+struct User {
+    #[airone(unique)]
+    email: String,
+    #[airone(index)]
+    age: u32,
+    name: String,
+}
+
+fn user(email: &str, age: u32, name: &str) -> User {
+    User {
+        email: email.to_string(),
+        age,
+        name: name.to_string(),
+    }
+}
+
+fn names(users: Vec<&User>) -> Vec<&str> {
+    users.iter().map(|u| u.name.as_str()).collect()
+}
+
+#[test]
+fn lookups_follow_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<User> = AironeDb::open(dir.path()).unwrap();
+    db.push(user("a@x.org", 30, "a")).unwrap();
+    db.push(user("b@x.org", 20, "b")).unwrap();
+    db.insert(0, user("c@x.org", 30, "c")).unwrap();
+
+    assert_eq!(db.find_by_email("b@x.org"), Some(&user("b@x.org", 20, "b")));
+    assert_eq!(db.find_by_email("missing"), None);
+    assert_eq!(names(db.find_by_age(&30)), ["c", "a"]);
+    assert_eq!(names(db.range_by_age(..)), ["b", "c", "a"]);
+    assert_eq!(names(db.range_by_age(25..)), ["c", "a"]);
+    assert_eq!(
+        names(db.range_by_email("b".to_string().."z".to_string())),
+        ["b", "c"]
+    );
+
+    db.get_mut(0).unwrap().set_age(10).unwrap();
+    db.get_mut(0)
+        .unwrap()
+        .set_email("d@x.org".to_string())
+        .unwrap();
+    assert_eq!(names(db.range_by_age(..)), ["c", "b", "a"]);
+    assert_eq!(db.find_by_email("c@x.org"), None);
+    assert_eq!(db.find_by_email("d@x.org").unwrap().name, "c");
+
+    db.remove(1).unwrap();
+    assert_eq!(names(db.range_by_age(..)), ["c", "b"]);
+    assert_eq!(db.find_by_email("b@x.org").unwrap().name, "b");
+    db.retain(|u| u.age > 15).unwrap();
+    assert_eq!(names(db.range_by_age(..)), ["b"]);
+    drop(db);
+
+    // Indexes are built at startup
+    let db: AironeDb<User> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.find_by_email("b@x.org").unwrap().name, "b");
+    assert_eq!(names(db.find_by_age(&20)), ["b"]);
+}
+
+#[test]
+fn unique_violations_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<User> = AironeDb::open(dir.path()).unwrap();
+    db.push(user("a@x.org", 30, "a")).unwrap();
+    db.push(user("b@x.org", 20, "b")).unwrap();
+
+    let violation = db.push(user("a@x.org", 40, "duplicate"));
+    assert!(
+        matches!(violation, Err(Error::UniqueViolation { column, value, .. }) if column == "email" && value == "a@x.org")
+    );
+    assert!(db
+        .get_mut(1)
+        .unwrap()
+        .set_email("a@x.org".to_string())
+        .is_err());
+    // Setting the same value again is fine
+    db.get_mut(1)
+        .unwrap()
+        .set_email("b@x.org".to_string())
+        .unwrap();
+
+    assert!(db
+        .modify(1, |u| {
+            u.email = "a@x.org".to_string();
+            u.age = 99;
+        })
+        .is_err());
+    assert_eq!(db.get(1), Some(&user("b@x.org", 20, "b")));
+    assert_eq!(names(db.find_by_age(&99)), Vec::<&str>::new());
+
+    let mut others = vec![user("c@x.org", 1, "c"), user("a@x.org", 2, "duplicate")];
+    assert!(db.append(&mut others).is_err());
+    assert_eq!(names(db.range_by_age(..)), ["c", "b", "a"]);
+    assert_eq!(others, [user("a@x.org", 2, "duplicate")]);
+    drop(db);
+
+    // Rejected changes are not persisted
+    let db: AironeDb<User> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(
+        db.get_all(),
+        &[
+            user("a@x.org", 30, "a"),
+            user("b@x.org", 20, "b"),
+            user("c@x.org", 1, "c")
+        ]
+    );
+}
+
+#[test]
+fn unique_violations_are_returned_in_manual_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<User, ManualSave> = AironeDb::open(dir.path()).unwrap();
+    db.push(user("a@x.org", 30, "a")).unwrap();
+    db.push(user("b@x.org", 20, "b")).unwrap();
+
+    assert!(db.insert(0, user("a@x.org", 40, "duplicate")).is_err());
+    assert!(db
+        .get_mut(1)
+        .unwrap()
+        .set_email("a@x.org".to_string())
+        .is_err());
+    assert!(db.modify(1, |u| u.email = "a@x.org".to_string()).is_err());
+    assert!(db
+        .query_mut()
+        .filter(User::name().eq("b"))
+        .update(|u| u.set_email("a@x.org".to_string()))
+        .is_err());
+    assert!(db
+        .extend([user("c@x.org", 1, "c"), user("a@x.org", 2, "duplicate")].into_iter())
+        .is_err());
+    assert_eq!(names(db.range_by_age(..)), ["c", "b", "a"]);
+    db.save().unwrap();
+    drop(db);
+
+    let db: AironeDb<User> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(
+        db.get_all(),
+        &[
+            user("a@x.org", 30, "a"),
+            user("b@x.org", 20, "b"),
+            user("c@x.org", 1, "c")
+        ]
+    );
+}
+
+#[test]
+fn indexes_follow_rollback() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<User, ManualSave> = AironeDb::open(dir.path()).unwrap();
+    db.push(user("a@x.org", 30, "a")).unwrap();
+    db.save().unwrap();
+
+    db.push(user("b@x.org", 20, "b")).unwrap();
+    db.get_mut(0)
+        .unwrap()
+        .set_email("c@x.org".to_string())
+        .unwrap();
+    assert!(db.push(user("b@x.org", 20, "duplicate")).is_err());
+    db.rollback();
+
+    assert_eq!(names(db.range_by_age(..)), ["a"]);
+    assert_eq!(db.find_by_email("a@x.org").unwrap().name, "a");
+    assert_eq!(db.find_by_email("b@x.org"), None);
+    assert_eq!(db.find_by_email("c@x.org"), None);
+    db.push(user("b@x.org", 20, "b")).unwrap();
+}
+
+#[test]
+fn duplicates_in_files_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path().join("User.csv"),
+        "email\tage\tname\na@x.org\t1\ta\na@x.org\t2\tb\n",
+    )
+    .unwrap();
+    let result = AironeDb::<User>::open(dir.path());
+    assert!(matches!(result, Err(Error::UniqueViolation { .. })));
+}
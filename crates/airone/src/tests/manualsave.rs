@@ -15,7 +15,7 @@ fn manualsave() {
         let mut d: AironeDb<Abc, ManualSave> =
             AironeDb::new_with_custom_name("manualsave").unwrap();
 
-        d.push(Abc { field1: 0 });
+        d.push(Abc { field1: 0 }).unwrap();
         d.save().unwrap();
 
         d.pop();
@@ -1,3 +1,32 @@
+extern crate self as airone;
+
+use crate::prelude::*;
+
+mod crash;
+mod errors;
+mod fields;
+mod format;
+mod ids;
+mod indexes;
 mod manualsave;
+mod open;
+mod query;
+mod schema;
+#[cfg(feature = "serde")]
+mod serde_record;
 #[allow(clippy::module_inception)]
 mod tests;
+
+/// Struct of the tests about the files themselves
+#[derive(Debug, PartialEq, AironeDbDerive)]
+struct Counter {
+    n: i32,
+    label: String,
+}
+
+fn counter(n: i32, label: &str) -> Counter {
+    Counter {
+        n,
+        label: label.to_string(),
+    }
+}
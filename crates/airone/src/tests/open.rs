@@ -0,0 +1,78 @@
+extern crate self as airone;
+
+use crate::error::Error;
+use crate::prelude::*;
+use save_mode::ManualSave;
+use std::fs::read_to_string;
+
+#[derive(Debug, PartialEq, AironeDbDerive)]
+struct Item {
+    name: String,
+    quantity: u32,
+}
+
+fn item(name: &str, quantity: u32) -> Item {
+    Item {
+        name: name.to_string(),
+        quantity,
+    }
+}
+
+#[test]
+fn open_in_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<Item> = AironeDb::open(dir.path()).unwrap();
+    db.push(item("apple", 3)).unwrap();
+    drop(db);
+
+    assert!(dir.path().join("Item.csv").is_file());
+    assert!(dir.path().join("Item.changes.csv").is_file());
+    let db: AironeDb<Item> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.get_all(), &[item("apple", 3)]);
+}
+
+#[test]
+fn open_with_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = OpenOptions::new()
+        .directory(dir.path())
+        .name("stock")
+        .extension("tsv")
+        .buffer_mode(BufferMode::AlwaysFlush)
+        .save_mode::<ManualSave>();
+    let mut db = AironeDb::<Item, _>::open(options).unwrap();
+    db.push(item("pear", 1)).unwrap();
+    db.save().unwrap();
+
+    // Flushed without closing the database
+    let changes = read_to_string(dir.path().join("stock.changes.tsv")).unwrap();
+    assert!(changes.contains("\tpear\t1\t"));
+    assert!(dir.path().join("stock.tsv").is_file());
+    drop(db);
+
+    let db: AironeDb<Item> = AironeDb::open(
+        OpenOptions::new()
+            .directory(dir.path())
+            .name("stock")
+            .extension("tsv"),
+    )
+    .unwrap();
+    assert_eq!(db.get_all(), &[item("pear", 1)]);
+    // Other names are other databases
+    let db: AironeDb<Item> = AironeDb::open(dir.path()).unwrap();
+    assert!(db.is_empty());
+}
+
+#[test]
+fn missing_directory_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    let result = AironeDb::<Item>::open(&missing);
+    assert!(matches!(result, Err(Error::DirectoryNotFound { path }) if path == missing));
+    assert!(!missing.exists());
+
+    let file = dir.path().join("file");
+    std::fs::write(&file, "").unwrap();
+    let result = AironeDb::<Item>::open(&file);
+    assert!(matches!(result, Err(Error::DirectoryNotFound { .. })));
+}
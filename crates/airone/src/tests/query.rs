@@ -0,0 +1,185 @@
+extern crate self as airone;
+
+use crate::prelude::*;
+use save_mode::ManualSave;
+use std::fs::read_to_string;
+
+#[derive(Debug, PartialEq, AironeDbDerive)]
+struct Animal {
+    #[airone(index)]
+    age: u32,
+    name: String,
+    weight_kg: f64,
+}
+
+fn animal(age: u32, name: &str, weight_kg: f64) -> Animal {
+    Animal {
+        age,
+        name: name.to_string(),
+        weight_kg,
+    }
+}
+
+fn names(animals: Vec<&Animal>) -> Vec<&str> {
+    animals.iter().map(|a| a.name.as_str()).collect()
+}
+
+fn fill(db: &mut AironeDb<Animal>) {
+    db.push(animal(3, "cat", 4.0)).unwrap();
+    db.push(animal(7, "dog", 20.0)).unwrap();
+    db.push(animal(1, "mouse", 0.1)).unwrap();
+    db.push(animal(7, "horse", 400.0)).unwrap();
+    db.push(animal(5, "fox", 6.0)).unwrap();
+}
+
+#[test]
+fn filter_order_and_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<Animal> = AironeDb::open(dir.path()).unwrap();
+    fill(&mut db);
+
+    assert_eq!(
+        names(db.query().filter(Animal::age().gt(3u32)).all()),
+        ["dog", "horse", "fox"]
+    );
+    assert_eq!(
+        names(
+            db.query()
+                .filter(Animal::age().gt(3u32))
+                .order_by(Animal::name())
+                .limit(2)
+                .all()
+        ),
+        ["dog", "fox"]
+    );
+    assert_eq!(
+        names(
+            db.query()
+                .order_by_desc(Animal::age())
+                .order_by(Animal::weight_kg())
+                .offset(1)
+                .all()
+        ),
+        ["horse", "fox", "cat", "mouse"]
+    );
+    assert_eq!(
+        names(
+            db.query()
+                .filter(Animal::age().eq(7u32).or(Animal::name().eq("cat")))
+                .filter(!Animal::weight_kg().gt(100.0))
+                .all()
+        ),
+        ["cat", "dog"]
+    );
+    assert_eq!(
+        names(
+            db.query()
+                .filter(Animal::age().ge(3u32).and(Animal::age().lt(7u32)))
+                .all()
+        ),
+        ["cat", "fox"]
+    );
+    assert_eq!(
+        names(
+            db.query()
+                .filter(Animal::name().matches(|n| n.len() == 3))
+                .filter(Animal::age().le(5u32))
+                .all()
+        ),
+        ["cat", "fox"]
+    );
+    assert_eq!(db.query().filter(Animal::age().ne(7u32)).count(), 3);
+    assert_eq!(db.query().filter(Animal::age().gt(7u32)).first(), None);
+    // Inverted bounds select nothing instead of panicking
+    assert_eq!(
+        db.query()
+            .filter(Animal::age().gt(5u32))
+            .filter(Animal::age().lt(2u32))
+            .count(),
+        0
+    );
+    assert_eq!(
+        db.query()
+            .filter(Predicate::new(|a: &Animal| a.age > 5))
+            .order_by(Animal::weight_kg())
+            .first(),
+        Some(&animal(7, "dog", 20.0))
+    );
+
+    assert_eq!(AnimalField::ALL.len(), 3);
+    assert_eq!(AnimalField::WeightKg.column(), "weight_kg");
+    assert_eq!(Animal::weight_kg().column(), "weight_kg");
+}
+
+#[test]
+fn bulk_changes_are_logged() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<Animal> = AironeDb::open(dir.path()).unwrap();
+    fill(&mut db);
+
+    let updated = db
+        .query_mut()
+        .filter(Animal::age().eq(7u32))
+        .update(|animal| animal.set_weight_kg(1.0))
+        .unwrap();
+    assert_eq!(updated, 2);
+    let removed = db
+        .query_mut()
+        .filter(Animal::age().lt(4u32))
+        .delete()
+        .unwrap();
+    assert_eq!(removed, [animal(3, "cat", 4.0), animal(1, "mouse", 0.1)]);
+    // The index follows the removals
+    assert_eq!(
+        names(db.query().filter(Animal::age().ge(5u32)).all()),
+        ["dog", "horse", "fox"]
+    );
+    drop(db);
+
+    let changes = read_to_string(dir.path().join("Animal.changes.csv")).unwrap();
+    let operations: Vec<&str> = changes
+        .lines()
+        .filter_map(|line| line.split('\t').next())
+        .filter(|op| ["A", "E", "D"].contains(op))
+        .collect();
+    assert_eq!(operations, ["A", "A", "A", "A", "A", "E", "E", "D", "D"]);
+
+    let db: AironeDb<Animal> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(
+        db.get_all(),
+        &[
+            animal(7, "dog", 1.0),
+            animal(7, "horse", 1.0),
+            animal(5, "fox", 6.0)
+        ]
+    );
+}
+
+#[test]
+fn bulk_changes_in_manual_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<Animal, ManualSave> = AironeDb::open(dir.path()).unwrap();
+    db.push(animal(3, "cat", 4.0)).unwrap();
+    db.push(animal(7, "dog", 20.0)).unwrap();
+    db.save().unwrap();
+
+    let updated = db
+        .query_mut()
+        .filter(Animal::name().eq("dog"))
+        .update(|animal| animal.set_age(8))
+        .unwrap();
+    assert_eq!(updated, 1);
+    assert_eq!(
+        db.query_mut().filter(Animal::age().gt(5u32)).delete().len(),
+        1
+    );
+    db.rollback();
+    assert_eq!(
+        db.get_all(),
+        &[animal(3, "cat", 4.0), animal(7, "dog", 20.0)]
+    );
+    assert_eq!(
+        names(db.query().filter(Animal::age().eq(7u32)).all()),
+        ["dog"]
+    );
+}
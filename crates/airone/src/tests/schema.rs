@@ -0,0 +1,139 @@
+extern crate self as airone;
+
+use crate::error::Error;
+use crate::prelude::*;
+use std::fs::{read_to_string, write};
+
+/// Current version of a struct first saved
+/// with the `name`, `age` and `legacy_id` columns
+#[derive(Debug, PartialEq, AironeDbDerive)]
+#[airone(drop = "legacy_id")]
+struct Pet {
+    #[airone(rename_from = "name")]
+    nickname: String,
+    age: u8,
+    #[airone(default = "dog".to_string())]
+    species: String,
+    #[airone(default)]
+    vaccinated: bool,
+}
+
+#[derive(Debug, PartialEq, AironeDbDerive)]
+#[airone(default)]
+struct Settings {
+    key: String,
+    enabled: bool,
+    retries: u32,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            key: String::new(),
+            enabled: true,
+            retries: 3,
+        }
+    }
+}
+
+fn pet(nickname: &str, age: u8, species: &str, vaccinated: bool) -> Pet {
+    Pet {
+        nickname: nickname.to_string(),
+        age,
+        species: species.to_string(),
+        vaccinated,
+    }
+}
+
+#[test]
+fn reordered_columns() {
+    let dir = tempfile::tempdir().unwrap();
+    write(
+        dir.path().join("Pet.csv"),
+        "#airone-format\t3\t4\nage\tvaccinated\tspecies\tnickname\n2\ttrue\tcat\tmiao\n",
+    )
+    .unwrap();
+
+    let db: AironeDb<Pet> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.get_all(), &[pet("miao", 2, "cat", true)]);
+    drop(db);
+
+    assert_eq!(
+        read_to_string(dir.path().join("Pet.csv")).unwrap(),
+        "#airone-format\t4\t5\nnickname\tage\tspecies\tvaccinated\nmiao\t2\tcat\ttrue\n"
+    );
+}
+
+#[test]
+fn older_layout_is_rewritten() {
+    let dir = tempfile::tempdir().unwrap();
+    // Legacy files, changes follow the layout of the base file
+    write(
+        dir.path().join("Pet.csv"),
+        "name\tage\tlegacy_id\nfido\t3\t27\nrex\t5\t28\n",
+    )
+    .unwrap();
+    write(
+        dir.path().join("Pet.changes.csv"),
+        "A\t2\tbobby\t1\t29\nE\t0\tname\tfuffy\nE\t1\tlegacy_id\t30\nE\t2\tage\t2\nD\t1\n",
+    )
+    .unwrap();
+
+    let expected = [pet("fuffy", 3, "dog", false), pet("bobby", 2, "dog", false)];
+    let db: AironeDb<Pet> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db.get_all(), &expected);
+    drop(db);
+
+    assert_eq!(
+        read_to_string(dir.path().join("Pet.csv")).unwrap(),
+        "#airone-format\t4\t1\nnickname\tage\tspecies\tvaccinated\nfuffy\t3\tdog\tfalse\nbobby\t2\tdog\tfalse\n"
+    );
+
+    // The new layout keeps working
+    {
+        let mut db: AironeDb<Pet> = AironeDb::open(dir.path()).unwrap();
+        db.get_mut(1).unwrap().set_vaccinated(true).unwrap();
+    }
+    let db: AironeDb<Pet> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(db[1], pet("bobby", 2, "dog", true));
+}
+
+#[test]
+fn default_of_the_struct() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path().join("Settings.csv"), "key\nfirst\n").unwrap();
+
+    let db: AironeDb<Settings> = AironeDb::open(dir.path()).unwrap();
+    assert_eq!(
+        db.get_all(),
+        &[Settings {
+            key: "first".to_string(),
+            enabled: true,
+            retries: 3,
+        }]
+    );
+}
+
+#[test]
+fn undeclared_changes_are_a_mismatch() {
+    for header in [
+        // `age` has no default
+        "name\tlegacy_id",
+        // `color` is not dropped
+        "name\tage\tcolor",
+        // Both the old and the new name
+        "name\tnickname\tage",
+    ] {
+        let dir = tempfile::tempdir().unwrap();
+        let base_file = dir.path().join("Pet.csv");
+        let base = format!("{}\n", header);
+        write(&base_file, &base).unwrap();
+        let result = AironeDb::<Pet>::open(dir.path());
+        assert!(
+            matches!(result, Err(Error::SchemaMismatch { .. })),
+            "{}",
+            header
+        );
+        // Nothing is rewritten
+        assert_eq!(read_to_string(&base_file).unwrap(), base);
+    }
+}
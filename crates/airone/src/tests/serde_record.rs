@@ -0,0 +1,138 @@
+extern crate self as airone;
+
+use crate::error::Error;
+use crate::prelude::*;
+use ::serde::{Deserialize, Serialize};
+use save_mode::ManualSave;
+use std::fs::read_to_string;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum Kind {
+    Plain,
+    Scored(u8),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Note {
+    title: String,
+    stars: Option<u32>,
+    tags: Vec<String>,
+    kind: Kind,
+    at: Point,
+}
+
+fn notes() -> Vec<Note> {
+    vec![
+        Note {
+            title: "42".to_string(),
+            stars: None,
+            tags: vec![],
+            kind: Kind::Plain,
+            at: Point { x: 1, y: 2 },
+        },
+        Note {
+            title: "Hello, \"world\"\n\t\\".to_string(),
+            stars: Some(5),
+            tags: vec!["a,b".to_string(), "".to_string()],
+            kind: Kind::Scored(3),
+            at: Point { x: -1, y: 0 },
+        },
+    ]
+}
+
+#[test]
+fn records_are_persisted() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<SerdeRecord<Note>> = AironeDb::open(dir.path()).unwrap();
+    db.extend(notes().into_iter().map(SerdeRecord)).unwrap();
+    db.modify(1, |note| {
+        note.stars = None;
+        note.kind = Kind::Plain;
+    })
+    .unwrap();
+    drop(db);
+
+    // Only the changed fields are logged
+    let changes = read_to_string(dir.path().join("Note.changes.csv")).unwrap();
+    let edits: Vec<&str> = changes
+        .lines()
+        .filter(|l| l.starts_with("E\t"))
+        .map(|l| l.split('\t').nth(2).unwrap())
+        .collect();
+    assert_eq!(edits, ["stars", "kind"]);
+    assert!(changes.contains("{\"Scored\":3}"));
+
+    let db: AironeDb<SerdeRecord<Note>> = AironeDb::open(dir.path()).unwrap();
+    let mut expected = notes();
+    expected[1].stars = None;
+    expected[1].kind = Kind::Plain;
+    let loaded: Vec<Note> = db.iter().map(|r| r.0.clone()).collect();
+    assert_eq!(loaded, expected);
+    drop(db);
+
+    let base = read_to_string(dir.path().join("Note.csv")).unwrap();
+    assert!(base.contains("title\tstars\ttags\tkind\tat\n"));
+    assert!(base.contains("42\t\\N\t[]\tPlain\t{\"x\":1,\"y\":2}\n"));
+}
+
+#[test]
+fn json_and_csv_round_trip() {
+    let export_dir = tempfile::tempdir().unwrap();
+    let import_dir = tempfile::tempdir().unwrap();
+    let mut db: AironeDb<SerdeRecord<Note>> = AironeDb::open(export_dir.path()).unwrap();
+    db.extend(notes().into_iter().map(SerdeRecord)).unwrap();
+
+    let mut json = Vec::new();
+    db.export_json(&mut json).unwrap();
+    let mut csv = Vec::new();
+    db.export_csv(&mut csv).unwrap();
+    assert!(String::from_utf8(csv.clone())
+        .unwrap()
+        .starts_with("title,stars,tags,kind,at\n42,,[],Plain,\"{\"\"x\"\":1,\"\"y\"\":2}\"\n"));
+
+    let mut imported: AironeDb<SerdeRecord<Note>, ManualSave> =
+        AironeDb::open(import_dir.path()).unwrap();
+    assert_eq!(imported.import_json(json.as_slice()).unwrap(), 2);
+    assert_eq!(imported.import_csv(csv.as_slice()).unwrap(), 2);
+    let loaded: Vec<Note> = imported.iter().map(|r| r.0.clone()).collect();
+    assert_eq!(loaded, [notes(), notes()].concat());
+
+    assert!(imported.import_json(&b"[{\"title\": 1}]"[..]).is_err());
+    assert!(imported.import_csv(&b"title,stars\n\"x"[..]).is_err());
+    assert_eq!(imported.len(), 4);
+}
+
+#[derive(Serialize, Deserialize)]
+struct Pair(i32, i32);
+
+#[derive(Serialize, Deserialize)]
+struct Flattened {
+    title: String,
+    #[serde(flatten)]
+    at: Point,
+}
+
+#[test]
+fn unsupported_shapes_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(matches!(
+        AironeDb::<SerdeRecord<Pair>>::open(dir.path()),
+        Err(Error::UnsupportedShape { type_name }) if type_name.ends_with("Pair")
+    ));
+    assert!(matches!(
+        AironeDb::<SerdeRecord<Flattened>>::open(dir.path()),
+        Err(Error::UnsupportedShape { .. })
+    ));
+    // Serialized as a string
+    assert!(matches!(
+        AironeDb::<SerdeRecord<std::net::Ipv4Addr>>::open(dir.path()),
+        Err(Error::UnsupportedShape { .. })
+    ));
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
@@ -431,17 +431,20 @@ fn add_remove_rollback() {
         a: 0,
         n: 5.0,
         testo: "Abc".to_string(),
-    });
+    })
+    .unwrap();
     db.push(Animal {
         a: 1,
         n: std::f64::consts::SQRT_2,
         testo: "Se\nco\nndo".to_string(),
-    });
+    })
+    .unwrap();
     db.push(Animal {
         a: 2,
         n: std::f64::consts::SQRT_2,
         testo: "Se\nco\nndo".to_string(),
-    });
+    })
+    .unwrap();
     db.save().unwrap();
 
     db.remove(1);
@@ -461,7 +464,8 @@ fn add_remove_rollback() {
             n: std::f64::consts::SQRT_2,
             testo: "Se\nco\nndo".to_string(),
         },
-    );
+    )
+    .unwrap();
     assert_eq!(db.len(), 4);
     assert_eq!(*db[0].get_a(), 0);
     assert_eq!(*db[1].get_a(), 56);
@@ -483,7 +487,8 @@ fn add_remove_rollback() {
             n: std::f64::consts::SQRT_2,
             testo: "Se\nco\nndo".to_string(),
         },
-    );
+    )
+    .unwrap();
     db.save().unwrap();
     db.remove(1);
     assert_eq!(db.len(), 3);
@@ -0,0 +1,135 @@
+use axum::{
+    extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Response},
+};
+use components::events::UserEvents;
+use components::state;
+use log::{debug, warn};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_stream::Stream;
+
+// /*
+//  Events
+// */
+// [get] /users/{user_id}/events
+// WebSocket is used when upgrade is requested, otherwise Server-Sent Events
+pub async fn get_user_events(
+    State(app_state): State<state::AppState>,
+    Path(user_id): Path<i32>,
+    ws: Option<WebSocketUpgrade>,
+) -> Response {
+    let events = UserEvents::new(app_state.app_usecase.subscribe_todo_events(), user_id);
+    match ws {
+        Some(ws) => ws.on_upgrade(move |socket| send_events(socket, events, user_id)),
+        None => Sse::new(event_stream(events, user_id))
+            .keep_alive(KeepAlive::default())
+            .into_response(),
+    }
+}
+
+// event_stream() converts events into SSE
+// lagged subscriber gets `error` event and should list todos again before reconnecting
+fn event_stream(
+    mut events: UserEvents,
+    user_id: i32,
+) -> impl Stream<Item = Result<Event, axum::Error>> {
+    async_stream::stream! {
+        loop {
+            match events.recv().await {
+                Ok(event) => yield Event::default().event(event.kind.name()).json_data(&event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("{} todo events are dropped for user {}", skipped, user_id);
+                    yield Event::default()
+                        .event("error")
+                        .json_data(serde_json::json!({ "error": lagged_message(skipped) }));
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+// send_events() sends events as text messages until client closes
+// lagged subscriber gets close frame with 1011
+async fn send_events(mut socket: WebSocket, mut events: UserEvents, user_id: i32) {
+    loop {
+        tokio::select! {
+            received = events.recv() => match received {
+                Ok(event) => {
+                    let text = match serde_json::to_string(&event) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            warn!("todo event can not be serialized: {:?}", e);
+                            continue;
+                        }
+                    };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("{} todo events are dropped for user {}", skipped, user_id);
+                    let frame = CloseFrame {
+                        code: close_code::ERROR,
+                        reason: lagged_message(skipped).into(),
+                    };
+                    let _ = socket.send(Message::Close(Some(frame))).await;
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            // messages from client are ignored, ping is answered by axum
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    debug!("websocket of todo events is closed for user {}", user_id);
+}
+
+fn lagged_message(skipped: u64) -> String {
+    format!("{} events are dropped", skipped)
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use components::events::{EventBus, TodoEvent, TodoEventKind};
+    use tokio_stream::StreamExt;
+
+    fn event(user_id: i32, todo_id: i32) -> TodoEvent {
+        TodoEvent {
+            kind: TodoEventKind::Deleted,
+            user_id,
+            todo_id,
+            todo: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_stream() {
+        let bus = EventBus::new(2);
+        let stream = event_stream(UserEvents::new(bus.subscribe(), 1), 1);
+        tokio::pin!(stream);
+
+        // events of other users are filtered out
+        bus.publish(event(2, 10));
+        bus.publish(event(1, 11));
+        assert!(stream.next().await.unwrap().is_ok());
+
+        // lagged subscriber gets error event and stream is closed
+        for todo_id in 0..3 {
+            bus.publish(event(1, todo_id));
+        }
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(stream.next().await.is_none());
+    }
+}
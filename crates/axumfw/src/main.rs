@@ -1,10 +1,13 @@
+use axum::Router;
 use components::args;
 use components::registry;
 use components::toml;
 use log::info;
+use std::sync::Arc;
 
 // local
 use axumfw::middlewares::common::apply_middleware;
+use axumfw::multiplex::MultiplexService;
 use axumfw::routes;
 
 // refer to
@@ -37,13 +40,22 @@ async fn main() {
     let admin_state = reg.create_admin_state();
     let app_state = reg.create_app_state();
 
+    let host = reg.conf.server.host.clone();
+    let port = reg.conf.server.port;
+
     // get router
     let router = routes::get_api_router(auth_state, admin_state, app_state);
+    // gRPC and gRPC-Web are served on the same port, routed by content-type
+    let router = if reg.conf.grpc.multiplex {
+        info!("gRPC is enabled on {}:{}", host, port);
+        let grpc = grpc_servers::routes::new_grpc_service(Arc::new(reg)).unwrap(); // may panic
+        Router::new().fallback_service(MultiplexService::new(router, grpc))
+    } else {
+        router
+    };
     // apply common middleware
     let router = apply_middleware(router);
 
-    let host = reg.conf.server.host;
-    let port = reg.conf.server.port;
     info!("run server {}:{}", host, port);
 
     // run server with hyper
@@ -33,7 +33,9 @@ pub fn apply_middleware(router: Router) -> Router {
                     // Don't compress gRPC
                     .and(NotForContentType::GRPC)
                     // Don't compress images
-                    .and(NotForContentType::IMAGES),
+                    .and(NotForContentType::IMAGES)
+                    // Don't compress Server-Sent Events
+                    .and(NotForContentType::SSE),
             ),
         )
 }
@@ -0,0 +1,171 @@
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::BoxError;
+use http::header::CONTENT_TYPE;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tower::{Service, ServiceExt};
+
+// refer to
+// https://github.com/tokio-rs/axum/blob/main/examples/rest-grpc-multiplex/src/multiplex_service.rs
+
+// is_grpc_request() is true for gRPC and gRPC-Web
+// - application/grpc, application/grpc+proto
+// - application/grpc-web, application/grpc-web-text
+fn is_grpc_request(req: &Request) -> bool {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .map(|content_type| content_type.as_bytes().starts_with(b"application/grpc"))
+        .unwrap_or(false)
+}
+
+// internal_error() is for error of gRPC service itself, not gRPC status
+fn internal_error(e: BoxError) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Fatal error: {:?}", e),
+    )
+        .into_response()
+}
+
+// MultiplexService routes requests to REST router or gRPC service by content-type
+// services are cloned per request like routes of axum::Router, so it is always ready
+// body of gRPC request is boxed as tonic services accept only tonic::body::BoxBody
+#[derive(Clone)]
+pub struct MultiplexService<R, G> {
+    rest: R,
+    grpc: G,
+}
+
+impl<R, G> MultiplexService<R, G> {
+    pub fn new(rest: R, grpc: G) -> Self {
+        Self { rest, grpc }
+    }
+}
+
+impl<R, G> Service<Request> for MultiplexService<R, G>
+where
+    R: Service<Request, Error = Infallible> + Clone + Send + 'static,
+    R::Response: IntoResponse,
+    R::Future: Send + 'static,
+    G: Service<http::Request<BoxBody>> + Clone + Send + 'static,
+    G::Error: Into<BoxError>,
+    G::Response: IntoResponse,
+    G::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        if is_grpc_request(&req) {
+            let future = self.grpc.clone().oneshot(req.map(tonic::body::boxed));
+            Box::pin(async move {
+                match future.await {
+                    Ok(res) => Ok(res.into_response()),
+                    Err(e) => Ok(internal_error(e.into())),
+                }
+            })
+        } else {
+            let future = self.rest.clone().oneshot(req);
+            Box::pin(async move { Ok(future.await?.into_response()) })
+        }
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Version;
+    use axum::routing::post;
+    use axum::Router;
+    use components::{registry::Registry, toml};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_multiplex() {
+        let rest = Router::new().route("/", post(|| async { "rest" }));
+        let grpc = Router::new().route("/", post(|| async { "grpc" }));
+        let service = MultiplexService::new(rest, grpc);
+
+        for (content_type, expected) in [
+            (Some("application/json"), "rest"),
+            (None, "rest"),
+            (Some("application/grpc"), "grpc"),
+            (Some("application/grpc+proto"), "grpc"),
+            (Some("application/grpc-web"), "grpc"),
+            (Some("application/grpc-web-text"), "grpc"),
+        ] {
+            let mut builder = Request::builder().method("POST").uri("/");
+            if let Some(content_type) = content_type {
+                builder = builder.header(CONTENT_TYPE, content_type);
+            }
+            let res = service
+                .clone()
+                .oneshot(builder.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, expected, "{:?}", content_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multiplex_grpc_service() {
+        let mut config = toml::load_config("../../config/local.toml").unwrap();
+        config.db.enabled = false;
+        let reg = Registry::new(config).await.unwrap();
+        let rest = Router::new().route("/", post(|| async { "rest" }));
+        let grpc = grpc_servers::routes::new_grpc_service(Arc::new(reg)).unwrap();
+        let service = MultiplexService::new(rest, grpc);
+
+        // request without token is rejected by JWT layer of gRPC service,
+        // native gRPC clients always speak HTTP/2
+        for (content_type, version) in [
+            ("application/grpc", Version::HTTP_2),
+            ("application/grpc-web", Version::HTTP_11),
+        ] {
+            let req = Request::builder()
+                .method("POST")
+                .version(version)
+                .uri("/todo.TodoService/ListTodos")
+                .header(CONTENT_TYPE, content_type)
+                .body(Body::empty())
+                .unwrap();
+            let res = service.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK, "{}", content_type);
+            assert_eq!(
+                res.headers().get("grpc-status").unwrap(),
+                "16",
+                "{}",
+                content_type
+            );
+        }
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/")
+            .body(Body::empty())
+            .unwrap();
+        let res = service.oneshot(req).await.unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "rest");
+    }
+}
@@ -69,6 +69,7 @@ fn api_admin_router(auth_state: state::AuthState, admin_state: state::AdminState
 // - Add Todo: [POST] `/app/users/{user_id}/todos`
 // - Update Todo for Specific User: [PUT] `/app/users/{user_id}/todos/{id}`
 // - Remove Todo for Specific User: [DELETE] `/app/users/{user_id}/todos/{id}`
+// - Watch Todo Events for Specific User: [GET] `/app/users/{user_id}/events` (SSE or WebSocket)
 
 fn api_app_login_router(state: state::AuthState) -> Router {
     Router::new()
@@ -90,6 +91,19 @@ fn api_app_users_todo_router(auth_state: state::AuthState, app_state: state::App
         .with_state(app_state)
 }
 
+fn api_app_users_events_router(auth_state: state::AuthState, app_state: state::AppState) -> Router {
+    Router::new()
+        .route(
+            "/users/:user_id/events",
+            get(handlers::events::get_user_events),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            auth_state.clone(),
+            auth_jwt::mw_app_auth_jwt,
+        ))
+        .with_state(app_state)
+}
+
 fn api_app_users_todo_id_router(
     auth_state: state::AuthState,
     app_state: state::AppState,
@@ -124,6 +138,10 @@ fn api_app_router(auth_state: state::AuthState, app_state: state::AppState) -> R
         .merge(api_app_users_todo_id_router(
             auth_state.clone(),
             app_state.clone(),
+        ))
+        .merge(api_app_users_events_router(
+            auth_state.clone(),
+            app_state.clone(),
         ));
 
     Router::new().nest("/app", internal)
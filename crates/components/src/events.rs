@@ -0,0 +1,176 @@
+use crate::schemas::sea_orm::todos as db_todos;
+use log::debug;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+// events kept for slow subscribers, older ones are dropped
+const CAPACITY: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl TodoEventKind {
+    // name is used as event type of SSE
+    pub fn name(&self) -> &'static str {
+        match self {
+            TodoEventKind::Created => "created",
+            TodoEventKind::Updated => "updated",
+            TodoEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+// TodoEvent is published after each mutation of todo
+// todo is None for deleted event
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TodoEvent {
+    pub kind: TodoEventKind,
+    pub user_id: i32,
+    pub todo_id: i32,
+    pub todo: Option<db_todos::Model>,
+}
+
+// EventBus is an in-process broadcast channel shared by usecases and servers
+#[derive(Clone, Debug)]
+pub struct EventBus {
+    sender: broadcast::Sender<TodoEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(CAPACITY)
+    }
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    // publish() never fails even if nobody subscribes
+    pub fn publish(&self, event: TodoEvent) {
+        if self.sender.send(event).is_err() {
+            debug!("no subscriber for todo event");
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TodoEvent> {
+        self.sender.subscribe()
+    }
+}
+
+// UserEvents receives events of a user only
+// it is shared by SSE and WebSocket handlers of REST API servers
+pub struct UserEvents {
+    receiver: broadcast::Receiver<TodoEvent>,
+    user_id: i32,
+}
+
+impl UserEvents {
+    pub fn new(receiver: broadcast::Receiver<TodoEvent>, user_id: i32) -> Self {
+        Self { receiver, user_id }
+    }
+
+    // recv() returns Lagged error once events are dropped for slow subscriber,
+    // and Closed error when the bus is dropped
+    pub async fn recv(&mut self) -> Result<TodoEvent, broadcast::error::RecvError> {
+        loop {
+            let event = self.receiver.recv().await?;
+            if event.user_id == self.user_id {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(todo_id: i32) -> TodoEvent {
+        TodoEvent {
+            kind: TodoEventKind::Deleted,
+            user_id: 1,
+            todo_id,
+            todo: None,
+        }
+    }
+
+    #[test]
+    fn test_event_bus() {
+        let bus = EventBus::new(2);
+        // without subscriber
+        bus.publish(event(1));
+
+        let mut first = bus.subscribe();
+        let mut second = bus.clone().subscribe();
+        bus.publish(event(2));
+        assert_eq!(first.try_recv().unwrap(), event(2));
+        assert_eq!(second.try_recv().unwrap(), event(2));
+
+        // slow subscriber misses old events
+        for todo_id in 3..6 {
+            bus.publish(event(todo_id));
+        }
+        assert!(matches!(
+            first.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(1))
+        ));
+        assert_eq!(first.try_recv().unwrap(), event(4));
+    }
+
+    #[tokio::test]
+    async fn test_user_events() {
+        let bus = EventBus::new(2);
+        let mut events = UserEvents::new(bus.subscribe(), 1);
+
+        // events of other users are skipped
+        let mut other = event(10);
+        other.user_id = 2;
+        bus.publish(other);
+        bus.publish(event(11));
+        assert_eq!(events.recv().await.unwrap(), event(11));
+
+        // lagged
+        for todo_id in 0..3 {
+            bus.publish(event(todo_id));
+        }
+        assert!(matches!(
+            events.recv().await,
+            Err(broadcast::error::RecvError::Lagged(1))
+        ));
+
+        // closed
+        drop(bus);
+        assert_eq!(events.recv().await.unwrap(), event(1));
+        assert_eq!(events.recv().await.unwrap(), event(2));
+        assert!(matches!(
+            events.recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        ));
+    }
+
+    #[test]
+    fn test_kind_name() {
+        for kind in [
+            TodoEventKind::Created,
+            TodoEventKind::Updated,
+            TodoEventKind::Deleted,
+        ] {
+            assert_eq!(
+                toml::Value::try_from(&kind).unwrap().as_str(),
+                Some(kind.name())
+            );
+        }
+    }
+}
@@ -2,6 +2,7 @@ pub mod args;
 pub mod dbs;
 pub mod entities;
 pub mod errors;
+pub mod events;
 pub mod hashes;
 pub mod jwt;
 pub mod rand;
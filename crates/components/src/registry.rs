@@ -10,6 +10,7 @@ cfg_if! {
     compile_error!("One of the features 'pbkdf2' or 'argon2' or 'scrypt' must be enabled");
   }
 }
+use crate::events;
 use crate::jwt;
 use crate::repositories::{todos, users};
 use crate::state;
@@ -100,6 +101,8 @@ cfg_if! {
             pub users_repo: Arc<dyn users::UserRepository>,
             pub jwt: Arc<dyn jwt::JWT>,
             pub hash: pbkdf2::HashPbkdf2,
+            pub db_conn: Option<DatabaseConnection>,
+            pub events: events::EventBus,
         }
     } else if #[cfg(feature = "argon2")] {
         pub struct Registry {
@@ -108,6 +111,8 @@ cfg_if! {
             pub users_repo: Arc<dyn users::UserRepository>,
             pub jwt: Arc<dyn jwt::JWT>,
             pub hash: argon2::HashArgon2,
+            pub db_conn: Option<DatabaseConnection>,
+            pub events: events::EventBus,
         }
     } else if #[cfg(feature = "scrypt")] {
         pub struct Registry {
@@ -116,6 +121,8 @@ cfg_if! {
             pub users_repo: Arc<dyn users::UserRepository>,
             pub jwt: Arc<dyn jwt::JWT>,
             pub hash: scrypt::HashScrypt,
+            pub db_conn: Option<DatabaseConnection>,
+            pub events: events::EventBus,
         }
     } else {
         compile_error!("One of the features 'pbkdf2' or 'argon2' or 'scrypt' must be enabled");
@@ -144,9 +151,19 @@ impl Registry {
             users_repo,
             jwt,
             hash,
+            db_conn,
+            events: events::EventBus::default(),
         })
     }
 
+    // ping_db() checks DB is ready, always Ok for memory repositories
+    pub async fn ping_db(&self) -> Result<(), DbErr> {
+        match &self.db_conn {
+            Some(conn) => conn.ping().await,
+            None => Ok(()),
+        }
+    }
+
     fn create_auth_usecase(&self) -> Arc<dyn auth::AuthUsecase> {
         Arc::new(auth::AuthAction::new(
             self.users_repo.clone(),
@@ -169,6 +186,7 @@ impl Registry {
         Arc::new(app::AppAction::new(
             self.todos_repo.clone(),
             self.users_repo.clone(),
+            self.events.clone(),
         ))
     }
 
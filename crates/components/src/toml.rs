@@ -74,6 +74,8 @@ pub struct Config {
     #[allow(dead_code)]
     pub logger: Logger,
     pub db: PostgreSQL,
+    #[serde(default)]
+    pub grpc: Grpc,
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq)]
@@ -105,6 +107,39 @@ pub struct PostgreSQL {
     pub password: String,
 }
 
+// gRPC server, every field is optional
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Grpc {
+    pub address: String,
+    pub web_address: String,
+    pub tls: Option<GrpcTls>,
+    // bytes, tonic default is 4MiB for decoding and unlimited for encoding
+    pub max_decoding_message_size: Option<usize>,
+    pub max_encoding_message_size: Option<usize>,
+    // axumfw serves gRPC and gRPC-Web on [server] port together with REST API
+    pub multiplex: bool,
+}
+
+impl Default for Grpc {
+    fn default() -> Self {
+        Self {
+            address: "[::1]:50051".to_string(),
+            web_address: "127.0.0.1:3000".to_string(),
+            tls: None,
+            max_decoding_message_size: None,
+            max_encoding_message_size: None,
+            multiplex: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GrpcTls {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
 // print loaded config
 #[allow(dead_code)]
 pub fn print_loaded_config(file_name: &str) {
@@ -167,8 +202,67 @@ mod tests {
                 user: "admin".to_string(),
                 password: "admin".to_string(),
             },
+            grpc: Grpc {
+                address: "[::1]:50051".to_string(),
+                web_address: "127.0.0.1:3000".to_string(),
+                tls: None,
+                max_decoding_message_size: Some(4 * 1024 * 1024),
+                max_encoding_message_size: Some(4 * 1024 * 1024),
+                multiplex: false,
+            },
         };
 
         assert_eq!(conf, expected_config);
     }
+
+    #[test]
+    fn test_grpc_config() {
+        let base = r#"
+            app_name = "api-server"
+            [server]
+            host = "0.0.0.0"
+            port = 8080
+            [jwt]
+            kind = "none"
+            duration_min = 30
+            [logger]
+            service = "api-server"
+            level = "info"
+            [db]
+            enabled = false
+            host = ""
+            dbname = ""
+            user = ""
+            password = ""
+        "#;
+
+        // [grpc] section can be omitted
+        let conf: Config = toml::from_str(base).unwrap();
+        assert_eq!(conf.grpc, Grpc::default());
+
+        let conf: Config = toml::from_str(&format!(
+            r#"{base}
+            [grpc]
+            address = "0.0.0.0:50051"
+            max_decoding_message_size = 1024
+            multiplex = true
+            [grpc.tls]
+            cert_path = "./certs/cert.pem"
+            key_path = "./certs/key.pem"
+            "#
+        ))
+        .unwrap();
+        assert_eq!(conf.grpc.address, "0.0.0.0:50051");
+        assert_eq!(conf.grpc.web_address, "127.0.0.1:3000");
+        assert_eq!(conf.grpc.max_decoding_message_size, Some(1024));
+        assert_eq!(conf.grpc.max_encoding_message_size, None);
+        assert!(conf.grpc.multiplex);
+        assert_eq!(
+            conf.grpc.tls,
+            Some(GrpcTls {
+                cert_path: "./certs/cert.pem".to_string(),
+                key_path: "./certs/key.pem".to_string(),
+            })
+        );
+    }
 }
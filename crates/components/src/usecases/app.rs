@@ -1,9 +1,11 @@
 use crate::entities::todos;
+use crate::events::{EventBus, TodoEvent, TodoEventKind};
 use crate::repositories::{todos as repo_todos, users as repo_users};
 use crate::schemas::sea_orm::todos as db_todos;
 use anyhow;
 use async_trait::async_trait;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 
 #[async_trait]
 pub trait AppUsecase: Send + Sync + 'static {
@@ -25,23 +27,43 @@ pub trait AppUsecase: Send + Sync + 'static {
         todo_body: todos::TodoUpdateBody,
     ) -> anyhow::Result<Option<db_todos::Model>>;
     async fn delete_user_todo(&self, user_id: i32, todo_id: i32) -> anyhow::Result<u64>;
+    // events are published after each mutation
+    fn subscribe_todo_events(&self) -> broadcast::Receiver<TodoEvent>;
 }
 
 pub struct AppAction {
     pub todos_repo: Arc<dyn repo_todos::TodoRepository>,
     pub users_repo: Arc<dyn repo_users::UserRepository>, // for now, not used anywhere
+    pub events: EventBus,
 }
 
 impl AppAction {
     pub fn new(
         todos_repo: Arc<dyn repo_todos::TodoRepository>,
         users_repo: Arc<dyn repo_users::UserRepository>,
+        events: EventBus,
     ) -> Self {
         AppAction {
             todos_repo,
             users_repo,
+            events,
         }
     }
+
+    fn publish(
+        &self,
+        kind: TodoEventKind,
+        user_id: i32,
+        todo_id: i32,
+        todo: Option<&db_todos::Model>,
+    ) {
+        self.events.publish(TodoEvent {
+            kind,
+            user_id,
+            todo_id,
+            todo: todo.cloned(),
+        });
+    }
 }
 
 #[async_trait]
@@ -66,6 +88,7 @@ impl AppUsecase for AppAction {
         todo_body: todos::TodoBody,
     ) -> anyhow::Result<db_todos::Model> {
         let ret = self.todos_repo.create(user_id, todo_body).await?;
+        self.publish(TodoEventKind::Created, ret.user_id, ret.id, Some(&ret));
         Ok(ret)
         // Ok(db_todos::Model {
         //     id: 1,
@@ -106,6 +129,9 @@ impl AppUsecase for AppAction {
         todo_body: todos::TodoUpdateBody,
     ) -> anyhow::Result<Option<db_todos::Model>> {
         let ret = self.todos_repo.update(todo_id, todo_body).await?;
+        if let Some(todo) = &ret {
+            self.publish(TodoEventKind::Updated, todo.user_id, todo.id, Some(todo));
+        }
         Ok(ret)
         // Ok(db_todos::Model {
         //     id: 1,
@@ -118,8 +144,15 @@ impl AppUsecase for AppAction {
         // })
     }
 
-    async fn delete_user_todo(&self, _user_id: i32, todo_id: i32) -> anyhow::Result<u64> {
+    async fn delete_user_todo(&self, user_id: i32, todo_id: i32) -> anyhow::Result<u64> {
         let ret = self.todos_repo.delete(todo_id).await?;
+        if ret > 0 {
+            self.publish(TodoEventKind::Deleted, user_id, todo_id, None);
+        }
         Ok(ret)
     }
+
+    fn subscribe_todo_events(&self) -> broadcast::Receiver<TodoEvent> {
+        self.events.subscribe()
+    }
 }
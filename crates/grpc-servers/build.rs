@@ -1,6 +1,20 @@
 // https://crates.io/crates/tonic-build
 // https://github.com/hyperium/tonic/blob/master/examples/helloworld-tutorial.md
+use std::env;
+use std::path::PathBuf;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/helloworld.proto")?;
+    // descriptor set is used by reflection service
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("grpc_descriptor.bin"))
+        .compile_protos(
+            &[
+                "proto/helloworld.proto",
+                "proto/user.proto",
+                "proto/todo.proto",
+            ],
+            &["proto"],
+        )?;
     Ok(())
 }
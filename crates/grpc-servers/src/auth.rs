@@ -0,0 +1,252 @@
+use components::jwt::PayLoad;
+use components::state;
+use log::debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::{Request, Status};
+use tower::{Layer, Service};
+
+// methods callable without token
+const PUBLIC_METHODS: [&str; 2] = ["/user.UserService/Login", "/user.UserService/AdminLogin"];
+
+// JwtLayer checks `authorization: Bearer xxx` metadata by method path
+// - user.UserService: is_admin must be true except login
+// - todo.TodoService: any valid token, owner is checked by authorize_owner() in service
+// - others: no check
+// validated PayLoad is inserted into request extensions
+#[derive(Clone)]
+pub struct JwtLayer {
+    auth_state: state::AuthState,
+}
+
+impl JwtLayer {
+    pub fn new(auth_state: state::AuthState) -> Self {
+        Self { auth_state }
+    }
+}
+
+impl<S> Layer<S> for JwtLayer {
+    type Service = JwtService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JwtService {
+            inner,
+            auth_state: self.auth_state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JwtService<S> {
+    inner: S,
+    auth_state: state::AuthState,
+}
+
+impl<S> JwtService<S> {
+    // authenticate() returns PayLoad when method requires token
+    fn authenticate(
+        &self,
+        path: &str,
+        headers: &http::HeaderMap,
+    ) -> Result<Option<PayLoad>, Status> {
+        if self.auth_state.auth_usecase.is_jwt_disable() || PUBLIC_METHODS.contains(&path) {
+            return Ok(None);
+        }
+        let admin_only = path.starts_with("/user.UserService/");
+        if !admin_only && !path.starts_with("/todo.TodoService/") {
+            return Ok(None);
+        }
+
+        let token = get_token(headers)?;
+        let payload = self
+            .auth_state
+            .auth_usecase
+            .validate_token(token)
+            .map_err(|e| {
+                debug!("token in invalid: {}", e);
+                Status::unauthenticated("token is invalid")
+            })?;
+        if admin_only && !payload.is_admin {
+            return Err(Status::permission_denied("admin only"));
+        }
+        Ok(Some(payload))
+    }
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for JwtService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<ReqBody>) -> Self::Future {
+        match self.authenticate(req.uri().path(), req.headers()) {
+            Ok(Some(payload)) => {
+                req.extensions_mut().insert(payload);
+            }
+            Ok(None) => {}
+            // error is returned as gRPC status without calling service
+            Err(status) => return Box::pin(async move { Ok(status.into_http()) }),
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+// get_token() retrieves token from `authorization: Bearer xxx`
+fn get_token(headers: &http::HeaderMap) -> Result<&str, Status> {
+    let token = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| Status::unauthenticated("token is missing"))?;
+    debug!("token: {}", token);
+    Ok(token)
+}
+
+// authorize_owner() allows admin or owner of user_id
+// PayLoad is missing only when JWT is disabled
+pub fn authorize_owner<T>(req: &Request<T>, user_id: i32) -> Result<(), Status> {
+    match req.extensions().get::<PayLoad>() {
+        Some(payload) if payload.is_admin || payload.user_id as i32 == user_id => Ok(()),
+        Some(_) => Err(Status::permission_denied(format!(
+            "no permission for user {}",
+            user_id
+        ))),
+        None => Ok(()),
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use components::hashes::pbkdf2::HashPbkdf2;
+    use components::jwt::SimpleJWT;
+    use components::repositories::users::UserRepositoryForMemory;
+    use components::usecases::auth::AuthAction;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn auth_state() -> state::AuthState {
+        state::AuthState {
+            auth_usecase: Arc::new(AuthAction::new(
+                Arc::new(UserRepositoryForMemory::new()),
+                HashPbkdf2::default(),
+                Arc::new(SimpleJWT::new(30)),
+            )),
+        }
+    }
+
+    // call() returns grpc-status and user_id in PayLoad seen by inner service
+    async fn call(
+        auth_state: &state::AuthState,
+        path: &str,
+        token: Option<&str>,
+    ) -> (Option<String>, Option<u64>) {
+        let inner = tower::service_fn(|req: http::Request<()>| async move {
+            let mut res = http::Response::new(tonic::body::empty_body());
+            if let Some(payload) = req.extensions().get::<PayLoad>() {
+                res.headers_mut()
+                    .insert("x-user-id", payload.user_id.to_string().parse().unwrap());
+            }
+            Ok::<_, Infallible>(res)
+        });
+        let mut builder = http::Request::builder().uri(path);
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {token}"));
+        }
+        let res = JwtLayer::new(auth_state.clone())
+            .layer(inner)
+            .oneshot(builder.body(()).unwrap())
+            .await
+            .unwrap();
+        let header = |name: &str| {
+            res.headers()
+                .get(name)
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+        (
+            header("grpc-status"),
+            header("x-user-id").map(|v| v.parse().unwrap()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_jwt_layer() {
+        let auth_state = auth_state();
+        let usecase = &auth_state.auth_usecase;
+        let admin = usecase
+            .generate_token(1, "admin@example.com", true)
+            .unwrap();
+        let user = usecase
+            .generate_token(2, "user@example.com", false)
+            .unwrap();
+        let unauthenticated = Some("16".to_string());
+        let permission_denied = Some("7".to_string());
+
+        // public
+        assert_eq!(
+            call(&auth_state, "/user.UserService/Login", None).await,
+            (None, None)
+        );
+        assert_eq!(
+            call(&auth_state, "/helloworld.Greeter/SayHello", None).await,
+            (None, None)
+        );
+
+        // admin only
+        let path = "/user.UserService/ListUsers";
+        assert_eq!(call(&auth_state, path, Some(&admin)).await, (None, Some(1)));
+        assert_eq!(
+            call(&auth_state, path, Some(&user)).await,
+            (permission_denied, None)
+        );
+        assert_eq!(
+            call(&auth_state, path, None).await,
+            (unauthenticated.clone(), None)
+        );
+
+        // any user
+        let path = "/todo.TodoService/ListTodos";
+        assert_eq!(call(&auth_state, path, Some(&user)).await, (None, Some(2)));
+        assert_eq!(
+            call(&auth_state, path, Some("invalid")).await,
+            (unauthenticated, None)
+        );
+    }
+
+    #[test]
+    fn test_authorize_owner() {
+        let request = |payload: Option<PayLoad>| {
+            let mut req = Request::new(());
+            if let Some(payload) = payload {
+                req.extensions_mut().insert(payload);
+            }
+            req
+        };
+        let admin = PayLoad::new(1, "admin@example.com".into(), true);
+        let user = PayLoad::new(2, "user@example.com".into(), false);
+
+        assert!(authorize_owner(&request(Some(admin)), 2).is_ok());
+        assert!(authorize_owner(&request(Some(user.clone())), 2).is_ok());
+        assert_eq!(
+            authorize_owner(&request(Some(user)), 3).unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+        assert!(authorize_owner(&request(None), 3).is_ok());
+    }
+}
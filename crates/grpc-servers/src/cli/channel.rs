@@ -0,0 +1,55 @@
+use clap::ValueEnum;
+use http_body_util::BodyExt;
+use hyper_util::rt::TokioExecutor;
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::codegen::{Body, Bytes};
+use tonic::transport::Channel;
+use tonic::Status;
+use tonic_web::GrpcWebClientLayer;
+use tower::util::BoxCloneService;
+use tower::{BoxError, ServiceBuilder, ServiceExt};
+
+/// Transport protocol
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Transport {
+    /// gRPC over HTTP/2
+    Grpc,
+    /// gRPC-Web over HTTP/1.1
+    GrpcWeb,
+}
+
+// GrpcChannel hides transport so that same clients are used for both
+pub type GrpcChannel = BoxCloneService<http::Request<BoxBody>, http::Response<BoxBody>, BoxError>;
+
+// box_body() unifies response body of grpc-web client with Channel
+fn box_body<B>(res: http::Response<B>) -> http::Response<BoxBody>
+where
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    res.map(|body| {
+        body.map_err(|e| Status::from_error(e.into()))
+            .boxed_unsync()
+    })
+}
+
+pub async fn connect(address: &str, transport: Transport) -> anyhow::Result<GrpcChannel> {
+    match transport {
+        Transport::Grpc => {
+            let channel = Channel::from_shared(address.to_string())?.connect().await?;
+            Ok(BoxCloneService::new(channel.map_err(BoxError::from)))
+        }
+        Transport::GrpcWeb => {
+            // grpc-web needs hyper client directly instead of Channel
+            let client =
+                hyper_util::client::legacy::Client::builder(TokioExecutor::new()).build_http();
+            let service = ServiceBuilder::new()
+                .layer(GrpcWebClientLayer::new())
+                .service(client)
+                .map_response(box_body)
+                .map_err(BoxError::from);
+            Ok(BoxCloneService::new(service))
+        }
+    }
+}
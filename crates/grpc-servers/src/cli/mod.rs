@@ -0,0 +1,480 @@
+pub mod channel;
+pub mod output;
+pub mod token;
+
+use crate::pb::todo::todo_service_client::TodoServiceClient;
+use crate::pb::todo::{
+    CreateTodoRequest, DeleteTodoRequest, GetTodoRequest, ListTodosRequest, TodoStatus,
+    UpdateTodoRequest, WatchTodosRequest,
+};
+use crate::pb::user::user_service_client::UserServiceClient;
+use crate::pb::user::{
+    CreateUserRequest, DeleteUserRequest, GetUserRequest, ListUsersRequest, LoginRequest,
+    UpdateUserRequest,
+};
+use channel::{GrpcChannel, Transport};
+use clap::{Parser, Subcommand, ValueEnum};
+use output::Output;
+use std::path::PathBuf;
+use tonic::codegen::http::Uri;
+use tonic::metadata::MetadataValue;
+use tonic::Request;
+
+/// gRPC client for user and todo services
+#[derive(Debug, Parser)]
+#[command(name = "grpc-client")]
+#[command(about = "A gRPC client for UserService and TodoService", long_about = None)]
+pub struct Args {
+    /// Server address
+    #[arg(short, long, default_value = "http://[::1]:50051")]
+    pub address: String,
+
+    /// Transport protocol
+    #[arg(short, long, value_enum, default_value_t = Transport::Grpc)]
+    pub transport: Transport,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Output::Table)]
+    pub output: Output,
+
+    /// Token cache file [default: ~/.grpc-client/token]
+    #[arg(long)]
+    pub token_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Login and cache token for following commands
+    Login {
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        password: String,
+        /// Login as admin
+        #[arg(long)]
+        admin: bool,
+    },
+    /// Remove cached token
+    Logout,
+    /// Manage todos of a user
+    #[command(subcommand)]
+    Todos(TodoCommand),
+    /// Manage users, admin only
+    #[command(subcommand)]
+    Users(UserCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TodoCommand {
+    /// Show todos
+    List {
+        #[arg(long)]
+        user_id: i32,
+        /// 0 means server default
+        #[arg(long, default_value_t = 0)]
+        page_size: i32,
+        /// next_page_token of previous page
+        #[arg(long, default_value = "")]
+        page_token: String,
+    },
+    /// Show a todo
+    Get {
+        #[arg(long)]
+        user_id: i32,
+        #[arg(long)]
+        todo_id: i32,
+    },
+    /// Add a todo
+    Create {
+        #[arg(long)]
+        user_id: i32,
+        #[arg(long)]
+        title: String,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long, value_enum, default_value_t = Status::Pending)]
+        status: Status,
+    },
+    /// Update given fields of a todo
+    Update {
+        #[arg(long)]
+        user_id: i32,
+        #[arg(long)]
+        todo_id: i32,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long, value_enum)]
+        status: Option<Status>,
+    },
+    /// Remove a todo
+    Delete {
+        #[arg(long)]
+        user_id: i32,
+        #[arg(long)]
+        todo_id: i32,
+    },
+    /// Show changes of todos until interrupted
+    Watch {
+        #[arg(long)]
+        user_id: i32,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UserCommand {
+    /// Show users
+    List {
+        /// 0 means server default
+        #[arg(long, default_value_t = 0)]
+        page_size: i32,
+        /// next_page_token of previous page
+        #[arg(long, default_value = "")]
+        page_token: String,
+    },
+    /// Show a user
+    Get {
+        #[arg(long)]
+        user_id: i32,
+    },
+    /// Add a user
+    Create {
+        #[arg(long)]
+        first_name: String,
+        #[arg(long)]
+        last_name: String,
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        password: String,
+        #[arg(long)]
+        admin: bool,
+    },
+    /// Update given fields of a user
+    Update {
+        #[arg(long)]
+        user_id: i32,
+        #[arg(long)]
+        first_name: Option<String>,
+        #[arg(long)]
+        last_name: Option<String>,
+        #[arg(long)]
+        email: Option<String>,
+        #[arg(long)]
+        password: Option<String>,
+        #[arg(long)]
+        admin: Option<bool>,
+    },
+    /// Remove a user
+    Delete {
+        #[arg(long)]
+        user_id: i32,
+    },
+}
+
+/// Todo status
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Status {
+    Pending,
+    Doing,
+    Done,
+    Canceled,
+}
+
+impl From<Status> for TodoStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Pending => TodoStatus::Pending,
+            Status::Doing => TodoStatus::Doing,
+            Status::Done => TodoStatus::Done,
+            Status::Canceled => TodoStatus::Canceled,
+        }
+    }
+}
+
+// Client keeps connection and cached token
+struct Client {
+    channel: GrpcChannel,
+    origin: Uri,
+    token: Option<String>,
+}
+
+impl Client {
+    fn users(&self) -> UserServiceClient<GrpcChannel> {
+        UserServiceClient::with_origin(self.channel.clone(), self.origin.clone())
+    }
+
+    fn todos(&self) -> TodoServiceClient<GrpcChannel> {
+        TodoServiceClient::with_origin(self.channel.clone(), self.origin.clone())
+    }
+
+    // request() adds `authorization: Bearer xxx` after login
+    fn request<T>(&self, message: T) -> anyhow::Result<Request<T>> {
+        let mut request = Request::new(message);
+        if let Some(token) = &self.token {
+            let value: MetadataValue<_> = format!("Bearer {}", token).parse()?;
+            request.metadata_mut().insert("authorization", value);
+        }
+        Ok(request)
+    }
+}
+
+// run() executes command and prints result to stdout
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let token_file = args.token_file.unwrap_or_else(token::default_path);
+    if let Command::Logout = args.command {
+        return token::remove(&token_file);
+    }
+
+    let client = Client {
+        channel: channel::connect(&args.address, args.transport).await?,
+        origin: args.address.parse()?,
+        token: token::load(&token_file)?,
+    };
+    let output = args.output;
+
+    match args.command {
+        Command::Login {
+            email,
+            password,
+            admin,
+        } => {
+            let request = LoginRequest { email, password };
+            let mut users = client.users();
+            let reply = if admin {
+                users.admin_login(request).await?
+            } else {
+                users.login(request).await?
+            }
+            .into_inner();
+            token::save(&token_file, &reply.token)?;
+            println!("{}", output.render(&reply));
+        }
+        Command::Logout => unreachable!("logout is done without connection"),
+        Command::Todos(command) => run_todo(&client, output, command).await?,
+        Command::Users(command) => run_user(&client, output, command).await?,
+    }
+    Ok(())
+}
+
+async fn run_todo(client: &Client, output: Output, command: TodoCommand) -> anyhow::Result<()> {
+    let mut todos = client.todos();
+    match command {
+        TodoCommand::List {
+            user_id,
+            page_size,
+            page_token,
+        } => {
+            let reply = todos
+                .list_todos(client.request(ListTodosRequest {
+                    user_id,
+                    page_size,
+                    page_token,
+                })?)
+                .await?
+                .into_inner();
+            println!(
+                "{}",
+                output.render_page(
+                    "todos",
+                    &reply.todos,
+                    &reply.next_page_token,
+                    reply.total_size
+                )
+            );
+        }
+        TodoCommand::Get { user_id, todo_id } => {
+            let todo = todos
+                .get_todo(client.request(GetTodoRequest { user_id, todo_id })?)
+                .await?
+                .into_inner();
+            println!("{}", output.render(&todo));
+        }
+        TodoCommand::Create {
+            user_id,
+            title,
+            description,
+            status,
+        } => {
+            let todo = todos
+                .create_todo(client.request(CreateTodoRequest {
+                    user_id,
+                    title,
+                    description,
+                    status: TodoStatus::from(status) as i32,
+                })?)
+                .await?
+                .into_inner();
+            println!("{}", output.render(&todo));
+        }
+        TodoCommand::Update {
+            user_id,
+            todo_id,
+            title,
+            description,
+            status,
+        } => {
+            let todo = todos
+                .update_todo(client.request(UpdateTodoRequest {
+                    user_id,
+                    todo_id,
+                    title,
+                    description,
+                    status: status.map(|s| TodoStatus::from(s) as i32),
+                })?)
+                .await?
+                .into_inner();
+            println!("{}", output.render(&todo));
+        }
+        TodoCommand::Delete { user_id, todo_id } => {
+            let reply = todos
+                .delete_todo(client.request(DeleteTodoRequest { user_id, todo_id })?)
+                .await?
+                .into_inner();
+            println!("{}", output.render(&reply));
+        }
+        TodoCommand::Watch { user_id } => {
+            let mut stream = todos
+                .watch_todos(client.request(WatchTodosRequest { user_id })?)
+                .await?
+                .into_inner();
+            let mut header = true;
+            while let Some(event) = stream.message().await? {
+                println!("{}", output.render_stream(&event, header));
+                header = false;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_user(client: &Client, output: Output, command: UserCommand) -> anyhow::Result<()> {
+    let mut users = client.users();
+    match command {
+        UserCommand::List {
+            page_size,
+            page_token,
+        } => {
+            let reply = users
+                .list_users(client.request(ListUsersRequest {
+                    page_size,
+                    page_token,
+                })?)
+                .await?
+                .into_inner();
+            println!(
+                "{}",
+                output.render_page(
+                    "users",
+                    &reply.users,
+                    &reply.next_page_token,
+                    reply.total_size
+                )
+            );
+        }
+        UserCommand::Get { user_id } => {
+            let user = users
+                .get_user(client.request(GetUserRequest { user_id })?)
+                .await?
+                .into_inner();
+            println!("{}", output.render(&user));
+        }
+        UserCommand::Create {
+            first_name,
+            last_name,
+            email,
+            password,
+            admin,
+        } => {
+            let user = users
+                .create_user(client.request(CreateUserRequest {
+                    first_name,
+                    last_name,
+                    email,
+                    password,
+                    is_admin: admin,
+                })?)
+                .await?
+                .into_inner();
+            println!("{}", output.render(&user));
+        }
+        UserCommand::Update {
+            user_id,
+            first_name,
+            last_name,
+            email,
+            password,
+            admin,
+        } => {
+            let user = users
+                .update_user(client.request(UpdateUserRequest {
+                    user_id,
+                    first_name,
+                    last_name,
+                    email,
+                    password,
+                    is_admin: admin,
+                })?)
+                .await?
+                .into_inner();
+            println!("{}", output.render(&user));
+        }
+        UserCommand::Delete { user_id } => {
+            let reply = users
+                .delete_user(client.request(DeleteUserRequest { user_id })?)
+                .await?
+                .into_inner();
+            println!("{}", output.render(&reply));
+        }
+    }
+    Ok(())
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let args = Args::try_parse_from([
+            "grpc-client",
+            "--transport",
+            "grpc-web",
+            "-o",
+            "json",
+            "todos",
+            "update",
+            "--user-id",
+            "1",
+            "--todo-id",
+            "2",
+            "--status",
+            "done",
+        ])
+        .unwrap();
+        assert_eq!(args.address, "http://[::1]:50051");
+        assert_eq!(args.transport, Transport::GrpcWeb);
+        assert_eq!(args.output, Output::Json);
+        assert!(matches!(
+            args.command,
+            Command::Todos(TodoCommand::Update {
+                user_id: 1,
+                todo_id: 2,
+                title: None,
+                status: Some(Status::Done),
+                ..
+            })
+        ));
+
+        // required option is missing
+        assert!(Args::try_parse_from(["grpc-client", "todos", "list"]).is_err());
+    }
+}
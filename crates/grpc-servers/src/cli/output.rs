@@ -0,0 +1,283 @@
+use crate::pb::todo::{DeleteTodoReply, Todo, TodoEvent, TodoEventKind, TodoStatus};
+use crate::pb::user::{DeleteUserReply, LoginReply, User};
+use chrono::DateTime;
+use clap::ValueEnum;
+use prost_types::Timestamp;
+use serde_json::{json, Map, Value};
+
+/// Output format
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Output {
+    /// Aligned columns
+    Table,
+    /// JSON, one line per event for watch
+    Json,
+}
+
+// Record is printed as a row of table or as JSON object
+pub trait Record {
+    fn fields(&self) -> Vec<(&'static str, Value)>;
+}
+
+impl Output {
+    pub fn render<R: Record>(&self, record: &R) -> String {
+        match self {
+            Output::Table => table(&[record.fields()]),
+            Output::Json => pretty(object(record.fields())),
+        }
+    }
+
+    // render_page() shows next_page_token only when there are more items
+    pub fn render_page<R: Record>(
+        &self,
+        key: &str,
+        records: &[R],
+        next_page_token: &str,
+        total_size: i32,
+    ) -> String {
+        match self {
+            Output::Table => {
+                let rows: Vec<_> = records.iter().map(Record::fields).collect();
+                let mut out = table(&rows);
+                if !next_page_token.is_empty() {
+                    out.push_str(&format!("\nnext_page_token: {}", next_page_token));
+                }
+                out
+            }
+            Output::Json => {
+                let items: Vec<Value> = records.iter().map(|r| object(r.fields())).collect();
+                pretty(json!({
+                    key: items,
+                    "next_page_token": next_page_token,
+                    "total_size": total_size,
+                }))
+            }
+        }
+    }
+
+    // render_stream() is for items received one by one
+    // width of columns is unknown, so values are separated by tab
+    pub fn render_stream<R: Record>(&self, record: &R, header: bool) -> String {
+        let fields = record.fields();
+        match self {
+            Output::Table => {
+                let row = fields.iter().map(|(_, v)| cell(v)).collect::<Vec<_>>();
+                if header {
+                    let names = fields.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+                    format!("{}\n{}", names.join("\t"), row.join("\t"))
+                } else {
+                    row.join("\t")
+                }
+            }
+            Output::Json => object(fields).to_string(),
+        }
+    }
+}
+
+fn object(fields: Vec<(&'static str, Value)>) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+fn pretty(value: Value) -> String {
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+// table() aligns columns by the widest cell, header is taken from the first row
+fn table(rows: &[Vec<(&'static str, Value)>]) -> String {
+    let Some(first) = rows.first() else {
+        return "no items".to_string();
+    };
+    let mut lines: Vec<Vec<String>> = vec![first.iter().map(|(k, _)| k.to_string()).collect()];
+    lines.extend(
+        rows.iter()
+            .map(|row| row.iter().map(|(_, v)| cell(v)).collect()),
+    );
+
+    let mut widths = vec![0; first.len()];
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn timestamp(ts: &Option<Timestamp>) -> Value {
+    ts.as_ref()
+        .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32))
+        .map(|dt| Value::String(dt.to_rfc3339()))
+        .unwrap_or(Value::Null)
+}
+
+// enum_name() converts `TODO_STATUS_PENDING` into `pending`
+fn enum_name(name: &str, prefix: &str) -> Value {
+    Value::String(name.trim_start_matches(prefix).to_lowercase())
+}
+
+fn status(status: i32) -> Value {
+    TodoStatus::try_from(status)
+        .map(|s| enum_name(s.as_str_name(), "TODO_STATUS_"))
+        .unwrap_or(Value::Null)
+}
+
+impl Record for Todo {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![
+            ("id", json!(self.id)),
+            ("user_id", json!(self.user_id)),
+            ("title", json!(self.title)),
+            ("description", json!(self.description)),
+            ("status", status(self.status)),
+            ("created_at", timestamp(&self.created_at)),
+            ("updated_at", timestamp(&self.updated_at)),
+        ]
+    }
+}
+
+// todo is flattened, its fields are empty for deleted event
+impl Record for TodoEvent {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        let kind = TodoEventKind::try_from(self.kind)
+            .map(|k| enum_name(k.as_str_name(), "TODO_EVENT_KIND_"))
+            .unwrap_or(Value::Null);
+        let todo = self.todo.as_ref();
+        vec![
+            ("kind", kind),
+            ("user_id", json!(self.user_id)),
+            ("todo_id", json!(self.todo_id)),
+            ("title", json!(todo.map(|t| &t.title))),
+            (
+                "description",
+                json!(todo.and_then(|t| t.description.as_ref())),
+            ),
+            (
+                "status",
+                todo.map(|t| status(t.status)).unwrap_or(Value::Null),
+            ),
+        ]
+    }
+}
+
+impl Record for User {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![
+            ("id", json!(self.id)),
+            ("first_name", json!(self.first_name)),
+            ("last_name", json!(self.last_name)),
+            ("email", json!(self.email)),
+            ("is_admin", json!(self.is_admin)),
+            ("created_at", timestamp(&self.created_at)),
+        ]
+    }
+}
+
+impl Record for LoginReply {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![
+            ("message", json!(self.message)),
+            ("token", json!(self.token)),
+        ]
+    }
+}
+
+impl Record for DeleteTodoReply {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![("deleted", json!(self.deleted))]
+    }
+}
+
+impl Record for DeleteUserReply {
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        vec![("deleted", json!(self.deleted))]
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(id: i32, title: &str) -> Todo {
+        Todo {
+            id,
+            user_id: 1,
+            title: title.to_string(),
+            description: None,
+            status: TodoStatus::Doing as i32,
+            created_at: Some(Timestamp {
+                seconds: 1_700_000_000,
+                nanos: 0,
+            }),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_render_table() {
+        let todos = [todo(1, "Programming"), todo(20, "Shopping")];
+        let expected = "\
+id  user_id  title        description  status  created_at                 updated_at
+1   1        Programming               doing   2023-11-14T22:13:20+00:00
+20  1        Shopping                  doing   2023-11-14T22:13:20+00:00
+next_page_token: 2";
+        assert_eq!(Output::Table.render_page("todos", &todos, "2", 3), expected);
+        assert_eq!(
+            Output::Table.render_page::<Todo>("todos", &[], "", 0),
+            "no items"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let value: Value =
+            serde_json::from_str(&Output::Json.render_page("todos", &[todo(1, "a")], "", 1))
+                .unwrap();
+        assert_eq!(value["todos"][0]["status"], "doing");
+        assert_eq!(value["todos"][0]["description"], Value::Null);
+        assert_eq!(value["next_page_token"], "");
+        assert_eq!(value["total_size"], 1);
+
+        let event = TodoEvent {
+            kind: TodoEventKind::Deleted as i32,
+            user_id: 1,
+            todo_id: 2,
+            todo: None,
+        };
+        assert_eq!(
+            Output::Json.render_stream(&event, true),
+            r#"{"description":null,"kind":"deleted","status":null,"title":null,"todo_id":2,"user_id":1}"#
+        );
+        assert_eq!(
+            Output::Table.render_stream(&event, false),
+            "deleted\t1\t2\t\t\t"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::Context;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+// default_path() returns `~/.grpc-client/token`
+// current directory is used when HOME is not set
+pub fn default_path() -> PathBuf {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(".grpc-client")
+        .join("token")
+}
+
+// load() returns None before login
+pub fn load(path: &Path) -> anyhow::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(token) => Ok(Some(token.trim().to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("fail to read token: {}", path.display())),
+    }
+}
+
+pub fn save(path: &Path, token: &str) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // token is readable only by owner, from the moment the file is created
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("fail to write token: {}", path.display()))?;
+    // file saved by previous version may be readable by others
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(token.as_bytes())
+        .with_context(|| format!("fail to write token: {}", path.display()))
+}
+
+pub fn remove(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("fail to remove token: {}", path.display())),
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".grpc-client").join("token");
+
+        assert_eq!(load(&path).unwrap(), None);
+        save(&path, "xxx.yyy.zzz").unwrap();
+        assert_eq!(load(&path).unwrap(), Some("xxx.yyy.zzz".to_string()));
+        remove(&path).unwrap();
+        assert_eq!(load(&path).unwrap(), None);
+        // logout twice is not an error
+        remove(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_token_is_readable_only_by_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        save(&path, "xxx.yyy.zzz").unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        // token saved by a previous version
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        save(&path, "aaa.bbb.ccc").unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(load(&path).unwrap(), Some("aaa.bbb.ccc".to_string()));
+    }
+}
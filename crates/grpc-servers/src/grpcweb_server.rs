@@ -1,5 +1,9 @@
-use tonic::{transport::Server, Request, Response, Status};
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use tower::ServiceBuilder;
 
+use components::{args, registry, toml};
+use grpc_servers::{auth, routes, transport};
 use hello_world::greeter_server::{Greeter, GreeterServer};
 use hello_world::{HelloReply, HelloRequest};
 
@@ -29,17 +33,39 @@ impl Greeter for MyGreeter {
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
-    let addr = "127.0.0.1:3000".parse().unwrap();
+    // load toml
+    let arg = args::get_args();
+    let config = match toml::load_config(arg.conf.as_str()) {
+        Ok(conf) => conf,
+        Err(error) => {
+            panic!("fail to load toml file [{}]: {:?}", arg.conf, error)
+        }
+    };
+
+    // same usecases as REST API
+    let reg = registry::Registry::new(config).await?;
+    let jwt_layer = auth::JwtLayer::new(reg.create_auth_state());
+
+    let addr = reg.conf.grpc.web_address.parse()?;
+    let server = transport::new_server(&reg.conf.grpc)?;
+    let routes = routes::new_routes(Arc::new(reg))?;
 
     let greeter = MyGreeter::default();
     let greeter = GreeterServer::new(greeter);
 
     println!("GreeterServer listening on {}", addr);
 
-    Server::builder()
+    server
         // GrpcWeb is over http1 so we must enable it.
         .accept_http1(true)
-        .add_service(tonic_web::enable(greeter))
+        // grpc-web layer is outside so that auth error is translated as well
+        .layer(
+            ServiceBuilder::new()
+                .layer(tonic_web::GrpcWebLayer::new())
+                .layer(jwt_layer),
+        )
+        .add_routes(routes)
+        .add_service(greeter)
         .serve(addr)
         .await?;
 
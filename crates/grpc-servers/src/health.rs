@@ -0,0 +1,45 @@
+use crate::pb::todo::todo_service_server::TodoServiceServer;
+use crate::pb::user::user_service_server::UserServiceServer;
+use crate::services::{todo::TodoGrpcService, user::UserGrpcService};
+use components::registry::Registry;
+use log::warn;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::server::NamedService;
+use tonic_health::pb::health_server::{Health, HealthServer};
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+// empty name means whole server
+const SERVICES: [&str; 3] = [
+    "",
+    <UserServiceServer<UserGrpcService> as NamedService>::NAME,
+    <TodoServiceServer<TodoGrpcService> as NamedService>::NAME,
+];
+
+// report_db_health() updates status of services by DB readiness until server stops
+pub async fn report_db_health(mut reporter: HealthReporter, reg: Arc<Registry>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let status = match reg.ping_db().await {
+            Ok(()) => ServingStatus::Serving,
+            Err(e) => {
+                warn!("DB is not ready: {:?}", e);
+                ServingStatus::NotServing
+            }
+        };
+        for service in SERVICES {
+            reporter.set_service_status(service, status).await;
+        }
+    }
+}
+
+// new_health_service() returns `grpc.health.v1.Health` updated in background
+pub fn new_health_service(reg: Arc<Registry>) -> HealthServer<impl Health> {
+    let (reporter, health_service) = tonic_health::server::health_reporter();
+    tokio::spawn(report_db_health(reporter, reg));
+    health_service
+}
@@ -0,0 +1,24 @@
+// tonic::Status is large, but it is returned as is by generated services
+#![allow(clippy::result_large_err)]
+
+pub mod auth;
+pub mod cli;
+pub mod health;
+pub mod reflection;
+pub mod routes;
+pub mod services;
+pub mod status;
+pub mod transport;
+
+pub mod pb {
+    pub mod user {
+        tonic::include_proto!("user");
+    }
+
+    pub mod todo {
+        tonic::include_proto!("todo");
+    }
+
+    // for reflection service
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("grpc_descriptor");
+}
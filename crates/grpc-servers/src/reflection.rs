@@ -0,0 +1,11 @@
+use crate::pb::FILE_DESCRIPTOR_SET;
+use tonic_reflection::server::v1::{ServerReflection, ServerReflectionServer};
+use tonic_reflection::server::Error;
+
+// new_reflection_service() exposes all services including health
+pub fn new_reflection_service() -> Result<ServerReflectionServer<impl ServerReflection>, Error> {
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()
+}
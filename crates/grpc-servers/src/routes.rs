@@ -0,0 +1,34 @@
+use crate::{auth, health, reflection, services};
+use components::registry::Registry;
+use std::sync::Arc;
+use tonic::service::Routes;
+use tonic_web::{GrpcWebLayer, GrpcWebService};
+use tower::ServiceBuilder;
+
+// new_routes() returns health, reflection, user and todo services
+pub fn new_routes(reg: Arc<Registry>) -> anyhow::Result<Routes> {
+    let (user_service, todo_service) = services::new_services(&reg);
+    let reflection_service = reflection::new_reflection_service()?;
+    let health_service = health::new_health_service(reg);
+
+    Ok(Routes::new(health_service)
+        .add_service(reflection_service)
+        .add_service(user_service)
+        .add_service(todo_service))
+}
+
+// GrpcService accepts both gRPC and gRPC-Web with JWT authentication
+// it is used to mount services on other HTTP server like axumfw
+pub type GrpcService = GrpcWebService<auth::JwtService<Routes>>;
+
+// new_grpc_service() applies same layers as grpcweb-server
+// grpc-web layer is outside so that auth error is translated as well
+pub fn new_grpc_service(reg: Arc<Registry>) -> anyhow::Result<GrpcService> {
+    let jwt_layer = auth::JwtLayer::new(reg.create_auth_state());
+    let routes = new_routes(reg)?.prepare();
+
+    Ok(ServiceBuilder::new()
+        .layer(GrpcWebLayer::new())
+        .layer(jwt_layer)
+        .service(routes))
+}
@@ -1,7 +1,10 @@
+use components::{args, registry, toml};
+use grpc_servers::{auth, routes, transport};
 use hello_world::greeter_server::{Greeter, GreeterServer};
 use hello_world::{HelloReply, HelloRequest};
 use log::info;
-use tonic::{transport::Server, Request, Response, Status};
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
 
 pub mod hello_world {
     tonic::include_proto!("helloworld");
@@ -30,11 +33,28 @@ impl Greeter for MyGreeter {
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
 
-    let addr = "[::1]:50051".parse()?;
+    // load toml
+    let arg = args::get_args();
+    let config = match toml::load_config(arg.conf.as_str()) {
+        Ok(conf) => conf,
+        Err(error) => {
+            panic!("fail to load toml file [{}]: {:?}", arg.conf, error)
+        }
+    };
+
+    // same usecases as REST API
+    let reg = registry::Registry::new(config).await?;
+    let jwt_layer = auth::JwtLayer::new(reg.create_auth_state());
+
+    let addr = reg.conf.grpc.address.parse()?;
+    let server = transport::new_server(&reg.conf.grpc)?;
+    let routes = routes::new_routes(Arc::new(reg))?;
     let greeter = MyGreeter::default();
-    info!("run gRPC server on :50051");
+    info!("run gRPC server on {}", addr);
 
-    Server::builder()
+    server
+        .layer(jwt_layer)
+        .add_routes(routes)
         .add_service(GreeterServer::new(greeter))
         .serve(addr)
         .await?;
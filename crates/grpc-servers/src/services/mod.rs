@@ -0,0 +1,133 @@
+pub mod todo;
+pub mod user;
+
+use crate::status;
+use chrono::NaiveDateTime;
+use components::registry::Registry;
+use prost_types::Timestamp;
+use tonic::Status;
+use validator::Validate;
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+// new_services() builds tonic services from registry
+// same usecases as REST API are used
+pub fn new_services(
+    reg: &Registry,
+) -> (
+    crate::pb::user::user_service_server::UserServiceServer<user::UserGrpcService>,
+    crate::pb::todo::todo_service_server::TodoServiceServer<todo::TodoGrpcService>,
+) {
+    let user_service =
+        user::UserGrpcService::new(reg.create_auth_state(), reg.create_admin_state());
+    let todo_service = todo::TodoGrpcService::new(reg.create_app_state());
+    let mut user_server =
+        crate::pb::user::user_service_server::UserServiceServer::new(user_service);
+    let mut todo_server =
+        crate::pb::todo::todo_service_server::TodoServiceServer::new(todo_service);
+
+    // message size limits in [grpc] section
+    let conf = &reg.conf.grpc;
+    if let Some(limit) = conf.max_decoding_message_size {
+        user_server = user_server.max_decoding_message_size(limit);
+        todo_server = todo_server.max_decoding_message_size(limit);
+    }
+    if let Some(limit) = conf.max_encoding_message_size {
+        user_server = user_server.max_encoding_message_size(limit);
+        todo_server = todo_server.max_encoding_message_size(limit);
+    }
+    (user_server, todo_server)
+}
+
+// paginate() returns a page and token for next page
+// page_token is offset of the first item
+pub(crate) fn paginate<T>(
+    items: Vec<T>,
+    page_size: i32,
+    page_token: &str,
+) -> Result<(Vec<T>, String), Status> {
+    let page_size = match page_size {
+        size if size < 0 => return Err(Status::invalid_argument("page_size must not be negative")),
+        0 => DEFAULT_PAGE_SIZE,
+        size => (size as usize).min(MAX_PAGE_SIZE),
+    };
+    let offset = if page_token.is_empty() {
+        0
+    } else {
+        page_token
+            .parse::<usize>()
+            .map_err(|_| Status::invalid_argument("page_token is invalid"))?
+    };
+
+    let end = offset.saturating_add(page_size);
+    let next_page_token = if end < items.len() {
+        end.to_string()
+    } else {
+        String::new()
+    };
+    let page = items.into_iter().skip(offset).take(page_size).collect();
+    Ok((page, next_page_token))
+}
+
+pub(crate) fn validate<T: Validate>(body: &T) -> Result<(), Status> {
+    body.validate().map_err(|e| status::invalid_argument(&e))
+}
+
+pub(crate) fn to_timestamp(datetime: Option<NaiveDateTime>) -> Option<Timestamp> {
+    datetime.map(|dt| {
+        let dt = dt.and_utc();
+        Timestamp {
+            seconds: dt.timestamp(),
+            nanos: dt.timestamp_subsec_nanos() as i32,
+        }
+    })
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        let items: Vec<i32> = (0..45).collect();
+
+        let (page, next) = paginate(items.clone(), 0, "").unwrap();
+        assert_eq!(page, (0..20).collect::<Vec<_>>());
+        assert_eq!(next, "20");
+
+        let (page, next) = paginate(items.clone(), 20, &next).unwrap();
+        assert_eq!(page, (20..40).collect::<Vec<_>>());
+        assert_eq!(next, "40");
+
+        let (page, next) = paginate(items.clone(), 20, &next).unwrap();
+        assert_eq!(page, (40..45).collect::<Vec<_>>());
+        assert_eq!(next, "");
+
+        // page_size is capped
+        let (page, _) = paginate((0..200).collect::<Vec<i32>>(), 1000, "").unwrap();
+        assert_eq!(page.len(), MAX_PAGE_SIZE);
+
+        // out of range
+        let (page, next) = paginate(items.clone(), 10, "100").unwrap();
+        assert!(page.is_empty());
+        assert_eq!(next, "");
+    }
+
+    #[test]
+    fn test_paginate_invalid() {
+        let items = vec![1, 2, 3];
+        assert_eq!(
+            paginate(items.clone(), -1, "").unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+        assert_eq!(
+            paginate(items, 10, "abc").unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use crate::auth::authorize_owner;
+use crate::pb::todo::todo_service_server::TodoService;
+use crate::pb::todo::{
+    CreateTodoRequest, DeleteTodoReply, DeleteTodoRequest, GetTodoRequest, ListTodosReply,
+    ListTodosRequest, Todo, TodoEvent, TodoEventKind, TodoStatus, UpdateTodoRequest,
+    WatchTodosRequest,
+};
+use crate::services::{paginate, to_timestamp, validate};
+use crate::status::from_error;
+use components::entities::todos;
+use components::events;
+use components::schemas::sea_orm::sea_orm_active_enums::TodoStatus as DbTodoStatus;
+use components::schemas::sea_orm::todos as db_todos;
+use components::state;
+use log::warn;
+use std::pin::Pin;
+use tokio::sync::broadcast;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+pub type TodoEventStream = Pin<Box<dyn Stream<Item = Result<TodoEvent, Status>> + Send>>;
+
+impl From<DbTodoStatus> for TodoStatus {
+    fn from(status: DbTodoStatus) -> Self {
+        match status {
+            DbTodoStatus::Canceled => TodoStatus::Canceled,
+            DbTodoStatus::Doing => TodoStatus::Doing,
+            DbTodoStatus::Done => TodoStatus::Done,
+            DbTodoStatus::Pending => TodoStatus::Pending,
+        }
+    }
+}
+
+impl From<db_todos::Model> for Todo {
+    fn from(model: db_todos::Model) -> Self {
+        Todo {
+            id: model.id,
+            user_id: model.user_id,
+            title: model.title,
+            description: model.description,
+            status: TodoStatus::from(model.status) as i32,
+            created_at: to_timestamp(model.created_at),
+            updated_at: to_timestamp(model.updated_at),
+        }
+    }
+}
+
+impl From<events::TodoEvent> for TodoEvent {
+    fn from(event: events::TodoEvent) -> Self {
+        let kind = match event.kind {
+            events::TodoEventKind::Created => TodoEventKind::Created,
+            events::TodoEventKind::Updated => TodoEventKind::Updated,
+            events::TodoEventKind::Deleted => TodoEventKind::Deleted,
+        };
+        TodoEvent {
+            kind: kind as i32,
+            user_id: event.user_id,
+            todo_id: event.todo_id,
+            todo: event.todo.map(Todo::from),
+        }
+    }
+}
+
+// watch_stream() converts events of the user into gRPC stream
+// lagged subscriber gets DATA_LOSS and should list todos again before watching
+fn watch_stream(
+    mut receiver: broadcast::Receiver<events::TodoEvent>,
+    user_id: i32,
+) -> TodoEventStream {
+    Box::pin(async_stream::stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if event.user_id == user_id => yield Ok(TodoEvent::from(event)),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("{} todo events are dropped for user {}", skipped, user_id);
+                    yield Err(Status::data_loss(format!("{} events are dropped", skipped)));
+                    break;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+// status_str() converts proto enum into status used by REST API
+// unspecified and unknown values are left empty to be rejected by validation
+fn status_str(status: i32) -> String {
+    match TodoStatus::try_from(status) {
+        Ok(TodoStatus::Canceled) => "canceled",
+        Ok(TodoStatus::Doing) => "doing",
+        Ok(TodoStatus::Done) => "done",
+        Ok(TodoStatus::Pending) => "pending",
+        _ => "",
+    }
+    .to_string()
+}
+
+fn not_found(todo_id: i32) -> Status {
+    Status::not_found(format!("Todo with ID {} not found", todo_id))
+}
+
+pub struct TodoGrpcService {
+    app_state: state::AppState,
+}
+
+impl TodoGrpcService {
+    pub fn new(app_state: state::AppState) -> Self {
+        Self { app_state }
+    }
+
+    // find_todo() returns todo only when it belongs to the user
+    async fn find_todo(&self, user_id: i32, todo_id: i32) -> Result<db_todos::Model, Status> {
+        match self
+            .app_state
+            .app_usecase
+            .get_user_todo(user_id, todo_id)
+            .await
+        {
+            Ok(Some(todo)) if todo.user_id == user_id => Ok(todo),
+            Ok(_) => Err(not_found(todo_id)),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl TodoService for TodoGrpcService {
+    type WatchTodosStream = TodoEventStream;
+
+    async fn list_todos(
+        &self,
+        request: Request<ListTodosRequest>,
+    ) -> Result<Response<ListTodosReply>, Status> {
+        authorize_owner(&request, request.get_ref().user_id)?;
+        let req = request.into_inner();
+
+        // usecase
+        let todo_list = self
+            .app_state
+            .app_usecase
+            .get_user_todo_list(req.user_id)
+            .await
+            .map_err(from_error)?;
+        let total_size = todo_list.len() as i32;
+        let (page, next_page_token) = paginate(todo_list, req.page_size, &req.page_token)?;
+        Ok(Response::new(ListTodosReply {
+            todos: page.into_iter().map(Todo::from).collect(),
+            next_page_token,
+            total_size,
+        }))
+    }
+
+    async fn get_todo(&self, request: Request<GetTodoRequest>) -> Result<Response<Todo>, Status> {
+        authorize_owner(&request, request.get_ref().user_id)?;
+        let req = request.into_inner();
+        let todo = self.find_todo(req.user_id, req.todo_id).await?;
+        Ok(Response::new(todo.into()))
+    }
+
+    async fn create_todo(
+        &self,
+        request: Request<CreateTodoRequest>,
+    ) -> Result<Response<Todo>, Status> {
+        authorize_owner(&request, request.get_ref().user_id)?;
+        let req = request.into_inner();
+        // validation
+        let body = todos::TodoBody {
+            title: req.title,
+            description: req.description,
+            status: status_str(req.status),
+        };
+        validate(&body)?;
+
+        // usecase
+        match self
+            .app_state
+            .app_usecase
+            .add_user_todo(req.user_id, body)
+            .await
+        {
+            Ok(todo) => Ok(Response::new(todo.into())),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+
+    async fn update_todo(
+        &self,
+        request: Request<UpdateTodoRequest>,
+    ) -> Result<Response<Todo>, Status> {
+        authorize_owner(&request, request.get_ref().user_id)?;
+        let req = request.into_inner();
+        // validation
+        let body = todos::TodoUpdateBody {
+            title: req.title,
+            description: req.description,
+            status: req.status.map(status_str),
+        };
+        validate(&body)?;
+
+        // usecase
+        self.find_todo(req.user_id, req.todo_id).await?;
+        match self
+            .app_state
+            .app_usecase
+            .update_user_todo(req.user_id, req.todo_id, body)
+            .await
+        {
+            Ok(Some(todo)) => Ok(Response::new(todo.into())),
+            Ok(None) => Err(not_found(req.todo_id)),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+
+    async fn delete_todo(
+        &self,
+        request: Request<DeleteTodoRequest>,
+    ) -> Result<Response<DeleteTodoReply>, Status> {
+        authorize_owner(&request, request.get_ref().user_id)?;
+        let req = request.into_inner();
+
+        // usecase
+        self.find_todo(req.user_id, req.todo_id).await?;
+        match self
+            .app_state
+            .app_usecase
+            .delete_user_todo(req.user_id, req.todo_id)
+            .await
+        {
+            Ok(0) => Err(not_found(req.todo_id)),
+            Ok(deleted) => Ok(Response::new(DeleteTodoReply { deleted })),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+
+    async fn watch_todos(
+        &self,
+        request: Request<WatchTodosRequest>,
+    ) -> Result<Response<Self::WatchTodosStream>, Status> {
+        authorize_owner(&request, request.get_ref().user_id)?;
+        let req = request.into_inner();
+
+        let receiver = self.app_state.app_usecase.subscribe_todo_events();
+        Ok(Response::new(watch_stream(receiver, req.user_id)))
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    #[test]
+    fn test_status_conversion() {
+        for status in ["canceled", "doing", "done", "pending"] {
+            let db_status = status.parse::<DbTodoStatus>().unwrap();
+            let proto_status = TodoStatus::from(db_status) as i32;
+            assert_eq!(status_str(proto_status), status);
+        }
+        assert_eq!(status_str(TodoStatus::Unspecified as i32), "");
+        assert_eq!(status_str(100), "");
+    }
+
+    fn event(user_id: i32, todo_id: i32) -> events::TodoEvent {
+        events::TodoEvent {
+            kind: events::TodoEventKind::Deleted,
+            user_id,
+            todo_id,
+            todo: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_stream() {
+        let bus = events::EventBus::new(2);
+        let mut stream = watch_stream(bus.subscribe(), 1);
+
+        // events of other users are filtered out
+        bus.publish(event(2, 10));
+        bus.publish(event(1, 11));
+        let received = stream.next().await.unwrap().unwrap();
+        assert_eq!(received.todo_id, 11);
+        assert_eq!(received.kind(), TodoEventKind::Deleted);
+        assert_eq!(received.todo, None);
+
+        // lagged
+        for todo_id in 0..3 {
+            bus.publish(event(1, todo_id));
+        }
+        let status = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DataLoss);
+        assert!(stream.next().await.is_none());
+    }
+}
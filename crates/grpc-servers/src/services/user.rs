@@ -0,0 +1,194 @@
+use crate::pb::user::user_service_server::UserService;
+use crate::pb::user::{
+    CreateUserRequest, DeleteUserReply, DeleteUserRequest, GetUserRequest, ListUsersReply,
+    ListUsersRequest, LoginReply, LoginRequest, UpdateUserRequest, User,
+};
+use crate::services::{paginate, to_timestamp, validate};
+use crate::status::from_error;
+use components::entities::users;
+use components::schemas::sea_orm::users as db_users;
+use components::state;
+use tonic::{Request, Response, Status};
+
+impl From<db_users::Model> for User {
+    // password is never returned
+    fn from(model: db_users::Model) -> Self {
+        User {
+            id: model.id,
+            first_name: model.first_name,
+            last_name: model.last_name,
+            email: model.email,
+            is_admin: model.is_admin,
+            created_at: to_timestamp(model.created_at),
+        }
+    }
+}
+
+pub struct UserGrpcService {
+    auth_state: state::AuthState,
+    admin_state: state::AdminState,
+}
+
+impl UserGrpcService {
+    pub fn new(auth_state: state::AuthState, admin_state: state::AdminState) -> Self {
+        Self {
+            auth_state,
+            admin_state,
+        }
+    }
+
+    async fn authenticate(&self, req: LoginRequest, is_admin: bool) -> Result<LoginReply, Status> {
+        // validation
+        let body = users::LoginBody {
+            email: req.email,
+            password: req.password,
+        };
+        validate(&body)?;
+
+        // authentication usecase
+        let auth_usecase = &self.auth_state.auth_usecase;
+        let user = if is_admin {
+            auth_usecase.login_admin(&body.email, &body.password).await
+        } else {
+            auth_usecase.login(&body.email, &body.password).await
+        };
+        match user {
+            Ok(Some(user)) => {
+                // return access key
+                let token = auth_usecase
+                    .generate_token(user.id, user.email.as_str(), user.is_admin)
+                    .map_err(from_error)?;
+                Ok(LoginReply {
+                    message: "Login successful".into(),
+                    token,
+                })
+            }
+            Ok(None) => Err(Status::unauthenticated("user is not found")),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl UserService for UserGrpcService {
+    async fn login(&self, request: Request<LoginRequest>) -> Result<Response<LoginReply>, Status> {
+        self.authenticate(request.into_inner(), false)
+            .await
+            .map(Response::new)
+    }
+
+    async fn admin_login(
+        &self,
+        request: Request<LoginRequest>,
+    ) -> Result<Response<LoginReply>, Status> {
+        self.authenticate(request.into_inner(), true)
+            .await
+            .map(Response::new)
+    }
+
+    async fn list_users(
+        &self,
+        request: Request<ListUsersRequest>,
+    ) -> Result<Response<ListUsersReply>, Status> {
+        let req = request.into_inner();
+
+        // usecase
+        let user_list = self
+            .admin_state
+            .admin_usecase
+            .get_user_list()
+            .await
+            .map_err(from_error)?;
+        let total_size = user_list.len() as i32;
+        let (page, next_page_token) = paginate(user_list, req.page_size, &req.page_token)?;
+        Ok(Response::new(ListUsersReply {
+            users: page.into_iter().map(User::from).collect(),
+            next_page_token,
+            total_size,
+        }))
+    }
+
+    async fn get_user(&self, request: Request<GetUserRequest>) -> Result<Response<User>, Status> {
+        let user_id = request.into_inner().user_id;
+
+        // usecase
+        match self.admin_state.admin_usecase.get_user(user_id).await {
+            Ok(Some(user)) => Ok(Response::new(user.into())),
+            Ok(None) => Err(Status::not_found(format!(
+                "User with ID {} not found",
+                user_id
+            ))),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+
+    async fn create_user(
+        &self,
+        request: Request<CreateUserRequest>,
+    ) -> Result<Response<User>, Status> {
+        let req = request.into_inner();
+        // validation
+        let body = users::UserBody {
+            first_name: req.first_name,
+            last_name: req.last_name,
+            email: req.email,
+            password: req.password,
+            is_admin: req.is_admin,
+        };
+        validate(&body)?;
+
+        // usecase
+        match self.admin_state.admin_usecase.add_user(body).await {
+            Ok(user) => Ok(Response::new(user.into())),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+
+    async fn update_user(
+        &self,
+        request: Request<UpdateUserRequest>,
+    ) -> Result<Response<User>, Status> {
+        let req = request.into_inner();
+        // validation
+        let body = users::UserUpdateBody {
+            first_name: req.first_name,
+            last_name: req.last_name,
+            email: req.email,
+            password: req.password,
+            is_admin: req.is_admin,
+        };
+        validate(&body)?;
+
+        // usecase
+        match self
+            .admin_state
+            .admin_usecase
+            .update_user(req.user_id, body)
+            .await
+        {
+            Ok(Some(user)) => Ok(Response::new(user.into())),
+            Ok(None) => Err(Status::not_found(format!(
+                "User with ID {} not found",
+                req.user_id
+            ))),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+
+    async fn delete_user(
+        &self,
+        request: Request<DeleteUserRequest>,
+    ) -> Result<Response<DeleteUserReply>, Status> {
+        let user_id = request.into_inner().user_id;
+
+        // usecase
+        match self.admin_state.admin_usecase.delete_user(user_id).await {
+            Ok(0) => Err(Status::not_found(format!(
+                "User with ID {} not found",
+                user_id
+            ))),
+            Ok(deleted) => Ok(Response::new(DeleteUserReply { deleted })),
+            Err(e) => Err(from_error(e)),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use components::errors::CustomError;
+use prost::Message;
+use sea_orm::{DbErr, SqlErr};
+use tonic::{Code, Status};
+use validator::{ValidationError, ValidationErrors};
+
+// google.rpc messages in
+// https://github.com/googleapis/googleapis/blob/master/google/rpc/status.proto
+// https://github.com/googleapis/googleapis/blob/master/google/rpc/error_details.proto
+pub mod rpc {
+    pub const BAD_REQUEST_TYPE_URL: &str = "type.googleapis.com/google.rpc.BadRequest";
+
+    // Status is sent as `grpc-status-details-bin` metadata
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Status {
+        #[prost(int32, tag = "1")]
+        pub code: i32,
+        #[prost(string, tag = "2")]
+        pub message: String,
+        #[prost(message, repeated, tag = "3")]
+        pub details: Vec<prost_types::Any>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BadRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub field_violations: Vec<FieldViolation>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FieldViolation {
+        #[prost(string, tag = "1")]
+        pub field: String,
+        #[prost(string, tag = "2")]
+        pub description: String,
+    }
+}
+
+// description() uses message of validator, or code and params like `length (max: 50, min: 1)`
+fn description(e: &ValidationError) -> String {
+    if let Some(message) = &e.message {
+        return message.to_string();
+    }
+    let mut params: Vec<String> = e
+        .params
+        .iter()
+        .filter(|(name, _)| *name != "value")
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect();
+    if params.is_empty() {
+        return e.code.to_string();
+    }
+    params.sort();
+    format!("{} ({})", e.code, params.join(", "))
+}
+
+// field_violations() returns violations sorted by field name
+pub fn field_violations(errors: &ValidationErrors) -> Vec<rpc::FieldViolation> {
+    let mut violations: Vec<rpc::FieldViolation> = errors
+        .field_errors()
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors.iter().map(move |e| rpc::FieldViolation {
+                field: field.to_string(),
+                description: description(e),
+            })
+        })
+        .collect();
+    violations.sort_by(|a, b| a.field.cmp(&b.field));
+    violations
+}
+
+// invalid_argument() returns INVALID_ARGUMENT with google.rpc.BadRequest details
+pub fn invalid_argument(errors: &ValidationErrors) -> Status {
+    let message = format!("request body is invalid: {}", errors);
+    let bad_request = rpc::BadRequest {
+        field_violations: field_violations(errors),
+    };
+    let details = rpc::Status {
+        code: Code::InvalidArgument as i32,
+        message: message.clone(),
+        details: vec![prost_types::Any {
+            type_url: rpc::BAD_REQUEST_TYPE_URL.to_string(),
+            value: bad_request.encode_to_vec(),
+        }],
+    };
+    Status::with_details(
+        Code::InvalidArgument,
+        message,
+        details.encode_to_vec().into(),
+    )
+}
+
+// bad_request() extracts google.rpc.BadRequest details for clients
+pub fn bad_request(status: &Status) -> Option<rpc::BadRequest> {
+    let details = rpc::Status::decode(status.details()).ok()?;
+    details
+        .details
+        .iter()
+        .find(|any| any.type_url == rpc::BAD_REQUEST_TYPE_URL)
+        .and_then(|any| rpc::BadRequest::decode(any.value.as_slice()).ok())
+}
+
+fn from_db_error(e: &DbErr) -> Option<Status> {
+    match e.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => {
+            return Some(Status::already_exists("record already exists"))
+        }
+        Some(SqlErr::ForeignKeyConstraintViolation(_)) => {
+            return Some(Status::failed_precondition("related record does not exist"))
+        }
+        _ => {}
+    }
+    match e {
+        DbErr::RecordNotFound(_) | DbErr::RecordNotUpdated => {
+            Some(Status::not_found("record is not found"))
+        }
+        DbErr::Conn(_) | DbErr::ConnectionAcquire(_) => {
+            Some(Status::unavailable("database is not available"))
+        }
+        _ => None,
+    }
+}
+
+// from_error() maps error returned by usecases into gRPC status
+// - validator::ValidationErrors: INVALID_ARGUMENT with details
+// - CustomError::UnauthorizedAccess: UNAUTHENTICATED
+// - CustomError::InvalidData: INVALID_ARGUMENT
+// - unique / foreign key constraint of DB: ALREADY_EXISTS / FAILED_PRECONDITION
+// - record not found in DB: NOT_FOUND
+// - connection to DB: UNAVAILABLE
+// - others: INTERNAL
+pub fn from_error(e: anyhow::Error) -> Status {
+    if let Some(errors) = e.downcast_ref::<ValidationErrors>() {
+        return invalid_argument(errors);
+    }
+    if let Some(status) = e.downcast_ref::<DbErr>().and_then(from_db_error) {
+        return status;
+    }
+    match e.downcast::<CustomError>() {
+        Ok(CustomError::UnauthorizedAccess) => Status::unauthenticated("Unauthorized access"),
+        Ok(CustomError::InvalidData) => Status::invalid_argument("invalid data"),
+        // wrapped error is mapped as well
+        Ok(CustomError::Other(inner)) => from_error(inner),
+        Err(e) => Status::internal(format!("Fatal error: {:?}", e)),
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use components::entities::todos::TodoBody;
+    use sea_orm::{ConnAcquireErr, RuntimeErr};
+    use validator::Validate;
+
+    fn invalid_todo() -> ValidationErrors {
+        TodoBody {
+            title: String::new(),
+            description: Some("a".repeat(201)),
+            status: "unknown".to_string(),
+        }
+        .validate()
+        .unwrap_err()
+    }
+
+    #[test]
+    fn test_invalid_argument_details() {
+        let status = invalid_argument(&invalid_todo());
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().starts_with("request body is invalid"));
+
+        let details = bad_request(&status).expect("BadRequest details");
+        let violations: Vec<(&str, &str)> = details
+            .field_violations
+            .iter()
+            .map(|v| (v.field.as_str(), v.description.as_str()))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                ("description", "length (max: 200, min: 1)"),
+                ("status", "invalid status"),
+                ("title", "length (max: 50, min: 1)"),
+            ]
+        );
+
+        // no details for other errors
+        assert_eq!(bad_request(&Status::internal("error")), None);
+    }
+
+    #[test]
+    fn test_from_error() {
+        let cases: Vec<(anyhow::Error, Code)> = vec![
+            (invalid_todo().into(), Code::InvalidArgument),
+            (
+                CustomError::UnauthorizedAccess.into(),
+                Code::Unauthenticated,
+            ),
+            (CustomError::InvalidData.into(), Code::InvalidArgument),
+            (
+                DbErr::RecordNotFound("users".to_string()).into(),
+                Code::NotFound,
+            ),
+            (DbErr::RecordNotUpdated.into(), Code::NotFound),
+            (
+                DbErr::Conn(RuntimeErr::Internal("refused".to_string())).into(),
+                Code::Unavailable,
+            ),
+            (
+                DbErr::ConnectionAcquire(ConnAcquireErr::Timeout).into(),
+                Code::Unavailable,
+            ),
+            (
+                CustomError::Other(DbErr::RecordNotUpdated.into()).into(),
+                Code::NotFound,
+            ),
+            (
+                DbErr::Custom("unexpected".to_string()).into(),
+                Code::Internal,
+            ),
+            (anyhow::anyhow!("unexpected"), Code::Internal),
+        ];
+        for (e, code) in cases {
+            let message = e.to_string();
+            assert_eq!(from_error(e).code(), code, "{}", message);
+        }
+
+        // details are kept for validation error from usecases
+        let status = from_error(invalid_todo().into());
+        assert_eq!(bad_request(&status).unwrap().field_violations.len(), 3);
+    }
+}
@@ -0,0 +1,20 @@
+use anyhow::Context;
+use components::toml;
+use std::fs;
+use tonic::transport::{Identity, Server, ServerTlsConfig};
+
+// new_server() returns builder with TLS when `[grpc.tls]` is given
+pub fn new_server(conf: &toml::Grpc) -> anyhow::Result<Server> {
+    let builder = Server::builder();
+    let Some(tls) = &conf.tls else {
+        return Ok(builder);
+    };
+
+    let cert = fs::read(&tls.cert_path)
+        .with_context(|| format!("fail to read certificate: {}", tls.cert_path))?;
+    let key = fs::read(&tls.key_path)
+        .with_context(|| format!("fail to read private key: {}", tls.key_path))?;
+    let builder =
+        builder.tls_config(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))?;
+    Ok(builder)
+}
@@ -0,0 +1,121 @@
+use crate::api::error::AppError;
+use crate::api::{json, parse_body, path_id, ApiState};
+use crate::request;
+use crate::responser::Response;
+use components::entities::login::LoginResult;
+use components::entities::users;
+
+/*
+ Admin
+*/
+
+// [post] /login
+pub fn admin_login(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    // validation
+    let body: users::LoginBody = parse_body(req)?;
+
+    // authentication usecase
+    let auth_usecase = &state.auth_state.auth_usecase;
+    match state.block_on(auth_usecase.login_admin(&body.email, &body.password)) {
+        Ok(Some(user)) => {
+            // return access key
+            match auth_usecase.generate_token(user.id, user.email.as_str(), user.is_admin) {
+                Ok(token) => json(&LoginResult {
+                    message: "Login successful".into(),
+                    token: Some(token),
+                }),
+                Err(e) => Err(AppError::InternalServerError(format!(
+                    "Fatal error: {:?}",
+                    e
+                ))),
+            }
+        }
+        Ok(None) => Err(AppError::Unauthorized("user is not found".into())),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// [get] /users
+pub fn get_user_list(_req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    // usecase
+    match state.block_on(state.admin_state.admin_usecase.get_user_list()) {
+        Ok(user_list) => json(&user_list),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// [post] /users
+pub fn add_user(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    // validation
+    let body: users::UserBody = parse_body(req)?;
+
+    // usecase
+    match state.block_on(state.admin_state.admin_usecase.add_user(body)) {
+        Ok(user) => json(&user),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// [get] "/users/{user_id}"
+pub fn get_user(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    let user_id = path_id(req, "user_id")?;
+
+    // usecase
+    match state.block_on(state.admin_state.admin_usecase.get_user(user_id)) {
+        Ok(Some(user)) => json(&user),
+        Ok(None) => Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            user_id
+        ))),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// [put] "/users/{user_id}"
+pub fn update_user(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    let user_id = path_id(req, "user_id")?;
+    // validate
+    let body: users::UserUpdateBody = parse_body(req)?;
+
+    // usecase
+    match state.block_on(state.admin_state.admin_usecase.update_user(user_id, body)) {
+        Ok(Some(user)) => json(&user),
+        Ok(None) => Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            user_id
+        ))),
+        Err(e) => Err(AppError::BadRequest(format!(
+            "request body is invalid: {:?}",
+            e
+        ))),
+    }
+}
+
+// [delete] "/users/{user_id}"
+pub fn delete_user(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    let user_id = path_id(req, "user_id")?;
+
+    match state.block_on(state.admin_state.admin_usecase.delete_user(user_id)) {
+        Ok(0) => Err(AppError::NotFound(format!(
+            "User with ID {} not found",
+            user_id
+        ))),
+        Ok(_) => Ok(Response::no_content()),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
@@ -0,0 +1,137 @@
+use crate::api::error::AppError;
+use crate::api::{json, parse_body, path_id, ApiState};
+use crate::request;
+use crate::responser::Response;
+use components::entities::login::LoginResult;
+use components::entities::{todos, users};
+
+/*
+ App
+*/
+
+// [post] /login
+pub fn app_login(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    // validation
+    let body: users::LoginBody = parse_body(req)?;
+
+    // authentication usecase
+    let auth_usecase = &state.auth_state.auth_usecase;
+    match state.block_on(auth_usecase.login(&body.email, &body.password)) {
+        Ok(Some(user)) => {
+            // return access key
+            match auth_usecase.generate_token(user.id, user.email.as_str(), user.is_admin) {
+                Ok(token) => json(&LoginResult {
+                    message: "Login successful".into(),
+                    token: Some(token),
+                }),
+                Err(e) => Err(AppError::InternalServerError(format!(
+                    "Fatal error: {:?}",
+                    e
+                ))),
+            }
+        }
+        Ok(None) => Err(AppError::Unauthorized("user is not found".into())),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// [get] /users/{user_id}/todos
+pub fn get_user_todo_list(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    let user_id = path_id(req, "user_id")?;
+
+    // usecase
+    match state.block_on(state.app_state.app_usecase.get_user_todo_list(user_id)) {
+        Ok(todo_list) => json(&todo_list),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// [post] /users/{user_id}/todos
+pub fn add_user_todo(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    let user_id = path_id(req, "user_id")?;
+    // validation
+    let body: todos::TodoBody = parse_body(req)?;
+
+    // usecase
+    match state.block_on(state.app_state.app_usecase.add_user_todo(user_id, body)) {
+        Ok(todo) => json(&todo),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// [get] "/users/{user_id}/todos/{todo_id}"
+pub fn get_user_todo(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    let user_id = path_id(req, "user_id")?;
+    let todo_id = path_id(req, "todo_id")?;
+
+    // usecase
+    match state.block_on(state.app_state.app_usecase.get_user_todo(user_id, todo_id)) {
+        Ok(Some(todo)) => json(&todo),
+        Ok(None) => Err(AppError::NotFound(format!(
+            "Todo with ID {} not found",
+            todo_id
+        ))),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// [put] "/users/{user_id}/todos/{todo_id}"
+pub fn update_user_todo(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    let user_id = path_id(req, "user_id")?;
+    let todo_id = path_id(req, "todo_id")?;
+    // validate
+    let body: todos::TodoUpdateBody = parse_body(req)?;
+
+    // usecase
+    match state.block_on(
+        state
+            .app_state
+            .app_usecase
+            .update_user_todo(user_id, todo_id, body),
+    ) {
+        Ok(Some(todo)) => json(&todo),
+        Ok(None) => Err(AppError::NotFound(format!(
+            "Todo with ID {} not found",
+            todo_id
+        ))),
+        Err(e) => Err(AppError::BadRequest(format!(
+            "request body is invalid: {:?}",
+            e
+        ))),
+    }
+}
+
+// [delete] "/users/{user_id}/todos/{todo_id}"
+pub fn delete_user_todo(req: &request::Request, state: &ApiState) -> Result<Response, AppError> {
+    let user_id = path_id(req, "user_id")?;
+    let todo_id = path_id(req, "todo_id")?;
+
+    match state.block_on(
+        state
+            .app_state
+            .app_usecase
+            .delete_user_todo(user_id, todo_id),
+    ) {
+        Ok(0) => Err(AppError::NotFound(format!(
+            "Todo with ID {} not found",
+            todo_id
+        ))),
+        Ok(_) => Ok(Response::no_content()),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
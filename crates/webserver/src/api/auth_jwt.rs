@@ -0,0 +1,80 @@
+use crate::errors::HTTPErrorMessage;
+use crate::middleware::Middleware;
+use crate::request;
+use components::entities::users;
+use components::state;
+use log::debug;
+
+// JwtMiddleware checks token for `/api/v1/admin` and `/api/v1/app/users` paths
+// - admin: is_admin must be true
+// - app: admin or owner of user_id in path
+pub struct JwtMiddleware {
+    auth_state: state::AuthState,
+}
+
+impl JwtMiddleware {
+    pub fn new(auth_state: state::AuthState) -> Self {
+        Self { auth_state }
+    }
+
+    fn validate_admin(&self, req: &request::Request) -> Result<(), HTTPErrorMessage> {
+        debug!("validate_admin is called");
+
+        let token = get_token(req)?;
+        match self.auth_state.auth_usecase.validate_token(token) {
+            // admin only
+            Ok(payload) if payload.is_admin => Ok(()),
+            Ok(_) => Err(HTTPErrorMessage::Unauthorized),
+            Err(e) => {
+                debug!("token in invalid: {}", e);
+                Err(HTTPErrorMessage::Unauthorized)
+            }
+        }
+    }
+
+    fn validate_app(&self, req: &request::Request) -> Result<(), HTTPErrorMessage> {
+        debug!("validate_app is called");
+
+        let token = get_token(req)?;
+        let user_id = users::extract_user_id(&req.path).map_err(|e| {
+            debug!("{}", e);
+            HTTPErrorMessage::BadRequest
+        })?;
+        debug!("user_id: {}", user_id);
+
+        match self.auth_state.auth_usecase.validate_token(token) {
+            Ok(payload) if payload.is_admin || payload.user_id as i32 == user_id => Ok(()),
+            Ok(_) => Err(HTTPErrorMessage::Unauthorized),
+            Err(e) => {
+                debug!("token in invalid: {}", e);
+                Err(HTTPErrorMessage::Unauthorized)
+            }
+        }
+    }
+}
+
+impl Middleware for JwtMiddleware {
+    fn handle(&self, req: &request::Request) -> anyhow::Result<(), HTTPErrorMessage> {
+        if self.auth_state.auth_usecase.is_jwt_disable() {
+            return Ok(());
+        }
+
+        if req.path.starts_with("/api/v1/admin/") && req.path != "/api/v1/admin/login" {
+            return self.validate_admin(req);
+        }
+        if req.path.starts_with("/api/v1/app/users/") {
+            return self.validate_app(req);
+        }
+        Ok(())
+    }
+}
+
+// get_token() retrieves token from `authorization: Bearer xxx`
+fn get_token(req: &request::Request) -> Result<&str, HTTPErrorMessage> {
+    let token = req
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(HTTPErrorMessage::Unauthorized)?;
+    debug!("token: {}", token);
+    Ok(token)
+}
@@ -0,0 +1,13 @@
+use crate::api::error::AppError;
+use crate::api::ApiState;
+use crate::request;
+use crate::responser::Response;
+
+pub fn health(_req: &request::Request, _state: &ApiState) -> Result<Response, AppError> {
+    Ok(Response::html(200, "OK"))
+}
+
+// TODO: remove after implementation done
+pub fn dummy(_req: &request::Request, _state: &ApiState) -> Result<Response, AppError> {
+    Ok(Response::html(200, "dummy"))
+}
@@ -0,0 +1,21 @@
+use crate::responser::Response;
+
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(String),          // 400
+    Unauthorized(String),        // 401
+    NotFound(String),            // 404
+    InternalServerError(String), // 500
+}
+
+impl From<AppError> for Response {
+    fn from(e: AppError) -> Self {
+        let (status, message) = match e {
+            AppError::BadRequest(msg) => (400, msg),
+            AppError::Unauthorized(msg) => (401, msg),
+            AppError::NotFound(msg) => (404, msg),
+            AppError::InternalServerError(msg) => (500, msg),
+        };
+        Response::error_json(status, &message)
+    }
+}
@@ -0,0 +1,83 @@
+pub mod admin;
+pub mod app;
+pub mod auth_jwt;
+pub mod basis;
+pub mod error;
+pub mod routes;
+
+use crate::request;
+use crate::responser::Response;
+use components::registry;
+use components::state;
+use error::AppError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use tokio::runtime::Runtime;
+use validator::Validate;
+
+// ApiState is shared by all API handlers
+// usecases are async, so handlers block on the runtime
+pub struct ApiState {
+    pub rt: Runtime,
+    pub auth_state: state::AuthState,
+    pub admin_state: state::AdminState,
+    pub app_state: state::AppState,
+}
+
+impl ApiState {
+    pub fn new(rt: Runtime, reg: &registry::Registry) -> Self {
+        Self {
+            rt,
+            auth_state: reg.create_auth_state(),
+            admin_state: reg.create_admin_state(),
+            app_state: reg.create_app_state(),
+        }
+    }
+
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.rt.block_on(future)
+    }
+}
+
+// json() returns value as json response
+fn json<T: Serialize>(value: &T) -> Result<Response, AppError> {
+    match serde_json::to_string(value) {
+        Ok(body) => Ok(Response::json(200, &body)),
+        Err(e) => Err(AppError::InternalServerError(format!(
+            "Fatal error: {:?}",
+            e
+        ))),
+    }
+}
+
+// parse_body() deserializes json body and validates it
+fn parse_body<T: DeserializeOwned + Validate>(req: &request::Request) -> Result<T, AppError> {
+    let body = match &req.body {
+        Some(body) => body,
+        None => return Err(AppError::BadRequest("request body is empty".into())),
+    };
+    let body: T = match serde_json::from_str(body) {
+        Ok(body) => body,
+        Err(e) => {
+            return Err(AppError::BadRequest(format!(
+                "request body is invalid: {:?}",
+                e
+            )))
+        }
+    };
+    if let Err(e) = body.validate() {
+        return Err(AppError::BadRequest(format!(
+            "request body is invalid: {:?}",
+            e
+        )));
+    }
+    Ok(body)
+}
+
+// path_id() returns path parameter as id
+fn path_id(req: &request::Request, name: &str) -> Result<i32, AppError> {
+    req.param(name)
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| AppError::BadRequest(format!("{} is invalid", name)))
+}
@@ -0,0 +1,70 @@
+use crate::api::auth_jwt::JwtMiddleware;
+use crate::api::error::AppError;
+use crate::api::{admin, app, basis, ApiState};
+use crate::request;
+use crate::responser::Response;
+use crate::router::Router;
+
+// api() converts AppError into json response
+fn api<F>(handler: F) -> impl Fn(&request::Request, &ApiState) -> anyhow::Result<Response>
+where
+    F: Fn(&request::Request, &ApiState) -> Result<Response, AppError>,
+{
+    move |req, state| Ok(handler(req, state).unwrap_or_else(Response::from))
+}
+
+// [Path] /api/v1/admin
+// - admin login: [POST] `/admin/login`
+// - Show User List: [GET] `/admin/users`
+// - Show User: [GET] `/admin/users/{user_id}`
+// - Add User: [POST] `/admin/users`
+// - Update User: [PUT] `/admin/users/{user_id}`
+// - Remove User: [DELETE] `/admin/users/{user_id}`
+fn set_admin_routes(router: &mut Router<ApiState>) {
+    router.post("/api/v1/admin/login", api(admin::admin_login));
+    router.get("/api/v1/admin/users", api(admin::get_user_list));
+    router.post("/api/v1/admin/users", api(admin::add_user));
+    router.get("/api/v1/admin/users/:user_id", api(admin::get_user));
+    router.put("/api/v1/admin/users/:user_id", api(admin::update_user));
+    router.delete("/api/v1/admin/users/:user_id", api(admin::delete_user));
+}
+
+// [Path] /api/v1/app
+// - client login: [POST] `/app/login`
+// - Show Todos for Specific User: [GET] `/app/users/{user_id}/todos`
+// - Add Todo: [POST] `/app/users/{user_id}/todos`
+// - Show Todo for Specific User: [GET] `/app/users/{user_id}/todos/{id}`
+// - Update Todo for Specific User: [PUT] `/app/users/{user_id}/todos/{id}`
+// - Remove Todo for Specific User: [DELETE] `/app/users/{user_id}/todos/{id}`
+fn set_app_routes(router: &mut Router<ApiState>) {
+    router.post("/api/v1/app/login", api(app::app_login));
+    router.get(
+        "/api/v1/app/users/:user_id/todos",
+        api(app::get_user_todo_list),
+    );
+    router.post("/api/v1/app/users/:user_id/todos", api(app::add_user_todo));
+    router.get(
+        "/api/v1/app/users/:user_id/todos/:todo_id",
+        api(app::get_user_todo),
+    );
+    router.put(
+        "/api/v1/app/users/:user_id/todos/:todo_id",
+        api(app::update_user_todo),
+    );
+    router.delete(
+        "/api/v1/app/users/:user_id/todos/:todo_id",
+        api(app::delete_user_todo),
+    );
+}
+
+// set_api_routes() adds jwt middleware and all API routes
+pub fn set_api_routes(router: &mut Router<ApiState>) {
+    let auth_state = router.state().auth_state.clone();
+    router.add_middleware(JwtMiddleware::new(auth_state));
+
+    router.get("/api/v1/health", api(basis::health));
+    // for dummy before implementation
+    router.get("/openapi.json", api(basis::dummy));
+    set_admin_routes(router);
+    set_app_routes(router);
+}
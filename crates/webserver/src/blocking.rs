@@ -0,0 +1,81 @@
+use crate::errors::HTTPErrorMessage;
+use crate::parser;
+use crate::responser::Response;
+use crate::router::Router;
+use crate::websocket::handshake;
+use log::{debug, error};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+// run() accepts and handles one connection at a time
+// TLS is terminated when config is given
+pub fn run<S>(
+    listener: TcpListener,
+    router: &Router<S>,
+    tls: Option<Arc<ServerConfig>>,
+) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("{e}");
+                continue;
+            }
+        };
+        let result = match &tls {
+            Some(config) => handle_tls_connection(stream, router, config.clone()),
+            None => handle_connection(stream, router),
+        };
+        if let Err(e) = result {
+            error!("{e}");
+        }
+    }
+    Ok(())
+}
+
+// handle_connection() reads a request and writes response
+// websocket upgrade keeps the connection until its handler returns
+pub fn handle_connection<S, T: Read + Write>(
+    mut stream: T,
+    router: &Router<S>,
+) -> anyhow::Result<()> {
+    let response = match parser::get_req_info(&mut stream) {
+        Ok(Some(request)) if handshake::is_upgrade(&request) => {
+            debug!("{:?}", request);
+            return router.handle_websocket(request, &mut stream);
+        }
+        Ok(Some(request)) => {
+            debug!("{:?}", request);
+            router.handle(request)
+        }
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            debug!("invalid request: {e}");
+            let custom_error = e
+                .downcast_ref::<HTTPErrorMessage>()
+                .unwrap_or(&HTTPErrorMessage::BadRequest);
+            Response::error_html(custom_error)
+        }
+    };
+    stream.write_all(&response.to_bytes())?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn handle_tls_connection<S>(
+    stream: TcpStream,
+    router: &Router<S>,
+    config: Arc<ServerConfig>,
+) -> anyhow::Result<()> {
+    let mut tls_stream = StreamOwned::new(ServerConnection::new(config)?, stream);
+    handle_connection(&mut tls_stream, router)?;
+
+    // close_notify tells client that response is complete
+    tls_stream.conn.send_close_notify();
+    while tls_stream.conn.wants_write() {
+        tls_stream.conn.write_tls(&mut tls_stream.sock)?;
+    }
+    Ok(())
+}
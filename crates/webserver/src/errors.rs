@@ -5,27 +5,99 @@ pub enum HTTPErrorMessage {
     #[error("Bad Request")]
     BadRequest,
 
+    #[error("Both Content-Length and Transfer-Encoding are given")]
+    ConflictingLength,
+
+    #[error("Duplicate header: {0}")]
+    DuplicateHeader(String),
+
+    #[error("Forbidden")]
+    Forbidden,
+
+    #[error("Request header fields too large")]
+    HeaderFieldsTooLarge,
+
+    #[error("Incomplete request")]
+    IncompleteRequest,
+
+    #[error("Internal Server Error")]
+    InternalServerError,
+
+    #[error("Invalid Content-Length")]
+    InvalidContentLength,
+
+    #[error("Invalid header")]
+    InvalidHeader,
+
+    #[error("Invalid character in header")]
+    InvalidHeaderCharacter,
+
     #[error("Invalid request format")]
     InvalidRequestFormat,
 
+    #[error("Invalid request line")]
+    InvalidRequestLine,
+
+    #[error("Invalid Sec-WebSocket-Key")]
+    InvalidWebSocketKey,
+
     #[error("Missing required header: {0}")]
     MissingHeader(String),
 
     #[error("Not Found")]
     NotFound,
 
+    #[error("Payload too large")]
+    PayloadTooLarge,
+
+    #[error("Range Not Satisfiable")]
+    RangeNotSatisfiable,
+
+    #[error("Request line too long")]
+    RequestLineTooLong,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
     #[error("Unsupported content type")]
     UnsupportedContentType,
+
+    #[error("Unsupported Transfer-Encoding")]
+    UnsupportedTransferEncoding,
+
+    #[error("Unsupported WebSocket version")]
+    UnsupportedWebSocketVersion,
+
+    #[error("WebSocket is not supported in this mode")]
+    WebSocketNotSupported,
 }
 
 impl HTTPErrorMessage {
     pub fn status_code(&self) -> u16 {
         match self {
             HTTPErrorMessage::BadRequest => 400,
+            HTTPErrorMessage::ConflictingLength => 400,
+            HTTPErrorMessage::DuplicateHeader(_) => 400,
+            HTTPErrorMessage::Forbidden => 403,
+            HTTPErrorMessage::HeaderFieldsTooLarge => 431,
+            HTTPErrorMessage::IncompleteRequest => 400,
+            HTTPErrorMessage::InternalServerError => 500,
+            HTTPErrorMessage::InvalidContentLength => 400,
+            HTTPErrorMessage::InvalidHeader => 400,
+            HTTPErrorMessage::InvalidHeaderCharacter => 400,
             HTTPErrorMessage::InvalidRequestFormat => 400,
+            HTTPErrorMessage::InvalidRequestLine => 400,
+            HTTPErrorMessage::InvalidWebSocketKey => 400,
             HTTPErrorMessage::MissingHeader(_) => 400,
             HTTPErrorMessage::NotFound => 404,
+            HTTPErrorMessage::PayloadTooLarge => 413,
+            HTTPErrorMessage::RangeNotSatisfiable => 416,
+            HTTPErrorMessage::RequestLineTooLong => 414,
+            HTTPErrorMessage::Unauthorized => 401,
             HTTPErrorMessage::UnsupportedContentType => 415,
+            HTTPErrorMessage::UnsupportedTransferEncoding => 501,
+            HTTPErrorMessage::UnsupportedWebSocketVersion => 426,
+            HTTPErrorMessage::WebSocketNotSupported => 501,
         }
     }
 
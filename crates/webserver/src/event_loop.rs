@@ -0,0 +1,350 @@
+use crate::errors::HTTPErrorMessage;
+use crate::parser;
+use crate::responser::Response;
+use crate::router::Router;
+use crate::websocket::handshake;
+use log::{debug, error};
+use mio::event::Event;
+use mio::net::{TcpListener, TcpStream};
+use mio::{Events, Interest, Poll, Token};
+use rustls::{ServerConfig, ServerConnection};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+const SERVER: Token = Token(0);
+const MAX_EVENTS: usize = 128;
+
+// Connection keeps buffers for a non-blocking client socket
+// with TLS, encrypted bytes are buffered in rustls instead of write_buf
+struct Connection {
+    stream: TcpStream,
+    tls: Option<ServerConnection>,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    written: usize,
+    responded: bool,
+}
+
+impl Connection {
+    fn new(stream: TcpStream, tls: Option<ServerConnection>) -> Self {
+        Self {
+            stream,
+            tls,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            written: 0,
+            responded: false,
+        }
+    }
+
+    // read_available() appends received plaintext to read_buf
+    // returns true when peer closed the connection
+    fn read_available(&mut self) -> io::Result<bool> {
+        let mut chunk = [0; 4096];
+        let Some(tls) = &mut self.tls else {
+            loop {
+                match self.stream.read(&mut chunk) {
+                    Ok(0) => return Ok(true),
+                    Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        };
+
+        let mut eof = false;
+        loop {
+            match tls.read_tls(&mut self.stream) {
+                Ok(0) => {
+                    eof = true;
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if let Err(e) = tls.process_new_packets() {
+            // send alert to client before closing
+            let _ = tls.write_tls(&mut self.stream);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+        loop {
+            match tls.reader().read(&mut chunk) {
+                // close_notify
+                Ok(0) => return Ok(true),
+                Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(eof),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(true),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn set_response(&mut self, response: Response) -> io::Result<()> {
+        self.responded = true;
+        match &mut self.tls {
+            Some(tls) => {
+                tls.writer().write_all(&response.to_bytes())?;
+                tls.send_close_notify();
+            }
+            None => {
+                self.write_buf = response.to_bytes();
+                self.written = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn wants_write(&self) -> bool {
+        match &self.tls {
+            Some(tls) => tls.wants_write(),
+            None => self.written < self.write_buf.len(),
+        }
+    }
+
+    // flush() writes pending bytes such as handshake and response
+    // returns true when nothing is left
+    fn flush(&mut self) -> io::Result<bool> {
+        while self.wants_write() {
+            let result = match &mut self.tls {
+                Some(tls) => tls.write_tls(&mut self.stream),
+                None => self.stream.write(&self.write_buf[self.written..]),
+            };
+            match result {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) if self.tls.is_none() => self.written += n,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+}
+
+// run() drives all connections on a single thread using mio
+// router, middleware and parser are shared with the blocking server
+// TLS is terminated when config is given
+pub fn run<S>(
+    listener: std::net::TcpListener,
+    router: &Router<S>,
+    tls: Option<Arc<ServerConfig>>,
+) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let mut listener = TcpListener::from_std(listener);
+
+    let mut poll = Poll::new()?;
+    let mut events = Events::with_capacity(MAX_EVENTS);
+    poll.registry()
+        .register(&mut listener, SERVER, Interest::READABLE)?;
+
+    let mut connections: HashMap<Token, Connection> = HashMap::new();
+    let mut next_token = SERVER.0 + 1;
+
+    loop {
+        if let Err(e) = poll.poll(&mut events, None) {
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+
+        for event in events.iter() {
+            match event.token() {
+                SERVER => loop {
+                    // accept until there are no more pending connections
+                    match listener.accept() {
+                        Ok((mut stream, addr)) => {
+                            debug!("accepted connection from {addr}");
+                            let tls_conn = match &tls {
+                                Some(config) => match ServerConnection::new(config.clone()) {
+                                    Ok(conn) => Some(conn),
+                                    Err(e) => {
+                                        error!("{e}");
+                                        continue;
+                                    }
+                                },
+                                None => None,
+                            };
+                            let token = Token(next_token);
+                            next_token += 1;
+                            poll.registry()
+                                .register(&mut stream, token, Interest::READABLE)?;
+                            connections.insert(token, Connection::new(stream, tls_conn));
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(e) => return Err(e),
+                    }
+                },
+                token => {
+                    let done = match connections.get_mut(&token) {
+                        Some(conn) => handle_event(&poll, conn, token, event, router),
+                        None => false,
+                    };
+                    if done {
+                        if let Some(mut conn) = connections.remove(&token) {
+                            let _ = poll.registry().deregister(&mut conn.stream);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// handle_event() returns true when the connection should be closed
+fn handle_event<S>(
+    poll: &Poll,
+    conn: &mut Connection,
+    token: Token,
+    event: &Event,
+    router: &Router<S>,
+) -> bool {
+    if event.is_readable() && !conn.responded {
+        if let Err(e) = read_request(conn, router) {
+            debug!("{e}");
+            return true;
+        }
+    }
+
+    // handshake or response is written as much as possible
+    match conn.flush() {
+        // one request per connection, same as blocking mode
+        Ok(true) if conn.responded => return true,
+        Ok(_) => {}
+        Err(e) => {
+            error!("{e}");
+            return true;
+        }
+    }
+
+    let interest = if conn.wants_write() {
+        Interest::READABLE | Interest::WRITABLE
+    } else {
+        Interest::READABLE
+    };
+    if let Err(e) = poll
+        .registry()
+        .reregister(&mut conn.stream, token, interest)
+    {
+        error!("{e}");
+        return true;
+    }
+    false
+}
+
+// read_request() reads available bytes and builds response once the request is complete
+fn read_request<S>(conn: &mut Connection, router: &Router<S>) -> anyhow::Result<()> {
+    let eof = conn.read_available()?;
+    if eof && conn.read_buf.is_empty() {
+        return Err(anyhow::anyhow!("connection closed by client"));
+    }
+
+    // size limits are checked by parser
+    let response = match parser::get_request_length(&conn.read_buf) {
+        Ok(Some(len)) => match parser::parse_request(&conn.read_buf[..len]) {
+            // handler can't block the loop, so websocket is served only in blocking mode
+            Ok(request) if handshake::is_upgrade(&request) => {
+                Response::error_html(&HTTPErrorMessage::WebSocketNotSupported)
+            }
+            Ok(request) => {
+                debug!("{:?}", request);
+                router.handle(request)
+            }
+            Err(e) => Response::error_html(&e),
+        },
+        // rest of request never comes
+        Ok(None) if eof => Response::error_html(&HTTPErrorMessage::IncompleteRequest),
+        // wait for the rest of request
+        Ok(None) => return Ok(()),
+        Err(e) => Response::error_html(&e),
+    };
+
+    conn.set_response(response)?;
+    Ok(())
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler;
+    use crate::middleware;
+    use std::net::{Shutdown, SocketAddr};
+    use std::thread;
+
+    fn spawn_server() -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut router = Router::new();
+            router.add_middleware(middleware::ContentTypeMiddleware);
+            router.get("/", handler::handler_a);
+            let _ = run(listener, &router, None);
+        });
+        addr
+    }
+
+    fn send(addr: SocketAddr, chunks: &[&[u8]]) -> String {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        for chunk in chunks {
+            stream.write_all(chunk).unwrap();
+            stream.flush().unwrap();
+            thread::sleep(std::time::Duration::from_millis(20));
+        }
+        stream.shutdown(Shutdown::Write).unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        res
+    }
+
+    #[test]
+    fn test_event_loop_serves_routes() {
+        let addr = spawn_server();
+
+        let res = send(addr, &[b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"]);
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let res = send(addr, &[b"GET /none HTTP/1.1\r\nHost: localhost\r\n\r\n"]);
+        assert!(res.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn test_event_loop_partial_request() {
+        let addr = spawn_server();
+
+        // request arrives in several pieces
+        let res = send(
+            addr,
+            &[b"GET / HTT", b"P/1.1\r\nHost: loc", b"alhost\r\n\r\n"],
+        );
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn test_event_loop_bad_request() {
+        let addr = spawn_server();
+
+        let res = send(addr, &[b"\x01\x02 / HTTP/1.1\r\n\r\n"]);
+        assert!(res.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn test_event_loop_websocket_not_supported() {
+        let addr = spawn_server();
+
+        let res = send(
+            addr,
+            &[b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n"],
+        );
+        assert!(res.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+    }
+}
@@ -1,9 +1,10 @@
 use crate::errors::HTTPErrorMessage;
 use crate::request;
 use crate::responser::Response;
+use crate::websocket::WebSocket;
 use log::debug;
 
-pub fn handler_a(req: &request::Request) -> anyhow::Result<Response> {
+pub fn handler_a<S>(req: &request::Request, _state: &S) -> anyhow::Result<Response> {
     if let Some(query) = &req.query {
         debug!(" received query: {}", query);
     }
@@ -12,7 +13,7 @@ pub fn handler_a(req: &request::Request) -> anyhow::Result<Response> {
     Ok(response)
 }
 
-pub fn handler_b(req: &request::Request) -> anyhow::Result<Response> {
+pub fn handler_b<S>(req: &request::Request, _state: &S) -> anyhow::Result<Response> {
     if let Some(body) = &req.body {
         debug!(" Received POST data: {}", body);
         //let response = "HTTP/1.1 200 OK\r\n\r\n<h1>Post Data Received</h1>\r\n".to_string();
@@ -24,3 +25,12 @@ pub fn handler_b(req: &request::Request) -> anyhow::Result<Response> {
         Ok(response)
     }
 }
+
+// ws_echo() sends back every message until client closes
+pub fn ws_echo<S>(_req: &request::Request, _state: &S, ws: &mut WebSocket) -> anyhow::Result<()> {
+    while let Some(message) = ws.recv()? {
+        debug!(" received message: {:?}", message);
+        ws.send(&message)?;
+    }
+    Ok(())
+}
@@ -1,7 +1,13 @@
+pub mod api;
+pub mod blocking;
 pub mod errors;
+pub mod event_loop;
 pub mod handler;
 pub mod middleware;
 pub mod parser;
 pub mod request;
 pub mod responser;
 pub mod router;
+pub mod static_files;
+pub mod tls;
+pub mod websocket;
@@ -1,106 +1,141 @@
-use clap::Parser;
-use log::{debug, error, info};
-use std::io::prelude::*;
-use std::net::{TcpListener, TcpStream};
+use clap::{Parser, ValueEnum};
+use components::registry;
+use components::toml;
+use log::{error, info};
+use std::net::TcpListener;
+use tokio::runtime::Builder;
 
 // local
-use webserver::errors::HTTPErrorMessage;
+use webserver::api::{routes, ApiState};
+use webserver::blocking;
+use webserver::event_loop;
 use webserver::handler;
 use webserver::middleware;
-use webserver::parser;
-use webserver::responser::Response;
 use webserver::router;
+use webserver::static_files::StaticFiles;
+use webserver::tls;
+
+/// How connections are processed
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    /// Accept and handle one connection at a time
+    Blocking,
+    /// Non-blocking sockets multiplexed by mio
+    EventLoop,
+}
 
 /// single thread HTTP server
 #[derive(Parser)]
 #[command(name = "SingleThreadHTTPServer")]
 #[command(about = "A single thread HTTP server with middleware", long_about = None)]
 struct Args {
-    /// Port number to listen on
-    #[arg(short, long, default_value_t = 8080)]
-    port: u16,
+    /// Config path, API server is enabled when it's given
+    conf: Option<String>,
+
+    /// Debug mode
+    #[arg(short('d'))]
+    debug_mode: bool,
+
+    /// Port number to listen on, overrides config
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// Directory to serve under `/static`
+    #[arg(long)]
+    static_dir: Option<String>,
+
+    /// Show file list for directory without index.html
+    #[arg(long)]
+    directory_index: bool,
+
+    /// Connection handling mode
+    #[arg(long, value_enum, default_value_t = Mode::Blocking)]
+    mode: Mode,
+
+    /// Certificate chain in PEM to enable HTTPS
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<String>,
+
+    /// Private key in PEM to enable HTTPS
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<String>,
 }
 
-fn handle_connection(mut stream: TcpStream, router: &router::Router) -> anyhow::Result<()> {
-    // Call get_method_path and handle each scenario
-    // let (method, path, query, body) = match parser::get_req_info(&stream)? {
-    //     Some((method, path, query, body)) => (method, path, query, body), // Continue if method and path are present
-    //     None => return Ok(()), // Exit early if None is returned
-    // };
-    let request = match parser::get_req_info(&stream)? {
-        Some(req) => req,
-        None => return Ok(()),
-    };
-    debug!("{:?}", request);
-
-    // middleware
-    if let Err(e) = router.run_middleware(&request) {
-        let custom_error = e
-            .downcast_ref::<HTTPErrorMessage>()
-            .unwrap_or(&HTTPErrorMessage::InvalidRequestFormat);
-
-        //let response = custom_error.response();
-        let response = Response::error_html(custom_error);
-        stream.write_all(response.to_string().as_bytes())?;
-        stream.flush()?;
-        return Ok(()); // Stop processing further after the error
+// configure_router() sets routes shared by demo and API server
+fn configure_router<S: 'static>(router: &mut router::Router<S>, args: &Args) {
+    router.add_middleware(middleware::ContentTypeMiddleware);
+    router.get("/", handler::handler_a);
+    router.post("/submit", handler::handler_b);
+    router.websocket("/ws/echo", handler::ws_echo);
+    if let Some(static_dir) = &args.static_dir {
+        info!("serve {static_dir} on /static");
+        router.mount_static(
+            StaticFiles::new("/static", static_dir).with_directory_index(args.directory_index),
+        );
     }
+}
 
-    // handler
-    let handler = router.route(&request.method, &request.path);
-    match handler {
-        Some(h) => {
-            let response = h(&request)?;
-            stream.write_all(response.to_string().as_bytes())?;
-        }
-        None => {
-            //let response = HTTPErrorMessage::NotFound.response();
-            let response = Response::error_html(&HTTPErrorMessage::NotFound);
-            stream.write_all(response.to_string().as_bytes())?;
+fn serve<S>(router: &router::Router<S>, addr: &str, args: &Args) {
+    // TLS
+    let tls = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => match tls::load_server_config(cert, key) {
+            Ok(config) => Some(config),
+            Err(e) => panic!("fail to load certificate: {:?}", e),
+        },
+        _ => None,
+    };
+
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    info!("run web server on {scheme}://{addr}");
+    let listener = TcpListener::bind(addr).unwrap();
+
+    let result = match args.mode {
+        Mode::Blocking => blocking::run(listener, router, tls),
+        Mode::EventLoop => {
+            info!("event loop mode");
+            event_loop::run(listener, router, tls)
         }
+    };
+    if let Err(e) = result {
+        error!("{e}");
     }
-    // match (method.as_ref(), path.as_ref()) {
-    //     ("GET", "/") => {
-    //         let response = "HTTP/1.1 200 OK\r\n\r\n<h1>Hello, GET!</h1>";
-    //         stream.write_all(response.as_bytes()).unwrap();
-    //     }
-    //     ("POST", "/submit") => {
-    //         println!("Received POST data: {}", body.as_ref().unwrap());
-    //         let response = "HTTP/1.1 200 OK\r\n\r\n<h1>Post Data Received</h1>";
-    //         stream.write_all(response.as_bytes()).unwrap();
-    //     }
-    //     _ => {
-    //         let response = "HTTP/1.1 404 NOT FOUND\r\n\r\n<h1>404 Not Found</h1>";
-    //         stream.write_all(response.as_bytes()).unwrap();
-    //     }
-    // }
-
-    stream.flush()?;
-    Ok(())
 }
 
 fn main() {
-    env_logger::init();
-
     // args
     let args = Args::parse();
-    let port = args.port;
 
-    // configure router
-    let mut router = router::Router::new();
-    router.add_middleware(middleware::ContentTypeMiddleware);
-    router.get("/", handler::handler_a);
-    router.post("/submit", handler::handler_b);
+    let level = if args.debug_mode { "debug" } else { "info" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).init();
 
-    // initialize server
-    let addr = format!("127.0.0.1:{}", port);
-    info!("run web server on {addr}");
-    let listener = TcpListener::bind(addr).unwrap();
+    let Some(file_path) = args.conf.as_deref() else {
+        // demo server without config
+        let mut router = router::Router::new();
+        configure_router(&mut router, &args);
+        let addr = format!("127.0.0.1:{}", args.port.unwrap_or(8080));
+        serve(&router, &addr, &args);
+        return;
+    };
 
-    for stream in listener.incoming() {
-        let stream = stream.unwrap();
-        if let Err(e) = handle_connection(stream, &router) {
-            error!("{e}");
+    // load toml
+    let config = match toml::load_config(file_path) {
+        Ok(conf) => conf,
+        Err(error) => {
+            panic!("fail to load toml file [{}]: {:?}", file_path, error)
         }
-    }
+    };
+
+    // registry and get each states
+    let rt = Builder::new_multi_thread().enable_all().build().unwrap();
+    let reg = rt.block_on(registry::Registry::new(config)).unwrap(); // may panic
+    let addr = format!(
+        "{}:{}",
+        reg.conf.server.host,
+        args.port.unwrap_or(reg.conf.server.port)
+    );
+
+    let mut router = router::Router::with_state(ApiState::new(rt, &reg));
+    configure_router(&mut router, &args);
+    routes::set_api_routes(&mut router);
+    serve(&router, &addr, &args);
 }
@@ -1,14 +1,22 @@
+use crate::errors::HTTPErrorMessage;
 use crate::request;
 use httparse::EMPTY_HEADER;
 use log::debug;
 use std::collections::HashMap;
 use std::io::prelude::*;
-use std::net::TcpStream;
 use std::str;
 use url::Url;
 
+// request line longer than this is rejected with 414
+pub const MAX_REQUEST_LINE_LEN: usize = 8 * 1024;
+// request line and headers bigger than this are rejected with 431
+pub const MAX_HEAD_LEN: usize = 16 * 1024;
+// body bigger than this is rejected with 413
+pub const MAX_BODY_LEN: usize = 1024 * 1024;
+const MAX_HEADERS: usize = 32;
+
 #[allow(dead_code)]
-fn get_host<'a>(headers: &'a [httparse::Header<'a>; 16]) -> &'a str {
+fn get_host<'a>(headers: &'a [httparse::Header<'a>]) -> &'a str {
     headers
         .iter()
         .find(|header| header.name.eq_ignore_ascii_case("Host"))
@@ -16,16 +24,6 @@ fn get_host<'a>(headers: &'a [httparse::Header<'a>; 16]) -> &'a str {
         .unwrap_or("")
 }
 
-fn get_request_body(buffer: &[u8]) -> Option<String> {
-    // .ok() method converts the Result into an Option
-    let request_str = str::from_utf8(buffer).ok()?;
-    let body_start = request_str.find("\r\n\r\n")? + 4;
-    if request_str[body_start..].is_empty() {
-        return None;
-    }
-    Some(request_str[body_start..].to_string())
-}
-
 pub fn get_query_parameters(path: &str) -> Option<(&str, &str)> {
     // sprit path into path and query by `?`
     // if let Some(pos) = path.find('?') {
@@ -55,8 +53,9 @@ fn parse_url(url_path: &str) {
     )
 }
 
-// get_req_info() returns Request
-pub fn get_req_info(mut stream: &TcpStream) -> anyhow::Result<Option<request::Request>> {
+// get_req_info() reads stream and returns Request
+// None is returned when connection is closed before anything is sent
+pub fn get_req_info<R: Read>(mut stream: R) -> anyhow::Result<Option<request::Request>> {
     // stream size may be bigger than 1024
     // let mut buffer = [0; 1024];
     // let bytes_read = stream.read(&mut buffer)?;
@@ -72,7 +71,6 @@ pub fn get_req_info(mut stream: &TcpStream) -> anyhow::Result<Option<request::Re
     const CHUNK_SIZE: usize = 512;
     let mut buffer = Vec::new();
     let mut chunk = [0; CHUNK_SIZE];
-    let mut bytes_read: usize = 0;
     loop {
         match stream.read(&mut chunk) {
             Ok(0) => {
@@ -80,12 +78,14 @@ pub fn get_req_info(mut stream: &TcpStream) -> anyhow::Result<Option<request::Re
             }
             Ok(chunk_bytes_read) => {
                 debug!("Read {} bytes", chunk_bytes_read);
-                bytes_read += chunk_bytes_read;
                 buffer.extend_from_slice(&chunk[..chunk_bytes_read]);
-                if CHUNK_SIZE > chunk_bytes_read {
-                    break;
+                // body may arrive after headers, so read until Content-Length is satisfied
+                match get_request_length(&buffer) {
+                    Ok(Some(_)) | Err(_) => break,
+                    Ok(None) => {}
                 }
             }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
             Err(e) => {
                 debug!("Error reading stream: {}", e);
                 break;
@@ -93,38 +93,55 @@ pub fn get_req_info(mut stream: &TcpStream) -> anyhow::Result<Option<request::Re
         }
     }
 
-    // handle headers
-    let mut headers = [EMPTY_HEADER; 16];
-    let mut req = httparse::Request::new(&mut headers);
-    req.parse(&buffer)?;
+    if buffer.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(parse_request(&buffer)?))
+}
 
-    // get method and path
-    let method = req.method.unwrap_or("").to_string();
-    let path = req.path.unwrap_or("");
+// get_request_length() returns total length of headers and body
+// once the whole request has been received, or None while it's still partial
+pub fn get_request_length(buffer: &[u8]) -> Result<Option<usize>, HTTPErrorMessage> {
+    let head = match parse_head(buffer)? {
+        Some(head) => head,
+        None => return Ok(None),
+    };
 
-    // get headers
-    let mut headers_map = HashMap::new();
-    for header in req.headers.iter() {
-        let name = header.name.to_string();
-        let value = std::str::from_utf8(header.value)?.to_string();
-        headers_map.insert(name, value);
+    let total = head.header_len + head.content_length;
+    if buffer.len() < total {
+        return Ok(None);
     }
+    Ok(Some(total))
+}
+
+// parse_request() returns Request from received bytes
+// bytes after Content-Length are ignored because connection is closed after response
+pub fn parse_request(buffer: &[u8]) -> Result<request::Request, HTTPErrorMessage> {
+    let head = parse_head(buffer)?.ok_or(HTTPErrorMessage::IncompleteRequest)?;
 
     // get query
     // can be replaced by `Url::parse``
-    let (path, query) = if let Some((path, query)) = get_query_parameters(path) {
+    let (path, query) = if let Some((path, query)) = get_query_parameters(&head.path) {
         (path, Some(query))
     } else {
-        (path, None)
+        (head.path.as_str(), None)
     };
     // convert Option<&str> to Option<String>
     let query = query.map(|s| s.to_string());
 
     // get body
-    let body: Option<String> = if method == "POST" {
-        get_request_body(&buffer[..bytes_read])
-    } else {
+    let body_end = head.header_len + head.content_length;
+    let body = buffer
+        .get(head.header_len..body_end)
+        .ok_or(HTTPErrorMessage::IncompleteRequest)?;
+    let body: Option<String> = if body.is_empty() {
         None
+    } else {
+        Some(
+            str::from_utf8(body)
+                .map_err(|_| HTTPErrorMessage::BadRequest)?
+                .to_string(),
+        )
     };
 
     // actually, no needs to host from header
@@ -135,11 +152,460 @@ pub fn get_req_info(mut stream: &TcpStream) -> anyhow::Result<Option<request::Re
     //     host, method, path
     // );
 
-    Ok(Some(request::Request::new(
-        method,
+    Ok(request::Request::new(
+        head.method,
         path.to_string(),
-        headers_map,
+        head.headers,
         query,
         body,
-    )))
+    ))
+}
+
+// Head keeps request line and headers
+struct Head {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    header_len: usize,
+    content_length: usize,
+}
+
+// parse_head() parses request line and headers
+// None is returned while headers are still partial
+fn parse_head(buffer: &[u8]) -> Result<Option<Head>, HTTPErrorMessage> {
+    check_request_line(buffer)?;
+
+    let mut headers = [EMPTY_HEADER; MAX_HEADERS];
+    let mut req = httparse::Request::new(&mut headers);
+    let header_len = match req.parse(buffer) {
+        Ok(httparse::Status::Complete(header_len)) => header_len,
+        Ok(httparse::Status::Partial) if buffer.len() > MAX_HEAD_LEN => {
+            return Err(HTTPErrorMessage::HeaderFieldsTooLarge)
+        }
+        Ok(httparse::Status::Partial) => return Ok(None),
+        Err(e) => return Err(from_httparse_error(e)),
+    };
+    if header_len > MAX_HEAD_LEN {
+        return Err(HTTPErrorMessage::HeaderFieldsTooLarge);
+    }
+    check_line_endings(&buffer[..header_len])?;
+
+    let headers_map = get_headers(req.headers)?;
+    let content_length = get_content_length(&headers_map)?;
+
+    Ok(Some(Head {
+        method: req.method.unwrap_or("").to_string(),
+        path: req.path.unwrap_or("").to_string(),
+        headers: headers_map,
+        header_len,
+        content_length,
+    }))
+}
+
+fn from_httparse_error(e: httparse::Error) -> HTTPErrorMessage {
+    debug!("httparse error: {}", e);
+    match e {
+        httparse::Error::HeaderName => HTTPErrorMessage::InvalidHeader,
+        httparse::Error::HeaderValue | httparse::Error::NewLine => {
+            HTTPErrorMessage::InvalidHeaderCharacter
+        }
+        httparse::Error::TooManyHeaders => HTTPErrorMessage::HeaderFieldsTooLarge,
+        _ => HTTPErrorMessage::InvalidRequestLine,
+    }
+}
+
+// check_request_line() rejects too long request line even if it's still partial
+fn check_request_line(buffer: &[u8]) -> Result<(), HTTPErrorMessage> {
+    // empty lines before request line are allowed
+    let start = buffer
+        .iter()
+        .position(|b| *b != b'\r' && *b != b'\n')
+        .unwrap_or(buffer.len());
+    let line_len = buffer[start..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .unwrap_or(buffer.len() - start);
+    if line_len > MAX_REQUEST_LINE_LEN {
+        return Err(HTTPErrorMessage::RequestLineTooLong);
+    }
+    Ok(())
+}
+
+// check_line_endings() rejects bare LF
+// httparse accepts it, but proxies may split headers differently
+fn check_line_endings(head: &[u8]) -> Result<(), HTTPErrorMessage> {
+    let bare_lf = head
+        .iter()
+        .enumerate()
+        .any(|(i, b)| *b == b'\n' && (i == 0 || head[i - 1] != b'\r'));
+    if bare_lf {
+        return Err(HTTPErrorMessage::InvalidHeaderCharacter);
+    }
+    Ok(())
+}
+
+// get_headers() converts headers into map
+// same name headers are combined by comma, except ones related to framing
+fn get_headers(headers: &[httparse::Header]) -> Result<HashMap<String, String>, HTTPErrorMessage> {
+    const SINGLE_HEADERS: [&str; 3] = ["Content-Length", "Host", "Transfer-Encoding"];
+
+    let mut headers_map: HashMap<String, String> = HashMap::new();
+    for header in headers {
+        let value = str::from_utf8(header.value)
+            .map_err(|_| HTTPErrorMessage::InvalidHeaderCharacter)?
+            .to_string();
+
+        let same = headers_map
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(header.name));
+        match same {
+            Some((name, _)) if SINGLE_HEADERS.iter().any(|s| s.eq_ignore_ascii_case(name)) => {
+                return Err(HTTPErrorMessage::DuplicateHeader(header.name.to_string()));
+            }
+            Some((_, v)) => {
+                v.push_str(", ");
+                v.push_str(&value);
+            }
+            None => {
+                headers_map.insert(header.name.to_string(), value);
+            }
+        }
+    }
+    Ok(headers_map)
+}
+
+// get_content_length() returns body length
+// chunked body is not supported, so Transfer-Encoding is rejected
+fn get_content_length(headers: &HashMap<String, String>) -> Result<usize, HTTPErrorMessage> {
+    let find = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+
+    let content_length = find("Content-Length");
+    if find("Transfer-Encoding").is_some() {
+        if content_length.is_some() {
+            return Err(HTTPErrorMessage::ConflictingLength);
+        }
+        return Err(HTTPErrorMessage::UnsupportedTransferEncoding);
+    }
+
+    let Some(value) = content_length else {
+        return Ok(0);
+    };
+    // only digits are allowed, `+1`, `1,1` or ` 1` are rejected
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(HTTPErrorMessage::InvalidContentLength);
+    }
+    let length: usize = value
+        .parse()
+        .map_err(|_| HTTPErrorMessage::InvalidContentLength)?;
+    if length > MAX_BODY_LEN {
+        return Err(HTTPErrorMessage::PayloadTooLarge);
+    }
+    Ok(length)
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    // ChunkedReader returns input split into given sizes like network packets
+    struct ChunkedReader {
+        data: Vec<u8>,
+        pos: usize,
+        sizes: Vec<usize>,
+    }
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let size = if self.sizes.is_empty() {
+                buf.len()
+            } else {
+                self.sizes.remove(0).max(1)
+            };
+            let n = size.min(buf.len()).min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    // status() returns 200 for valid request, otherwise status code of error
+    fn status(input: &[u8]) -> u16 {
+        match get_req_info(Cursor::new(input.to_vec())) {
+            Ok(_) => 200,
+            Err(e) => e
+                .downcast_ref::<HTTPErrorMessage>()
+                .expect("error must be HTTPErrorMessage")
+                .status_code(),
+        }
+    }
+
+    fn parse_err(input: &[u8]) -> HTTPErrorMessage {
+        parse_request(input).expect_err("request must be rejected")
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let req = parse_request(
+            b"POST /submit?id=1 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4\r\n\r\nbody",
+        )
+        .unwrap();
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/submit");
+        assert_eq!(req.query.as_deref(), Some("id=1"));
+        assert_eq!(req.header("host"), Some("localhost"));
+        assert_eq!(req.body.as_deref(), Some("body"));
+    }
+
+    #[test]
+    fn test_get_request_length() {
+        let input = b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody";
+        assert!(matches!(get_request_length(&input[..10]), Ok(None)));
+        assert!(matches!(
+            get_request_length(&input[..input.len() - 1]),
+            Ok(None)
+        ));
+        assert!(matches!(get_request_length(input), Ok(Some(n)) if n == input.len()));
+    }
+
+    #[test]
+    fn test_empty_stream() {
+        assert!(get_req_info(Cursor::new(Vec::new())).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_duplicate_headers() {
+        let req =
+            parse_request(b"GET / HTTP/1.1\r\nAccept: text/html\r\naccept: */*\r\n\r\n").unwrap();
+        assert_eq!(req.header("Accept"), Some("text/html, */*"));
+
+        assert!(matches!(
+            parse_err(b"GET / HTTP/1.1\r\nHost: a\r\nHost: b\r\n\r\n"),
+            HTTPErrorMessage::DuplicateHeader(name) if name == "Host"
+        ));
+        assert!(matches!(
+            parse_err(b"POST / HTTP/1.1\r\nContent-Length: 1\r\nContent-Length: 1\r\n\r\na"),
+            HTTPErrorMessage::DuplicateHeader(_)
+        ));
+    }
+
+    #[test]
+    fn test_framing_errors() {
+        assert!(matches!(
+            parse_err(
+                b"POST / HTTP/1.1\r\nContent-Length: 1\r\nTransfer-Encoding: chunked\r\n\r\n"
+            ),
+            HTTPErrorMessage::ConflictingLength
+        ));
+        assert!(matches!(
+            parse_err(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            HTTPErrorMessage::UnsupportedTransferEncoding
+        ));
+        assert!(matches!(
+            parse_err(b"POST / HTTP/1.1\r\nContent-Length: 0x10\r\n\r\n"),
+            HTTPErrorMessage::InvalidContentLength
+        ));
+        assert!(matches!(
+            parse_err(b"POST / HTTP/1.1\r\nContent-Length: 2000000\r\n\r\n"),
+            HTTPErrorMessage::PayloadTooLarge
+        ));
+        assert!(matches!(
+            parse_err(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nabc"),
+            HTTPErrorMessage::IncompleteRequest
+        ));
+    }
+
+    #[test]
+    fn test_header_errors() {
+        assert!(matches!(
+            parse_err(b"GET / HTTP/1.1\r\nX-A: a\nX-B: b\r\n\r\n"),
+            HTTPErrorMessage::InvalidHeaderCharacter
+        ));
+        assert!(matches!(
+            parse_err(b"GET / HTTP/1.1\r\nX-A: a\x00\r\n\r\n"),
+            HTTPErrorMessage::InvalidHeaderCharacter
+        ));
+        assert!(matches!(
+            parse_err(b"GET / HTTP/1.1\r\nX A: a\r\n\r\n"),
+            HTTPErrorMessage::InvalidHeader
+        ));
+        assert!(matches!(
+            parse_err(b"GET / HTTP/1.1\r\nHost: a\r\n"),
+            HTTPErrorMessage::IncompleteRequest
+        ));
+    }
+
+    #[test]
+    fn test_request_line_errors() {
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_REQUEST_LINE_LEN));
+        assert!(matches!(
+            parse_err(long.as_bytes()),
+            HTTPErrorMessage::RequestLineTooLong
+        ));
+        // rejected before line is completed
+        assert!(matches!(
+            get_request_length(&long.as_bytes()[..MAX_REQUEST_LINE_LEN + 10]),
+            Err(HTTPErrorMessage::RequestLineTooLong)
+        ));
+        assert!(matches!(
+            parse_err(b"GET / FOO/1.1\r\n\r\n"),
+            HTTPErrorMessage::InvalidRequestLine
+        ));
+    }
+
+    // corpus file name starts with expected status, e.g. `414__long_request_line.http`
+    #[test]
+    fn test_corpus() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/parser");
+        let mut count = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            let expected = match name.split("__").next().unwrap() {
+                "ok" => 200,
+                code => code.parse().unwrap(),
+            };
+            let input = fs::read(&path).unwrap();
+            assert_eq!(status(&input), expected, "{name}");
+
+            // result must not depend on how bytes arrive
+            for size in [1, 7, 64] {
+                let reader = ChunkedReader {
+                    data: input.clone(),
+                    pos: 0,
+                    sizes: vec![size; input.len()],
+                };
+                let actual = match get_req_info(reader) {
+                    Ok(_) => 200,
+                    Err(e) => e.downcast_ref::<HTTPErrorMessage>().unwrap().status_code(),
+                };
+                assert_eq!(actual, expected, "{name} with chunk size {size}");
+            }
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+
+    fn corpus() -> Vec<Vec<u8>> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/parser");
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+            .collect()
+    }
+
+    fn token() -> impl Strategy<Value = String> {
+        "[A-Za-z][A-Za-z0-9-]{0,15}"
+    }
+
+    proptest! {
+        // arbitrary bytes never panic and valid result keeps invariants
+        #[test]
+        fn prop_arbitrary_bytes(input in proptest::collection::vec(any::<u8>(), 0..2048)) {
+            if let Ok(Some(req)) = get_req_info(Cursor::new(input)) {
+                prop_assert!(!req.method.is_empty());
+            }
+        }
+
+        // mutated corpus never panics and returns HTTPErrorMessage on failure
+        #[test]
+        fn prop_mutated_corpus(
+            index in any::<prop::sample::Index>(),
+            mutations in proptest::collection::vec((any::<prop::sample::Index>(), any::<u8>(), 0..3u8), 1..8),
+        ) {
+            let corpus = corpus();
+            let mut input = corpus[index.index(corpus.len())].clone();
+            for (pos, byte, op) in mutations {
+                let at = pos.index(input.len() + 1);
+                match op {
+                    0 => input.insert(at, byte),
+                    1 if at < input.len() => input[at] = byte,
+                    _ => input.truncate(at),
+                }
+            }
+            match get_req_info(Cursor::new(input)) {
+                Ok(_) => {}
+                Err(e) => prop_assert!(e.downcast_ref::<HTTPErrorMessage>().is_some()),
+            }
+        }
+
+        // well-formed request is parsed the same regardless of chunking
+        #[test]
+        fn prop_valid_request(
+            method in prop::sample::select(vec!["GET", "POST", "PUT", "DELETE"]),
+            segments in proptest::collection::vec("[a-z0-9_]{1,10}", 0..5),
+            headers in proptest::collection::vec((token(), "[ -~]{0,30}"), 0..10),
+            body in "[ -~]{0,200}",
+            chunk in 1..64usize,
+        ) {
+            let path = format!("/{}", segments.join("/"));
+            let mut raw = format!("{method} {path} HTTP/1.1\r\n");
+            for (name, value) in &headers {
+                raw.push_str(&format!("X-{name}: {value}\r\n"));
+            }
+            raw.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+
+            let reader = ChunkedReader {
+                data: raw.clone().into_bytes(),
+                pos: 0,
+                sizes: vec![chunk; raw.len()],
+            };
+            let req = get_req_info(reader).unwrap().unwrap();
+            prop_assert_eq!(&req.method, method);
+            prop_assert_eq!(&req.path, &path);
+            prop_assert_eq!(req.body.as_deref().unwrap_or_default(), body.as_str());
+            for (name, _) in &headers {
+                let name = format!("X-{}", name);
+                prop_assert!(req.header(&name).is_some());
+            }
+        }
+
+        // two Content-Length headers are always rejected
+        #[test]
+        fn prop_duplicate_content_length(a in 0..100usize, b in 0..100usize) {
+            let raw = format!(
+                "POST / HTTP/1.1\r\nContent-Length: {a}\r\nContent-Length: {b}\r\n\r\n{}",
+                "x".repeat(a.max(b))
+            );
+            prop_assert!(matches!(
+                parse_request(raw.as_bytes()),
+                Err(HTTPErrorMessage::DuplicateHeader(_))
+            ));
+        }
+
+        // control characters in header value are rejected
+        #[test]
+        fn prop_header_injection(
+            prefix in "[a-z]{0,10}",
+            bad in prop::sample::select(vec![b'\0', b'\n', b'\r', 0x7f, 0x01]),
+            suffix in "[a-z]{0,10}",
+        ) {
+            let mut raw = b"GET / HTTP/1.1\r\nX-Name: ".to_vec();
+            raw.extend_from_slice(prefix.as_bytes());
+            raw.push(bad);
+            raw.extend_from_slice(suffix.as_bytes());
+            raw.extend_from_slice(b"\r\n\r\n");
+            let result = parse_request(&raw);
+            prop_assert!(
+                matches!(
+                    result,
+                    Err(HTTPErrorMessage::InvalidHeaderCharacter) | Err(HTTPErrorMessage::InvalidHeader)
+                ),
+                "{:?}",
+                result.map(|r| r.headers)
+            );
+        }
+    }
 }
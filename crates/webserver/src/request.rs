@@ -7,6 +7,8 @@ pub struct Request {
     pub headers: HashMap<String, String>,
     pub query: Option<String>,
     pub body: Option<String>,
+    // path parameters such as `:user_id` filled by router
+    pub params: HashMap<String, String>,
 }
 
 impl Request {
@@ -23,6 +25,20 @@ impl Request {
             headers,
             query,
             body,
+            params: HashMap::new(),
         }
     }
+
+    // header() finds header value by case-insensitive name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    // param() returns path parameter captured by router
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(|v| v.as_str())
+    }
 }
@@ -3,32 +3,50 @@ use crate::errors::HTTPErrorMessage;
 pub struct Response {
     pub status_code: u16,
     pub headers: Vec<(String, String)>,
-    pub body: String,
+    pub body: Vec<u8>,
 }
 
 impl Response {
-    #[allow(clippy::inherent_to_string)]
-    pub fn to_string(&self) -> String {
-        let headers = self
+    // to_bytes() serializes status line, headers and body
+    // Content-Length is added unless handler already set it (e.g. HEAD request)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut headers = self
             .headers
             .iter()
-            .map(|(k, v)| format!("{}: {}", k, v))
-            .collect::<Vec<_>>()
-            .join("\r\n");
-        format!(
-            "HTTP/1.1 {} {}\r\n{}\r\n\r\n{}\r\n",
+            .map(|(k, v)| format!("{}: {}\r\n", k, v))
+            .collect::<String>();
+        if self.has_body_status() && self.header("Content-Length").is_none() {
+            headers.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+
+        let mut bytes = format!(
+            "HTTP/1.1 {} {}\r\n{}\r\n",
             self.status_code,
             get_status_message(self.status_code),
             headers,
-            self.body
         )
+        .into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    // 1xx, 204 and 304 must not have body
+    fn has_body_status(&self) -> bool {
+        !matches!(self.status_code, 100..=199 | 204 | 304)
     }
 
     pub fn html(status_code: u16, body: &str) -> Self {
         Self {
             status_code,
             headers: vec![("Content-Type".to_string(), "text/html".to_string())],
-            body: body.to_string(),
+            body: body.as_bytes().to_vec(),
         }
     }
 
@@ -36,7 +54,7 @@ impl Response {
         Self {
             status_code,
             headers: vec![("Content-Type".to_string(), "application/json".to_string())],
-            body: body.to_string(),
+            body: body.as_bytes().to_vec(),
         }
     }
 
@@ -47,19 +65,42 @@ impl Response {
         )
     }
 
-    // WIP: adjustment
+    // error_json() returns `{"error": message}` as same as other frameworks
     pub fn error_json(status_code: u16, message: &str) -> Self {
-        Self::json(status_code, message)
+        Self::json(
+            status_code,
+            &serde_json::json!({ "error": message }).to_string(),
+        )
+    }
+
+    pub fn no_content() -> Self {
+        Self {
+            status_code: 204,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
     }
 }
 
 fn get_status_message(status_code: u16) -> &'static str {
     match status_code {
+        101 => "Switching Protocols",
         200 => "OK",
+        204 => "No Content",
+        206 => "Partial Content",
+        304 => "Not Modified",
         400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
         404 => "Not Found",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
         415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        426 => "Upgrade Required",
+        431 => "Request Header Fields Too Large",
         500 => "Internal Server Error",
+        501 => "Not Implemented",
         _ => "Unknown",
     }
 }
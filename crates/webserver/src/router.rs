@@ -1,47 +1,145 @@
+use crate::errors::HTTPErrorMessage;
 use crate::middleware;
 use crate::request;
 use crate::responser::Response;
+use crate::static_files::StaticFiles;
+use crate::websocket::{handshake, WebSocket, WsError};
+use log::{debug, error};
 use std::collections::HashMap;
+use std::io::{Read, Write};
 
-type Handler = fn(&request::Request) -> anyhow::Result<Response>;
+// handler receives application state shared by all routes
+pub type Handler<S> = Box<dyn Fn(&request::Request, &S) -> anyhow::Result<Response>>;
 
-pub struct Router {
-    route_map: HashMap<(String, String), Handler>,
+// websocket handler receives connection after handshake
+// connection is closed when handler returns
+pub type WsHandler<S> = Box<dyn Fn(&request::Request, &S, &mut WebSocket) -> anyhow::Result<()>>;
+
+// route with parameters like `/users/:user_id`
+struct PatternRoute<H> {
+    method: String,
+    segments: Vec<String>,
+    handler: H,
+}
+
+impl<H> PatternRoute<H> {
+    fn new(method: &str, path: &str, handler: H) -> Self {
+        Self {
+            method: method.to_string(),
+            segments: path
+                .trim_matches('/')
+                .split('/')
+                .map(|s| s.to_string())
+                .collect(),
+            handler,
+        }
+    }
+
+    // matches() returns captured parameters when method and path match
+    fn matches(&self, method: &str, path: &str) -> Option<HashMap<String, String>> {
+        if self.method != method {
+            return None;
+        }
+        let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        if parts.len() != self.segments.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (segment, part) in self.segments.iter().zip(parts) {
+            match segment.strip_prefix(':') {
+                Some(name) if !part.is_empty() => {
+                    params.insert(name.to_string(), part.to_string());
+                }
+                Some(_) => return None,
+                None if segment != part => return None,
+                None => {}
+            }
+        }
+        Some(params)
+    }
+}
+
+pub struct Router<S = ()> {
+    route_map: HashMap<(String, String), Handler<S>>,
+    pattern_routes: Vec<PatternRoute<Handler<S>>>,
+    ws_routes: Vec<PatternRoute<WsHandler<S>>>,
     middlewares: Vec<Box<dyn middleware::Middleware>>,
+    static_files: Vec<StaticFiles>,
+    state: S,
 }
 
-impl Router {
+impl Router<()> {
     pub fn new() -> Self {
+        Self::with_state(())
+    }
+}
+
+impl<S> Router<S> {
+    pub fn with_state(state: S) -> Self {
         Router {
             route_map: HashMap::new(),
+            pattern_routes: Vec::new(),
+            ws_routes: Vec::new(),
             middlewares: Vec::new(),
+            static_files: Vec::new(),
+            state,
         }
     }
 
-    pub fn get(&mut self, path: &str, handler: Handler) {
-        self.route_map
-            .insert(("GET".to_string(), path.to_string()), handler);
+    pub fn state(&self) -> &S {
+        &self.state
     }
-    // pub fn get<F>(&mut self, path: &str, handler: F)
-    // where
-    //     F: Fn(&request::Request, &mut TcpStream) -> anyhow::Result<()> + 'static,
-    // {
-    //     self.routes.insert(path.to_string(), Box::new(handler));
-    // }
 
-    pub fn post(&mut self, path: &str, handler: Handler) {
-        self.route_map
-            .insert(("POST".to_string(), path.to_string()), handler);
+    pub fn get<F>(&mut self, path: &str, handler: F)
+    where
+        F: Fn(&request::Request, &S) -> anyhow::Result<Response> + 'static,
+    {
+        self.add_route("GET", path, handler);
     }
 
-    pub fn put(&mut self, path: &str, handler: Handler) {
-        self.route_map
-            .insert(("PUT".to_string(), path.to_string()), handler);
+    pub fn post<F>(&mut self, path: &str, handler: F)
+    where
+        F: Fn(&request::Request, &S) -> anyhow::Result<Response> + 'static,
+    {
+        self.add_route("POST", path, handler);
     }
 
-    pub fn delete(&mut self, path: &str, handler: Handler) {
+    pub fn put<F>(&mut self, path: &str, handler: F)
+    where
+        F: Fn(&request::Request, &S) -> anyhow::Result<Response> + 'static,
+    {
+        self.add_route("PUT", path, handler);
+    }
+
+    pub fn delete<F>(&mut self, path: &str, handler: F)
+    where
+        F: Fn(&request::Request, &S) -> anyhow::Result<Response> + 'static,
+    {
+        self.add_route("DELETE", path, handler);
+    }
+
+    fn add_route<F>(&mut self, method: &str, path: &str, handler: F)
+    where
+        F: Fn(&request::Request, &S) -> anyhow::Result<Response> + 'static,
+    {
+        if path.split('/').any(|segment| segment.starts_with(':')) {
+            self.pattern_routes
+                .push(PatternRoute::new(method, path, Box::new(handler)));
+            return;
+        }
         self.route_map
-            .insert(("DELETE".to_string(), path.to_string()), handler);
+            .insert((method.to_string(), path.to_string()), Box::new(handler));
+    }
+
+    // websocket route accepts `GET` with `Upgrade: websocket`
+    // path parameters are available as same as other routes
+    pub fn websocket<F>(&mut self, path: &str, handler: F)
+    where
+        F: Fn(&request::Request, &S, &mut WebSocket) -> anyhow::Result<()> + 'static,
+    {
+        self.ws_routes
+            .push(PatternRoute::new("GET", path, Box::new(handler)));
     }
 
     // add middleware
@@ -49,9 +147,39 @@ impl Router {
         self.middlewares.push(Box::new(middleware));
     }
 
+    // add static directory
+    pub fn mount_static(&mut self, static_files: StaticFiles) {
+        self.static_files.push(static_files);
+    }
+
     // route finds handler specified by method and path
-    pub fn route(&self, method: &str, path: &str) -> Option<&Handler> {
-        self.route_map.get(&(method.to_string(), path.to_string()))
+    // exact path is preferred, then path parameters are captured from pattern routes
+    pub fn route(
+        &self,
+        method: &str,
+        path: &str,
+    ) -> Option<(&Handler<S>, HashMap<String, String>)> {
+        if let Some(h) = self.route_map.get(&(method.to_string(), path.to_string())) {
+            return Some((h, HashMap::new()));
+        }
+        self.pattern_routes.iter().find_map(|route| {
+            route
+                .matches(method, path)
+                .map(|params| (&route.handler, params))
+        })
+    }
+
+    pub fn route_websocket(&self, path: &str) -> Option<(&WsHandler<S>, HashMap<String, String>)> {
+        self.ws_routes.iter().find_map(|route| {
+            route
+                .matches("GET", path)
+                .map(|params| (&route.handler, params))
+        })
+    }
+
+    // serve_static returns response from mounted static directory if path matches
+    pub fn serve_static(&self, req: &request::Request) -> Option<Response> {
+        self.static_files.iter().find_map(|s| s.serve(req))
     }
 
     pub fn run_middleware(&self, req: &request::Request) -> anyhow::Result<()> {
@@ -61,6 +189,97 @@ impl Router {
         }
         Ok(())
     }
+
+    // handle runs middleware and handler, then returns response
+    // this is shared by both blocking server and event loop
+    pub fn handle(&self, mut req: request::Request) -> Response {
+        // middleware
+        if let Err(e) = self.run_middleware(&req) {
+            let custom_error = e
+                .downcast_ref::<HTTPErrorMessage>()
+                .unwrap_or(&HTTPErrorMessage::InvalidRequestFormat);
+            return Response::error_html(custom_error); // Stop processing further after the error
+        }
+
+        // handler
+        match self.route(&req.method, &req.path) {
+            Some((h, params)) => {
+                req.params = params;
+                h(&req, &self.state).unwrap_or_else(|e| error_response(&e))
+            }
+            None => self
+                .serve_static(&req)
+                .unwrap_or_else(|| Response::error_html(&HTTPErrorMessage::NotFound)),
+        }
+    }
+
+    // handle_websocket runs middleware and handshake, then hands stream over to handler
+    // only blocking server supports this because handler owns the stream until it returns
+    pub fn handle_websocket<T: Read + Write>(
+        &self,
+        mut req: request::Request,
+        stream: &mut T,
+    ) -> anyhow::Result<()> {
+        let response = match self.prepare_websocket(&mut req) {
+            Ok(response) => response,
+            Err(e) => {
+                stream.write_all(&handshake::error_response(&e).to_bytes())?;
+                stream.flush()?;
+                return Ok(());
+            }
+        };
+        stream.write_all(&response.to_bytes())?;
+        stream.flush()?;
+
+        let Some((h, _)) = self.route_websocket(&req.path) else {
+            return Ok(());
+        };
+        let mut ws = WebSocket::new(stream);
+        let (code, reason) = match h(&req, &self.state, &mut ws) {
+            Ok(()) => (1000, String::new()),
+            Err(e) => match e.downcast_ref::<WsError>() {
+                Some(ws_error) => {
+                    debug!("websocket error: {ws_error}");
+                    (ws_error.close_code(), ws_error.to_string())
+                }
+                None => {
+                    error!("websocket handler error: {e:?}");
+                    (1011, String::new())
+                }
+            },
+        };
+        // connection may be broken already
+        if let Err(e) = ws.close(code, &reason) {
+            debug!("{e}");
+        }
+        Ok(())
+    }
+
+    // prepare_websocket() returns `101 Switching Protocols` when route and handshake are valid
+    fn prepare_websocket(&self, req: &mut request::Request) -> Result<Response, HTTPErrorMessage> {
+        if let Err(e) = self.run_middleware(req) {
+            return Err(e
+                .downcast::<HTTPErrorMessage>()
+                .unwrap_or(HTTPErrorMessage::InvalidRequestFormat));
+        }
+        let Some((_, params)) = self.route_websocket(&req.path) else {
+            return Err(HTTPErrorMessage::NotFound);
+        };
+        req.params = params;
+        handshake::accept(req)
+    }
+}
+
+// error_response converts handler error into response
+// HTTPErrorMessage keeps its own status, anything else is 500
+fn error_response(e: &anyhow::Error) -> Response {
+    match e.downcast_ref::<HTTPErrorMessage>() {
+        Some(custom_error) => Response::error_html(custom_error),
+        None => {
+            error!("handler error: {e:?}");
+            Response::error_html(&HTTPErrorMessage::InternalServerError)
+        }
+    }
 }
 
 impl Default for Router {
@@ -68,3 +287,93 @@ impl Default for Router {
         Self::new()
     }
 }
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn request(method: &str, path: &str) -> request::Request {
+        request::Request::new(
+            method.to_string(),
+            path.to_string(),
+            HashMap::new(),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_handler_with_state() {
+        let mut router = Router::with_state(String::from("state"));
+        router.get("/", |_req, state: &String| Ok(Response::html(200, state)));
+
+        let res = router.handle(request("GET", "/"));
+        assert_eq!(res.status_code, 200);
+        assert_eq!(res.body, b"state");
+    }
+
+    #[test]
+    fn test_handler_captures_closure() {
+        let counter = std::rc::Rc::new(Cell::new(0));
+        let captured = counter.clone();
+        let mut router = Router::new();
+        router.post("/count", move |_req, _state| {
+            captured.set(captured.get() + 1);
+            Ok(Response::html(200, "ok"))
+        });
+
+        router.handle(request("POST", "/count"));
+        router.handle(request("POST", "/count"));
+        assert_eq!(counter.get(), 2);
+    }
+
+    #[test]
+    fn test_handler_error() {
+        let mut router = Router::new();
+        router.get("/fail", |_req, _state| Err(anyhow::anyhow!("db is down")));
+        router.get("/missing", |_req, _state| {
+            Err(HTTPErrorMessage::NotFound.into())
+        });
+
+        assert_eq!(router.handle(request("GET", "/fail")).status_code, 500);
+        assert_eq!(router.handle(request("GET", "/missing")).status_code, 404);
+        assert_eq!(router.handle(request("GET", "/none")).status_code, 404);
+    }
+
+    #[test]
+    fn test_path_parameters() {
+        let mut router = Router::new();
+        router.get("/users/list", |_req, _state| {
+            Ok(Response::html(200, "list"))
+        });
+        router.get("/users/:user_id/todos/:todo_id", |req, _state| {
+            let body = format!(
+                "{}-{}",
+                req.param("user_id").unwrap(),
+                req.param("todo_id").unwrap()
+            );
+            Ok(Response::html(200, &body))
+        });
+        router.get("/users/:user_id", |req, _state| {
+            Ok(Response::html(200, req.param("user_id").unwrap()))
+        });
+
+        assert_eq!(router.handle(request("GET", "/users/list")).body, b"list");
+        assert_eq!(router.handle(request("GET", "/users/10")).body, b"10");
+        assert_eq!(
+            router.handle(request("GET", "/users/1/todos/2")).body,
+            b"1-2"
+        );
+        assert_eq!(router.handle(request("GET", "/users/")).status_code, 404);
+        assert_eq!(router.handle(request("PUT", "/users/1")).status_code, 404);
+        assert_eq!(
+            router.handle(request("GET", "/users/1/todos")).status_code,
+            404
+        );
+    }
+}
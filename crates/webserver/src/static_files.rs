@@ -0,0 +1,587 @@
+use crate::errors::HTTPErrorMessage;
+use crate::request;
+use crate::responser::Response;
+use log::{debug, error};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use std::fs::{self, File, Metadata};
+use std::io::{self, prelude::*, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// characters to be escaped in links of directory index page
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+const INDEX_FILE: &str = "index.html";
+
+// StaticFiles serves files under `root` directory for requests under `mount_path`
+//
+// # Examples
+//
+// ```
+// router.mount_static(StaticFiles::new("/static", "./public").with_directory_index(true));
+// ```
+pub struct StaticFiles {
+    mount_path: String,
+    root: PathBuf,
+    directory_index: bool,
+}
+
+// ByteRange is the result of `Range` header evaluation
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    Partial(u64, u64), // inclusive start and end
+    Unsatisfiable,
+}
+
+impl StaticFiles {
+    pub fn new(mount_path: &str, root: impl Into<PathBuf>) -> Self {
+        Self {
+            mount_path: mount_path.trim_end_matches('/').to_string(),
+            root: root.into(),
+            directory_index: false,
+        }
+    }
+
+    // with_directory_index() enables listing page for directories without index.html
+    pub fn with_directory_index(mut self, enabled: bool) -> Self {
+        self.directory_index = enabled;
+        self
+    }
+
+    // serve() returns None when request is not for this mount point
+    pub fn serve(&self, req: &request::Request) -> Option<Response> {
+        if req.method != "GET" && req.method != "HEAD" {
+            return None;
+        }
+        let rel_path = self.strip_mount_path(&req.path)?;
+
+        let response = match self.resolve(rel_path) {
+            Ok(file_path) => self.respond(req, rel_path, &file_path),
+            Err(e) => Response::error_html(&e),
+        };
+        Some(response)
+    }
+
+    fn strip_mount_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let rest = path.strip_prefix(self.mount_path.as_str())?;
+        if rest.is_empty() || rest.starts_with('/') {
+            Some(rest)
+        } else {
+            None
+        }
+    }
+
+    // resolve() maps url path to file path inside root directory
+    // path traversal like `..`, `%2e%2e` or symbolic link to outside of root is rejected
+    fn resolve(&self, rel_path: &str) -> Result<PathBuf, HTTPErrorMessage> {
+        let decoded = percent_decode_str(rel_path)
+            .decode_utf8()
+            .map_err(|_| HTTPErrorMessage::BadRequest)?;
+        if decoded.contains('\0') {
+            return Err(HTTPErrorMessage::BadRequest);
+        }
+
+        let mut file_path = self.root.clone();
+        for segment in decoded.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => return Err(HTTPErrorMessage::Forbidden),
+                s if s.contains('\\') || Path::new(s).is_absolute() => {
+                    return Err(HTTPErrorMessage::Forbidden)
+                }
+                s => file_path.push(s),
+            }
+        }
+
+        let root = self.root.canonicalize().map_err(|e| {
+            error!("static root {:?} is not available: {}", self.root, e);
+            HTTPErrorMessage::NotFound
+        })?;
+        let file_path = file_path
+            .canonicalize()
+            .map_err(|_| HTTPErrorMessage::NotFound)?;
+        if !file_path.starts_with(&root) {
+            debug!("{:?} is outside of {:?}", file_path, root);
+            return Err(HTTPErrorMessage::Forbidden);
+        }
+        Ok(file_path)
+    }
+
+    fn respond(&self, req: &request::Request, rel_path: &str, file_path: &Path) -> Response {
+        if file_path.is_dir() {
+            let index_path = file_path.join(INDEX_FILE);
+            if index_path.is_file() {
+                return respond_file(req, &index_path);
+            }
+            if self.directory_index {
+                return match render_directory_index(&self.mount_path, rel_path, file_path) {
+                    Ok(html) => Response::html(200, &html),
+                    Err(e) => {
+                        error!("fail to read directory {:?}: {}", file_path, e);
+                        Response::error_html(&HTTPErrorMessage::InternalServerError)
+                    }
+                };
+            }
+            return Response::error_html(&HTTPErrorMessage::NotFound);
+        }
+        respond_file(req, file_path)
+    }
+}
+
+fn respond_file(req: &request::Request, file_path: &Path) -> Response {
+    let metadata = match fs::metadata(file_path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Response::error_html(&HTTPErrorMessage::NotFound),
+    };
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = get_etag(&metadata);
+
+    let mut headers = vec![
+        (
+            "Content-Type".to_string(),
+            get_mime_type(file_path).to_string(),
+        ),
+        ("Accept-Ranges".to_string(), "bytes".to_string()),
+        ("ETag".to_string(), etag.clone()),
+    ];
+    if let Some(modified) = modified {
+        headers.push((
+            "Last-Modified".to_string(),
+            httpdate::fmt_http_date(modified),
+        ));
+    }
+
+    // conditional request
+    if is_not_modified(req, &etag, modified) {
+        return Response {
+            status_code: 304,
+            headers,
+            body: Vec::new(),
+        };
+    }
+
+    // range request
+    let range = match req.header("Range") {
+        Some(range) if req.method == "GET" => parse_range(range, len),
+        _ => ByteRange::Full,
+    };
+    let (status_code, start, end) = match range {
+        ByteRange::Full => (200, 0, len.saturating_sub(1)),
+        ByteRange::Partial(start, end) => {
+            headers.push((
+                "Content-Range".to_string(),
+                format!("bytes {}-{}/{}", start, end, len),
+            ));
+            (206, start, end)
+        }
+        ByteRange::Unsatisfiable => {
+            let mut response = Response::error_html(&HTTPErrorMessage::RangeNotSatisfiable);
+            response
+                .headers
+                .push(("Content-Range".to_string(), format!("bytes */{}", len)));
+            return response;
+        }
+    };
+    let content_length = if len == 0 { 0 } else { end - start + 1 };
+
+    // HEAD returns only headers
+    if req.method == "HEAD" {
+        headers.push(("Content-Length".to_string(), content_length.to_string()));
+        return Response {
+            status_code,
+            headers,
+            body: Vec::new(),
+        };
+    }
+
+    match read_file(file_path, start, content_length) {
+        Ok(body) => Response {
+            status_code,
+            headers,
+            body,
+        },
+        Err(e) => {
+            error!("fail to read {:?}: {}", file_path, e);
+            Response::error_html(&HTTPErrorMessage::InternalServerError)
+        }
+    }
+}
+
+fn read_file(file_path: &Path, start: u64, length: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(file_path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut body = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut body)?;
+    Ok(body)
+}
+
+// ETag is built from size and modified time
+fn get_etag(metadata: &Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", metadata.len(), modified)
+}
+
+// If-None-Match takes precedence over If-Modified-Since
+fn is_not_modified(req: &request::Request, etag: &str, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = req.header("If-None-Match") {
+        return if_none_match.split(',').map(|s| s.trim()).any(|tag| {
+            // weak comparison
+            tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/")
+        });
+    }
+    if let (Some(if_modified_since), Some(modified)) = (req.header("If-Modified-Since"), modified) {
+        if let Ok(since) = httpdate::parse_http_date(if_modified_since) {
+            // Last-Modified has only seconds precision
+            let modified_secs = modified
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let since_secs = since
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            return modified_secs <= since_secs;
+        }
+    }
+    false
+}
+
+// parse_range() supports single range only
+// - `bytes=0-99`, `bytes=100-`, `bytes=-100`
+// multiple ranges or invalid format is ignored and whole content is returned
+fn parse_range(header: &str, len: u64) -> ByteRange {
+    let spec = match header.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return ByteRange::Full,
+    };
+    let (start, end) = match spec.split_once('-') {
+        Some(pair) => pair,
+        None => return ByteRange::Full,
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // suffix range
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(len.saturating_sub(suffix), len - 1),
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let start = match start.parse::<u64>() {
+        Ok(start) => start,
+        Err(_) => return ByteRange::Full,
+    };
+    let end = if end.is_empty() {
+        len.saturating_sub(1)
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => end.min(len.saturating_sub(1)),
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+fn get_mime_type(file_path: &Path) -> &'static str {
+    let ext = file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        _ => "application/octet-stream",
+    }
+}
+
+fn render_directory_index(mount_path: &str, rel_path: &str, dir: &Path) -> io::Result<String> {
+    let mut entries = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            (name, is_dir)
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let base = format!("{}/{}", mount_path, rel_path.trim_matches('/'));
+    let base = base.trim_end_matches('/');
+    let title = escape_html(&format!("{}/", base));
+
+    let items = entries
+        .iter()
+        .map(|(name, is_dir)| {
+            let suffix = if *is_dir { "/" } else { "" };
+            format!(
+                "<li><a href=\"{}/{}{}\">{}{}</a></li>",
+                base,
+                utf8_percent_encode(name, PATH_SEGMENT),
+                suffix,
+                escape_html(name),
+                suffix
+            )
+        })
+        .collect::<String>();
+
+    Ok(format!(
+        "<html><head><title>Index of {0}</title></head><body><h1>Index of {0}</h1><ul>{1}</ul></body></html>",
+        title, items
+    ))
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn new_request(method: &str, path: &str, headers: &[(&str, &str)]) -> request::Request {
+        let headers = headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+        request::Request::new(method.to_string(), path.to_string(), headers, None, None)
+    }
+
+    // create_root() creates test directory
+    // - index.html
+    // - hello.txt
+    // - sub/data.json
+    fn create_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("webserver-static-{}", name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("index.html"), "<h1>index</h1>").unwrap();
+        fs::write(root.join("hello.txt"), "0123456789").unwrap();
+        fs::write(root.join("sub/data.json"), "{}").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-4", 10), ByteRange::Partial(0, 4));
+        assert_eq!(parse_range("bytes=5-", 10), ByteRange::Partial(5, 9));
+        assert_eq!(parse_range("bytes=-3", 10), ByteRange::Partial(7, 9));
+        assert_eq!(parse_range("bytes=-30", 10), ByteRange::Partial(0, 9));
+        assert_eq!(parse_range("bytes=8-100", 10), ByteRange::Partial(8, 9));
+        assert_eq!(parse_range("bytes=10-", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,3-4", 10), ByteRange::Full);
+        assert_eq!(parse_range("bytes=5-2", 10), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 10), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_get_mime_type() {
+        assert_eq!(
+            get_mime_type(Path::new("a/index.HTML")),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(get_mime_type(Path::new("logo.png")), "image/png");
+        assert_eq!(
+            get_mime_type(Path::new("no_extension")),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn test_serve_file_and_index() {
+        let root = create_root("serve");
+        let static_files = StaticFiles::new("/static", &root);
+
+        let res = static_files
+            .serve(&new_request("GET", "/static/hello.txt", &[]))
+            .unwrap();
+        assert_eq!(res.status_code, 200);
+        assert_eq!(res.body, b"0123456789");
+        assert_eq!(
+            res.header("Content-Type"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert!(res.header("ETag").is_some());
+        assert!(res.header("Last-Modified").is_some());
+
+        // index.html
+        let res = static_files
+            .serve(&new_request("GET", "/static/", &[]))
+            .unwrap();
+        assert_eq!(res.body, b"<h1>index</h1>");
+
+        // directory without index.html
+        let res = static_files
+            .serve(&new_request("GET", "/static/sub", &[]))
+            .unwrap();
+        assert_eq!(res.status_code, 404);
+        let static_files = static_files.with_directory_index(true);
+        let res = static_files
+            .serve(&new_request("GET", "/static/sub", &[]))
+            .unwrap();
+        assert_eq!(res.status_code, 200);
+        assert!(String::from_utf8(res.body)
+            .unwrap()
+            .contains("href=\"/static/sub/data.json\""));
+
+        // other mount point or method
+        assert!(static_files
+            .serve(&new_request("GET", "/staticfoo", &[]))
+            .is_none());
+        assert!(static_files
+            .serve(&new_request("POST", "/static/hello.txt", &[]))
+            .is_none());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_path_traversal() {
+        let root = create_root("traversal");
+        let static_files = StaticFiles::new("/static", root.join("sub"));
+
+        for path in [
+            "/static/../hello.txt",
+            "/static/%2e%2e/hello.txt",
+            "/static/sub/..%2f..%2fhello.txt",
+            "/static/..\\hello.txt",
+        ] {
+            let res = static_files.serve(&new_request("GET", path, &[])).unwrap();
+            assert_eq!(res.status_code, 403, "{}", path);
+        }
+        let res = static_files
+            .serve(&new_request("GET", "/static/%00", &[]))
+            .unwrap();
+        assert_eq!(res.status_code, 400);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_conditional_and_range() {
+        let root = create_root("conditional");
+        let static_files = StaticFiles::new("/static", &root);
+
+        let res = static_files
+            .serve(&new_request("GET", "/static/hello.txt", &[]))
+            .unwrap();
+        let etag = res.header("ETag").unwrap().to_string();
+        let last_modified = res.header("Last-Modified").unwrap().to_string();
+
+        // If-None-Match
+        let res = static_files
+            .serve(&new_request(
+                "GET",
+                "/static/hello.txt",
+                &[("If-None-Match", &etag)],
+            ))
+            .unwrap();
+        assert_eq!(res.status_code, 304);
+        assert!(res.body.is_empty());
+        let res = static_files
+            .serve(&new_request(
+                "GET",
+                "/static/hello.txt",
+                &[("if-none-match", "\"other\"")],
+            ))
+            .unwrap();
+        assert_eq!(res.status_code, 200);
+
+        // If-Modified-Since
+        let res = static_files
+            .serve(&new_request(
+                "GET",
+                "/static/hello.txt",
+                &[("If-Modified-Since", &last_modified)],
+            ))
+            .unwrap();
+        assert_eq!(res.status_code, 304);
+
+        // Range
+        let res = static_files
+            .serve(&new_request(
+                "GET",
+                "/static/hello.txt",
+                &[("Range", "bytes=2-5")],
+            ))
+            .unwrap();
+        assert_eq!(res.status_code, 206);
+        assert_eq!(res.body, b"2345");
+        assert_eq!(res.header("Content-Range"), Some("bytes 2-5/10"));
+
+        let res = static_files
+            .serve(&new_request(
+                "GET",
+                "/static/hello.txt",
+                &[("Range", "bytes=20-")],
+            ))
+            .unwrap();
+        assert_eq!(res.status_code, 416);
+        assert_eq!(res.header("Content-Range"), Some("bytes */10"));
+
+        // HEAD
+        let res = static_files
+            .serve(&new_request("HEAD", "/static/hello.txt", &[]))
+            .unwrap();
+        assert_eq!(res.status_code, 200);
+        assert!(res.body.is_empty());
+        assert_eq!(res.header("Content-Length"), Some("10"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
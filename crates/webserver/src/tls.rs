@@ -0,0 +1,40 @@
+use anyhow::Context;
+use rustls::crypto::ring;
+use rustls::ServerConfig;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+// only HTTP/1.1 is implemented
+pub const ALPN_HTTP11: &[u8] = b"http/1.1";
+
+// load_server_config() builds rustls config from certificate chain and private key in PEM
+pub fn load_server_config(
+    cert_path: impl AsRef<Path>,
+    key_path: impl AsRef<Path>,
+) -> anyhow::Result<Arc<ServerConfig>> {
+    let cert_path = cert_path.as_ref();
+    let key_path = key_path.as_ref();
+
+    let mut reader = BufReader::new(
+        File::open(cert_path).with_context(|| format!("fail to open {}", cert_path.display()))?,
+    );
+    let certs = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificate is found in {}", cert_path.display());
+    }
+
+    let mut reader = BufReader::new(
+        File::open(key_path).with_context(|| format!("fail to open {}", key_path.display()))?,
+    );
+    let key = rustls_pemfile::private_key(&mut reader)?
+        .with_context(|| format!("no private key is found in {}", key_path.display()))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![ALPN_HTTP11.to_vec()];
+    Ok(Arc::new(config))
+}
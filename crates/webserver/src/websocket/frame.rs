@@ -0,0 +1,252 @@
+use crate::websocket::WsError;
+
+// payload bigger than this is rejected with close code 1009
+pub const MAX_PAYLOAD_LEN: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    fn from_u8(value: u8) -> Result<Self, WsError> {
+        match value {
+            0x0 => Ok(OpCode::Continuation),
+            0x1 => Ok(OpCode::Text),
+            0x2 => Ok(OpCode::Binary),
+            0x8 => Ok(OpCode::Close),
+            0x9 => Ok(OpCode::Ping),
+            0xA => Ok(OpCode::Pong),
+            _ => Err(WsError::Protocol("reserved opcode")),
+        }
+    }
+
+    fn as_u8(&self) -> u8 {
+        match self {
+            OpCode::Continuation => 0x0,
+            OpCode::Text => 0x1,
+            OpCode::Binary => 0x2,
+            OpCode::Close => 0x8,
+            OpCode::Ping => 0x9,
+            OpCode::Pong => 0xA,
+        }
+    }
+
+    pub fn is_control(&self) -> bool {
+        matches!(self, OpCode::Close | OpCode::Ping | OpCode::Pong)
+    }
+}
+
+// Frame is a single RFC 6455 frame, payload is already unmasked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: OpCode,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(fin: bool, opcode: OpCode, payload: Vec<u8>) -> Self {
+        Self {
+            fin,
+            opcode,
+            payload,
+        }
+    }
+
+    // parse() returns frame and consumed length, or None while frame is still partial
+    // client frames must be masked, server frames must not
+    pub fn parse(buf: &[u8], expect_masked: bool) -> Result<Option<(Frame, usize)>, WsError> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let fin = buf[0] & 0x80 != 0;
+        if buf[0] & 0x70 != 0 {
+            return Err(WsError::Protocol("reserved bits must be 0"));
+        }
+        let opcode = OpCode::from_u8(buf[0] & 0x0F)?;
+        let masked = buf[1] & 0x80 != 0;
+        if masked != expect_masked {
+            return Err(WsError::Protocol("invalid masking"));
+        }
+
+        // payload length
+        let mut pos = 2;
+        let len = match buf[1] & 0x7F {
+            126 => {
+                let Some(bytes) = buf.get(pos..pos + 2) else {
+                    return Ok(None);
+                };
+                pos += 2;
+                u16::from_be_bytes([bytes[0], bytes[1]]) as u64
+            }
+            127 => {
+                let Some(bytes) = buf.get(pos..pos + 8) else {
+                    return Ok(None);
+                };
+                pos += 8;
+                let len = u64::from_be_bytes(bytes.try_into().unwrap());
+                if len >> 63 != 0 {
+                    return Err(WsError::Protocol(
+                        "most significant bit of length must be 0",
+                    ));
+                }
+                len
+            }
+            len => len as u64,
+        };
+        if opcode.is_control() && (!fin || len > 125) {
+            return Err(WsError::Protocol("invalid control frame"));
+        }
+        if len > MAX_PAYLOAD_LEN as u64 {
+            return Err(WsError::MessageTooBig);
+        }
+        let len = len as usize;
+
+        let mask = if masked {
+            let Some(bytes) = buf.get(pos..pos + 4) else {
+                return Ok(None);
+            };
+            pos += 4;
+            Some([bytes[0], bytes[1], bytes[2], bytes[3]])
+        } else {
+            None
+        };
+
+        let Some(payload) = buf.get(pos..pos + len) else {
+            return Ok(None);
+        };
+        let mut payload = payload.to_vec();
+        if let Some(mask) = mask {
+            apply_mask(&mut payload, mask);
+        }
+        Ok(Some((Frame::new(fin, opcode, payload), pos + len)))
+    }
+
+    // to_bytes() serializes frame, client must give mask
+    pub fn to_bytes(&self, mask: Option<[u8; 4]>) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.payload.len() + 14);
+        bytes.push(if self.fin { 0x80 } else { 0 } | self.opcode.as_u8());
+
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        let len = self.payload.len();
+        if len < 126 {
+            bytes.push(mask_bit | len as u8);
+        } else if len <= u16::MAX as usize {
+            bytes.push(mask_bit | 126);
+            bytes.extend_from_slice(&(len as u16).to_be_bytes());
+        } else {
+            bytes.push(mask_bit | 127);
+            bytes.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+
+        match mask {
+            Some(mask) => {
+                bytes.extend_from_slice(&mask);
+                let start = bytes.len();
+                bytes.extend_from_slice(&self.payload);
+                apply_mask(&mut bytes[start..], mask);
+            }
+            None => bytes.extend_from_slice(&self.payload),
+        }
+        bytes
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, b) in payload.iter_mut().enumerate() {
+        *b ^= mask[i % 4];
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc_examples() {
+        // examples in RFC 6455 section 5.7
+        let unmasked = [0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f];
+        let (frame, len) = Frame::parse(&unmasked, false).unwrap().unwrap();
+        assert_eq!(len, unmasked.len());
+        assert_eq!(frame, Frame::new(true, OpCode::Text, b"Hello".to_vec()));
+        assert_eq!(frame.to_bytes(None), unmasked);
+
+        let masked = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let (frame, _) = Frame::parse(&masked, true).unwrap().unwrap();
+        assert_eq!(frame.payload, b"Hello");
+        assert_eq!(frame.to_bytes(Some([0x37, 0xfa, 0x21, 0x3d])), masked);
+
+        // fragmented text
+        let first = [0x01, 0x03, 0x48, 0x65, 0x6c];
+        let second = [0x80, 0x02, 0x6c, 0x6f];
+        let (frame, _) = Frame::parse(&first, false).unwrap().unwrap();
+        assert!(!frame.fin);
+        assert_eq!(frame.opcode, OpCode::Text);
+        let (frame, _) = Frame::parse(&second, false).unwrap().unwrap();
+        assert!(frame.fin);
+        assert_eq!(frame.opcode, OpCode::Continuation);
+    }
+
+    #[test]
+    fn test_extended_length() {
+        for len in [125, 126, 65535, 65536] {
+            let frame = Frame::new(true, OpCode::Binary, vec![7; len]);
+            let bytes = frame.to_bytes(Some([1, 2, 3, 4]));
+            // partial input
+            assert!(Frame::parse(&bytes[..bytes.len() - 1], true)
+                .unwrap()
+                .is_none());
+            let (parsed, consumed) = Frame::parse(&bytes, true).unwrap().unwrap();
+            assert_eq!(consumed, bytes.len());
+            assert_eq!(parsed, frame);
+        }
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        // unmasked client frame
+        assert!(matches!(
+            Frame::parse(&[0x81, 0x00], true),
+            Err(WsError::Protocol(_))
+        ));
+        // reserved bit
+        assert!(matches!(
+            Frame::parse(&[0xC1, 0x80, 0, 0, 0, 0], true),
+            Err(WsError::Protocol(_))
+        ));
+        // reserved opcode
+        assert!(matches!(
+            Frame::parse(&[0x83, 0x80, 0, 0, 0, 0], true),
+            Err(WsError::Protocol(_))
+        ));
+        // fragmented ping
+        assert!(matches!(
+            Frame::parse(&[0x09, 0x80, 0, 0, 0, 0], true),
+            Err(WsError::Protocol(_))
+        ));
+        // too long control frame
+        assert!(matches!(
+            Frame::parse(&[0x89, 0xFE, 0x00, 0x7E], true),
+            Err(WsError::Protocol(_))
+        ));
+        // too big payload
+        let mut header = vec![0x82, 0xFF];
+        header.extend_from_slice(&(MAX_PAYLOAD_LEN as u64 + 1).to_be_bytes());
+        assert!(matches!(
+            Frame::parse(&header, true),
+            Err(WsError::MessageTooBig)
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::errors::HTTPErrorMessage;
+use crate::request;
+use crate::responser::Response;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha1::{Digest, Sha1};
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const VERSION: &str = "13";
+
+// has_token() checks comma separated header value like `Connection: keep-alive, Upgrade`
+fn has_token(req: &request::Request, name: &str, token: &str) -> bool {
+    req.header(name)
+        .map(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+        .unwrap_or(false)
+}
+
+// is_upgrade() returns true when client asks for websocket
+pub fn is_upgrade(req: &request::Request) -> bool {
+    has_token(req, "Upgrade", "websocket")
+}
+
+// accept_key() computes `Sec-WebSocket-Accept` from `Sec-WebSocket-Key`
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+// accept() validates opening handshake and returns `101 Switching Protocols`
+pub fn accept(req: &request::Request) -> Result<Response, HTTPErrorMessage> {
+    if req.method != "GET" {
+        return Err(HTTPErrorMessage::BadRequest);
+    }
+    if !is_upgrade(req) {
+        return Err(HTTPErrorMessage::MissingHeader("Upgrade".to_string()));
+    }
+    if !has_token(req, "Connection", "upgrade") {
+        return Err(HTTPErrorMessage::MissingHeader("Connection".to_string()));
+    }
+    if req.header("Sec-WebSocket-Version") != Some(VERSION) {
+        return Err(HTTPErrorMessage::UnsupportedWebSocketVersion);
+    }
+    let key = req
+        .header("Sec-WebSocket-Key")
+        .ok_or_else(|| HTTPErrorMessage::MissingHeader("Sec-WebSocket-Key".to_string()))?;
+    // key must be base64-encoded 16 bytes
+    match STANDARD.decode(key) {
+        Ok(decoded) if decoded.len() == 16 => {}
+        _ => return Err(HTTPErrorMessage::InvalidWebSocketKey),
+    }
+
+    Ok(Response {
+        status_code: 101,
+        headers: vec![
+            ("Upgrade".to_string(), "websocket".to_string()),
+            ("Connection".to_string(), "Upgrade".to_string()),
+            ("Sec-WebSocket-Accept".to_string(), accept_key(key)),
+        ],
+        body: Vec::new(),
+    })
+}
+
+// error_response() tells supported version to client
+pub fn error_response(e: &HTTPErrorMessage) -> Response {
+    let mut response = Response::error_html(e);
+    if let HTTPErrorMessage::UnsupportedWebSocketVersion = e {
+        response
+            .headers
+            .push(("Sec-WebSocket-Version".to_string(), VERSION.to_string()));
+    }
+    response
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn request(headers: &[(&str, &str)]) -> request::Request {
+        let headers: HashMap<String, String> = headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        request::Request::new("GET".to_string(), "/ws".to_string(), headers, None, None)
+    }
+
+    #[test]
+    fn test_accept() {
+        // example in RFC 6455 section 1.3
+        let req = request(&[
+            ("Upgrade", "websocket"),
+            ("Connection", "keep-alive, Upgrade"),
+            ("Sec-WebSocket-Version", "13"),
+            ("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="),
+        ]);
+        let res = accept(&req).unwrap();
+        assert_eq!(res.status_code, 101);
+        assert_eq!(
+            res.header("Sec-WebSocket-Accept"),
+            Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=")
+        );
+    }
+
+    #[test]
+    fn test_accept_error() {
+        let key = ("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==");
+        let version = ("Sec-WebSocket-Version", "13");
+        let upgrade = ("Upgrade", "websocket");
+        let connection = ("Connection", "Upgrade");
+
+        assert!(matches!(
+            accept(&request(&[key, version, upgrade])),
+            Err(HTTPErrorMessage::MissingHeader(_))
+        ));
+        assert!(matches!(
+            accept(&request(&[key, version, connection])),
+            Err(HTTPErrorMessage::MissingHeader(_))
+        ));
+        assert!(matches!(
+            accept(&request(&[version, upgrade, connection])),
+            Err(HTTPErrorMessage::MissingHeader(_))
+        ));
+        assert!(matches!(
+            accept(&request(&[
+                ("Sec-WebSocket-Key", "short"),
+                version,
+                upgrade,
+                connection
+            ])),
+            Err(HTTPErrorMessage::InvalidWebSocketKey)
+        ));
+
+        let Err(e) = accept(&request(&[
+            key,
+            ("Sec-WebSocket-Version", "8"),
+            upgrade,
+            connection,
+        ])) else {
+            panic!("version 8 must be rejected");
+        };
+        let res = error_response(&e);
+        assert_eq!(res.status_code, 426);
+        assert_eq!(res.header("Sec-WebSocket-Version"), Some("13"));
+    }
+}
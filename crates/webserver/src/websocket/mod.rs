@@ -0,0 +1,394 @@
+pub mod frame;
+pub mod handshake;
+
+use frame::{Frame, OpCode, MAX_PAYLOAD_LEN};
+use std::io::{self, Read, Write};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<(u16, String)>),
+}
+
+#[derive(Debug, Error)]
+pub enum WsError {
+    #[error("connection is already closed")]
+    ConnectionClosed,
+
+    #[error("invalid UTF-8 in text message")]
+    InvalidUtf8,
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("message too big")]
+    MessageTooBig,
+
+    #[error("protocol error: {0}")]
+    Protocol(&'static str),
+}
+
+impl WsError {
+    // close_code() returns status code sent in close frame
+    pub fn close_code(&self) -> u16 {
+        match self {
+            WsError::Protocol(_) => 1002,
+            WsError::InvalidUtf8 => 1007,
+            WsError::MessageTooBig => 1009,
+            _ => 1011,
+        }
+    }
+}
+
+// Stream is implemented for TcpStream and TLS stream
+pub trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+// WebSocket is a server side connection after handshake
+// pings are answered and fragmented messages are reassembled in recv()
+pub struct WebSocket<'a> {
+    stream: &'a mut dyn Stream,
+    read_buf: Vec<u8>,
+    // opcode and payload of fragmented message in progress
+    fragment: Option<(OpCode, Vec<u8>)>,
+    close_sent: bool,
+    close_received: bool,
+}
+
+impl<'a> WebSocket<'a> {
+    pub fn new(stream: &'a mut dyn Stream) -> Self {
+        Self {
+            stream,
+            read_buf: Vec::new(),
+            fragment: None,
+            close_sent: false,
+            close_received: false,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.close_sent || self.close_received
+    }
+
+    // recv() returns next data message, or None once connection is closed
+    pub fn recv(&mut self) -> Result<Option<Message>, WsError> {
+        if self.close_received {
+            return Ok(None);
+        }
+        loop {
+            let Some(frame) = self.read_frame()? else {
+                // peer disappeared without close frame
+                self.close_received = true;
+                return Ok(None);
+            };
+
+            match frame.opcode {
+                OpCode::Ping => {
+                    if !self.close_sent {
+                        self.write_frame(&Frame::new(true, OpCode::Pong, frame.payload))?;
+                    }
+                }
+                OpCode::Pong => {}
+                OpCode::Close => {
+                    self.close_received = true;
+                    let close = parse_close_payload(&frame.payload)?;
+                    // echo status code back as RFC 6455 section 5.5.1
+                    if !self.close_sent {
+                        let code = close.as_ref().map(|(code, _)| *code);
+                        self.send_close(code, "")?;
+                    }
+                    return Ok(None);
+                }
+                OpCode::Text | OpCode::Binary => {
+                    if self.fragment.is_some() {
+                        return Err(WsError::Protocol("expected continuation frame"));
+                    }
+                    if frame.fin {
+                        return to_message(frame.opcode, frame.payload).map(Some);
+                    }
+                    self.fragment = Some((frame.opcode, frame.payload));
+                }
+                OpCode::Continuation => {
+                    let Some((opcode, mut payload)) = self.fragment.take() else {
+                        return Err(WsError::Protocol("unexpected continuation frame"));
+                    };
+                    if payload.len() + frame.payload.len() > MAX_PAYLOAD_LEN {
+                        return Err(WsError::MessageTooBig);
+                    }
+                    payload.extend_from_slice(&frame.payload);
+                    if frame.fin {
+                        return to_message(opcode, payload).map(Some);
+                    }
+                    self.fragment = Some((opcode, payload));
+                }
+            }
+        }
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), WsError> {
+        if self.close_sent {
+            return Err(WsError::ConnectionClosed);
+        }
+        let (opcode, payload) = match message {
+            Message::Text(text) => (OpCode::Text, text.as_bytes().to_vec()),
+            Message::Binary(data) => (OpCode::Binary, data.clone()),
+            Message::Ping(data) => (OpCode::Ping, data.clone()),
+            Message::Pong(data) => (OpCode::Pong, data.clone()),
+            Message::Close(close) => {
+                return match close {
+                    Some((code, reason)) => self.send_close(Some(*code), reason),
+                    None => self.send_close(None, ""),
+                };
+            }
+        };
+        if opcode.is_control() && payload.len() > 125 {
+            return Err(WsError::Protocol("control frame payload too long"));
+        }
+        self.write_frame(&Frame::new(true, opcode, payload))
+    }
+
+    pub fn send_text(&mut self, text: &str) -> Result<(), WsError> {
+        self.send(&Message::Text(text.to_string()))
+    }
+
+    // close() sends close frame and waits for reply from client
+    pub fn close(&mut self, code: u16, reason: &str) -> Result<(), WsError> {
+        if !self.close_sent {
+            self.send_close(Some(code), reason)?;
+        }
+        while !self.close_received {
+            match self.read_frame()? {
+                Some(frame) if frame.opcode == OpCode::Close => self.close_received = true,
+                Some(_) => {}
+                None => self.close_received = true,
+            }
+        }
+        Ok(())
+    }
+
+    fn send_close(&mut self, code: Option<u16>, reason: &str) -> Result<(), WsError> {
+        let mut payload = Vec::new();
+        if let Some(code) = code {
+            payload.extend_from_slice(&code.to_be_bytes());
+            // reason is truncated to fit into control frame
+            let mut end = reason.len().min(123);
+            while !reason.is_char_boundary(end) {
+                end -= 1;
+            }
+            payload.extend_from_slice(&reason.as_bytes()[..end]);
+        }
+        self.write_frame(&Frame::new(true, OpCode::Close, payload))?;
+        self.close_sent = true;
+        Ok(())
+    }
+
+    // read_frame() reads from stream until a whole frame is buffered
+    // returns None on EOF
+    fn read_frame(&mut self) -> Result<Option<Frame>, WsError> {
+        let mut chunk = [0; 4096];
+        loop {
+            if let Some((frame, len)) = Frame::parse(&self.read_buf, true)? {
+                self.read_buf.drain(..len);
+                return Ok(Some(frame));
+            }
+            match self.stream.read(&mut chunk) {
+                Ok(0) if self.read_buf.is_empty() => return Ok(None),
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    // server never masks frames
+    fn write_frame(&mut self, frame: &Frame) -> Result<(), WsError> {
+        self.stream.write_all(&frame.to_bytes(None))?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+// iterating over WebSocket yields messages until connection is closed or fails
+impl Iterator for WebSocket<'_> {
+    type Item = Result<Message, WsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.recv() {
+            Ok(message) => message.map(Ok),
+            Err(e) => {
+                self.close_received = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn to_message(opcode: OpCode, payload: Vec<u8>) -> Result<Message, WsError> {
+    match opcode {
+        OpCode::Text => String::from_utf8(payload)
+            .map(Message::Text)
+            .map_err(|_| WsError::InvalidUtf8),
+        _ => Ok(Message::Binary(payload)),
+    }
+}
+
+fn parse_close_payload(payload: &[u8]) -> Result<Option<(u16, String)>, WsError> {
+    match payload {
+        [] => Ok(None),
+        [_] => Err(WsError::Protocol("invalid close payload")),
+        [high, low, reason @ ..] => {
+            let code = u16::from_be_bytes([*high, *low]);
+            // codes which must not be sent on the wire
+            if !matches!(code, 1000..=1003 | 1007..=1011 | 3000..=4999) {
+                return Err(WsError::Protocol("invalid close code"));
+            }
+            let reason = String::from_utf8(reason.to_vec()).map_err(|_| WsError::InvalidUtf8)?;
+            Ok(Some((code, reason)))
+        }
+    }
+}
+
+/******************************************************************************
+ Test
+******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // MockStream replays client frames and records server output
+    struct MockStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(frames: &[Frame]) -> Self {
+            let input = frames
+                .iter()
+                .flat_map(|f| f.to_bytes(Some([1, 2, 3, 4])))
+                .collect();
+            Self {
+                input: Cursor::new(input),
+                output: Vec::new(),
+            }
+        }
+
+        fn sent_frames(&self) -> Vec<Frame> {
+            let mut frames = Vec::new();
+            let mut pos = 0;
+            while let Some((frame, len)) = Frame::parse(&self.output[pos..], false).unwrap() {
+                frames.push(frame);
+                pos += len;
+            }
+            frames
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_recv_fragment_ping_close() {
+        let mut stream = MockStream::new(&[
+            Frame::new(false, OpCode::Text, b"Hel".to_vec()),
+            // control frame in the middle of fragmented message
+            Frame::new(true, OpCode::Ping, b"hi".to_vec()),
+            Frame::new(true, OpCode::Continuation, b"lo".to_vec()),
+            Frame::new(true, OpCode::Binary, vec![1, 2]),
+            Frame::new(true, OpCode::Close, vec![0x03, 0xE8]),
+        ]);
+        let mut ws = WebSocket::new(&mut stream);
+        let messages: Vec<Message> = ws.by_ref().map(|m| m.unwrap()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                Message::Text("Hello".to_string()),
+                Message::Binary(vec![1, 2])
+            ]
+        );
+        assert!(ws.is_closed());
+        assert!(matches!(
+            ws.send_text("late"),
+            Err(WsError::ConnectionClosed)
+        ));
+
+        assert_eq!(
+            stream.sent_frames(),
+            vec![
+                Frame::new(true, OpCode::Pong, b"hi".to_vec()),
+                Frame::new(true, OpCode::Close, vec![0x03, 0xE8]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recv_error() {
+        let cases = [
+            (
+                vec![Frame::new(true, OpCode::Continuation, b"a".to_vec())],
+                1002,
+            ),
+            (
+                vec![
+                    Frame::new(false, OpCode::Text, b"a".to_vec()),
+                    Frame::new(true, OpCode::Text, b"b".to_vec()),
+                ],
+                1002,
+            ),
+            (vec![Frame::new(true, OpCode::Text, vec![0xFF, 0xFE])], 1007),
+            (vec![Frame::new(true, OpCode::Close, vec![0x03])], 1002),
+            (
+                vec![Frame::new(true, OpCode::Close, vec![0x03, 0xED])],
+                1002,
+            ),
+        ];
+        for (frames, code) in cases {
+            let mut stream = MockStream::new(&frames);
+            let mut ws = WebSocket::new(&mut stream);
+            let e = ws.recv().unwrap_err();
+            assert_eq!(e.close_code(), code, "{e}");
+        }
+    }
+
+    #[test]
+    fn test_close_handshake() {
+        let mut stream = MockStream::new(&[
+            Frame::new(true, OpCode::Text, b"ignored".to_vec()),
+            Frame::new(true, OpCode::Close, Vec::new()),
+        ]);
+        let mut ws = WebSocket::new(&mut stream);
+        ws.send_text("bye").unwrap();
+        ws.close(1000, "done").unwrap();
+
+        let mut close = vec![0x03, 0xE8];
+        close.extend_from_slice(b"done");
+        assert_eq!(
+            stream.sent_frames(),
+            vec![
+                Frame::new(true, OpCode::Text, b"bye".to_vec()),
+                Frame::new(true, OpCode::Close, close),
+            ]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use rcgen::{generate_simple_self_signed, CertifiedKey};
+use rustls::crypto::ring;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use webserver::{blocking, event_loop, handler, middleware, router, tls};
+
+// TestCert writes self-signed certificate into temporary directory
+struct TestCert {
+    dir: PathBuf,
+    cert_pem: String,
+}
+
+impl TestCert {
+    fn new(name: &str) -> Self {
+        let CertifiedKey { cert, signing_key } =
+            generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let dir =
+            std::env::temp_dir().join(format!("webserver_tls_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cert.pem"), cert.pem()).unwrap();
+        fs::write(dir.join("key.pem"), signing_key.serialize_pem()).unwrap();
+        Self {
+            dir,
+            cert_pem: cert.pem(),
+        }
+    }
+
+    fn cert_path(&self) -> PathBuf {
+        self.dir.join("cert.pem")
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.dir.join("key.pem")
+    }
+
+    fn client_config(&self) -> Arc<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut self.cert_pem.as_bytes()) {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let mut config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Arc::new(config)
+    }
+}
+
+impl Drop for TestCert {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn spawn_server(cert: &TestCert, event_loop_mode: bool) -> SocketAddr {
+    let config = tls::load_server_config(cert.cert_path(), cert.key_path()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut router = router::Router::new();
+        router.add_middleware(middleware::ContentTypeMiddleware);
+        router.get("/", handler::handler_a);
+        router.post("/submit", handler::handler_b);
+        let _ = if event_loop_mode {
+            event_loop::run(listener, &router, Some(config))
+        } else {
+            blocking::run(listener, &router, Some(config))
+        };
+    });
+    addr
+}
+
+// send() returns negotiated ALPN protocol and response
+fn send(addr: SocketAddr, config: Arc<ClientConfig>, request: &[u8]) -> (Vec<u8>, String) {
+    let conn = ClientConnection::new(config, ServerName::try_from("localhost").unwrap()).unwrap();
+    let sock = TcpStream::connect(addr).unwrap();
+    let mut stream = StreamOwned::new(conn, sock);
+    stream.write_all(request).unwrap();
+    stream.flush().unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let alpn = stream.conn.alpn_protocol().unwrap_or_default().to_vec();
+    (alpn, String::from_utf8(response).unwrap())
+}
+
+fn check_https(event_loop_mode: bool) {
+    let cert = TestCert::new(if event_loop_mode {
+        "event_loop"
+    } else {
+        "blocking"
+    });
+    let addr = spawn_server(&cert, event_loop_mode);
+
+    let (alpn, res) = send(
+        addr,
+        cert.client_config(),
+        b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert_eq!(alpn, b"http/1.1");
+    assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{res}");
+    assert!(res.ends_with("<h1>Hello, GET!</h1>"), "{res}");
+
+    let body = r#"{"id":"my_id"}"#;
+    let req = format!(
+        "POST /submit HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let (_, res) = send(addr, cert.client_config(), req.as_bytes());
+    assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{res}");
+
+    // plain HTTP is rejected, but server keeps running
+    let mut plain = TcpStream::connect(addr).unwrap();
+    plain
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut buf = Vec::new();
+    let _ = plain.read_to_end(&mut buf);
+    assert!(!buf.starts_with(b"HTTP/1.1 200"));
+
+    let (_, res) = send(
+        addr,
+        cert.client_config(),
+        b"GET /none HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(res.starts_with("HTTP/1.1 404 Not Found\r\n"), "{res}");
+}
+
+#[test]
+fn test_https_blocking() {
+    check_https(false);
+}
+
+#[test]
+fn test_https_event_loop() {
+    check_https(true);
+}
+
+#[test]
+fn test_load_server_config_error() {
+    let cert = TestCert::new("error");
+    assert!(tls::load_server_config(cert.dir.join("none.pem"), cert.key_path()).is_err());
+    // key is not found in certificate file
+    assert!(tls::load_server_config(cert.cert_path(), cert.cert_path()).is_err());
+    // certificate is not found in key file
+    assert!(tls::load_server_config(cert.key_path(), cert.key_path()).is_err());
+}
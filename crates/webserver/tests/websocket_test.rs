@@ -0,0 +1,150 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use webserver::blocking;
+use webserver::handler;
+use webserver::router::Router;
+use webserver::websocket::frame::{Frame, OpCode};
+
+const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+
+fn spawn_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut router = Router::new();
+        router.websocket("/ws/echo", handler::ws_echo);
+        router.websocket("/ws/fail", |_req, _state, _ws| {
+            Err(anyhow::anyhow!("handler failed"))
+        });
+        let _ = blocking::run(listener, &router, None);
+    });
+    addr
+}
+
+// connect() sends opening handshake and returns response head
+fn connect(addr: SocketAddr, path: &str, version: &str) -> (TcpStream, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let req = format!(
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {KEY}\r\nSec-WebSocket-Version: {version}\r\n\r\n"
+    );
+    stream.write_all(req.as_bytes()).unwrap();
+
+    // read head byte by byte not to consume frames
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0; 1];
+        if stream.read(&mut byte).unwrap() == 0 {
+            break;
+        }
+        head.push(byte[0]);
+    }
+    (stream, String::from_utf8(head).unwrap())
+}
+
+fn send_frame(stream: &mut TcpStream, frame: Frame) {
+    stream
+        .write_all(&frame.to_bytes(Some([0x12, 0x34, 0x56, 0x78])))
+        .unwrap();
+}
+
+fn recv_frame(stream: &mut TcpStream, buf: &mut Vec<u8>) -> Frame {
+    loop {
+        if let Some((frame, len)) = Frame::parse(buf, false).unwrap() {
+            buf.drain(..len);
+            return frame;
+        }
+        let mut chunk = [0; 1024];
+        let n = stream.read(&mut chunk).unwrap();
+        assert!(n > 0, "connection closed");
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+#[test]
+fn test_websocket_echo() {
+    let addr = spawn_server();
+    let (mut stream, head) = connect(addr, "/ws/echo", "13");
+    assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+    let mut buf = Vec::new();
+
+    send_frame(
+        &mut stream,
+        Frame::new(true, OpCode::Text, b"hello".to_vec()),
+    );
+    assert_eq!(
+        recv_frame(&mut stream, &mut buf),
+        Frame::new(true, OpCode::Text, b"hello".to_vec())
+    );
+
+    // fragmented binary with ping in between
+    send_frame(
+        &mut stream,
+        Frame::new(false, OpCode::Binary, vec![1; 70000]),
+    );
+    send_frame(
+        &mut stream,
+        Frame::new(true, OpCode::Ping, b"ping".to_vec()),
+    );
+    send_frame(
+        &mut stream,
+        Frame::new(true, OpCode::Continuation, vec![2; 10]),
+    );
+    assert_eq!(
+        recv_frame(&mut stream, &mut buf),
+        Frame::new(true, OpCode::Pong, b"ping".to_vec())
+    );
+    let frame = recv_frame(&mut stream, &mut buf);
+    assert_eq!(frame.opcode, OpCode::Binary);
+    assert_eq!(frame.payload.len(), 70010);
+
+    // close handshake
+    send_frame(
+        &mut stream,
+        Frame::new(true, OpCode::Close, vec![0x03, 0xE8]),
+    );
+    assert_eq!(
+        recv_frame(&mut stream, &mut buf),
+        Frame::new(true, OpCode::Close, vec![0x03, 0xE8])
+    );
+    assert_eq!(stream.read(&mut [0; 16]).unwrap(), 0);
+}
+
+#[test]
+fn test_websocket_protocol_error() {
+    let addr = spawn_server();
+    let (mut stream, _) = connect(addr, "/ws/echo", "13");
+    let mut buf = Vec::new();
+
+    // unmasked frame from client
+    stream
+        .write_all(&Frame::new(true, OpCode::Text, b"hello".to_vec()).to_bytes(None))
+        .unwrap();
+    let frame = recv_frame(&mut stream, &mut buf);
+    assert_eq!(frame.opcode, OpCode::Close);
+    assert_eq!(frame.payload[..2], 1002u16.to_be_bytes());
+}
+
+#[test]
+fn test_websocket_handler_error() {
+    let addr = spawn_server();
+    let (mut stream, _) = connect(addr, "/ws/fail", "13");
+    let mut buf = Vec::new();
+
+    let frame = recv_frame(&mut stream, &mut buf);
+    assert_eq!(frame, Frame::new(true, OpCode::Close, vec![0x03, 0xF3]));
+    send_frame(&mut stream, Frame::new(true, OpCode::Close, Vec::new()));
+}
+
+#[test]
+fn test_websocket_handshake_error() {
+    let addr = spawn_server();
+
+    let (_, head) = connect(addr, "/ws/echo", "8");
+    assert!(head.starts_with("HTTP/1.1 426 Upgrade Required\r\n"));
+    assert!(head.contains("Sec-WebSocket-Version: 13\r\n"));
+
+    let (_, head) = connect(addr, "/ws/none", "13");
+    assert!(head.starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
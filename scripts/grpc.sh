@@ -0,0 +1,82 @@
+#!/bin/bash
+# gRPC version of app.hurl using grpc-client
+# - reset database and run grpc-server before running script
+# - ADDRESS=http://127.0.0.1:8080 for axumfw with `[grpc] multiplex = true`
+# - TRANSPORT=grpc-web for grpcweb-server
+set -euo pipefail
+
+ADDRESS=${ADDRESS:-http://[::1]:50051}
+TRANSPORT=${TRANSPORT:-grpc}
+TOKEN_FILE=$(mktemp -d)/token
+trap 'rm -rf "$(dirname "${TOKEN_FILE}")"' EXIT
+
+client() {
+  cargo run -q --bin grpc-client -- -a "${ADDRESS}" -t "${TRANSPORT}" -o json --token-file "${TOKEN_FILE}" "$@"
+}
+
+# assert <jq filter> <json>
+assert() {
+  if [ "$(echo "$2" | jq "$1")" != "true" ]; then
+    echo "assertion failed: $1"
+    echo "$2"
+    exit 1
+  fi
+}
+
+echo "[todo list without token]"
+if client todos list --user-id 1 2>/dev/null; then
+  echo "expected error"
+  exit 1
+fi
+
+echo "[admin login]"
+assert '.token != ""' "$(client login --admin --email john.doe@example.com --password password1234)"
+
+echo "[user list]"
+res=$(client users list)
+assert '.users | length == 1' "${res}"
+userid=$(echo "${res}" | jq '.users[0].id')
+
+echo "[add todo]"
+res=$(client todos create --user-id "${userid}" --title Programming --description "sample program using tonic")
+assert '.title == "Programming" and .status == "pending"' "${res}"
+todoid=$(echo "${res}" | jq '.id')
+
+echo "[get todo]"
+assert '.id == '"${todoid}" "$(client todos get --user-id "${userid}" --todo-id "${todoid}")"
+
+echo "[partially update todo]"
+res=$(client todos update --user-id "${userid}" --todo-id "${todoid}" --status doing)
+assert '.title == "Programming" and .status == "doing"' "${res}"
+
+echo "[todo list]"
+assert '.todos | length == 1' "$(client todos list --user-id "${userid}")"
+
+echo "[delete todo]"
+assert '.deleted == 1' "$(client todos delete --user-id "${userid}" --todo-id "${todoid}")"
+
+echo "[get deleted todo]"
+if client todos get --user-id "${userid}" --todo-id "${todoid}" 2>/dev/null; then
+  echo "expected error"
+  exit 1
+fi
+
+echo "[add app user]"
+res=$(client users create --first-name Foo --last-name Bar --email foo.bar@example.com --password password9999)
+app_userid=$(echo "${res}" | jq '.id')
+
+echo "[app user login]"
+client login --email foo.bar@example.com --password password9999 >/dev/null
+
+echo "[todo list of other user]"
+if client todos list --user-id "${userid}" 2>/dev/null; then
+  echo "expected error"
+  exit 1
+fi
+
+echo "[delete app user by admin]"
+client login --admin --email john.doe@example.com --password password1234 >/dev/null
+assert '.deleted == 1' "$(client users delete --user-id "${app_userid}")"
+
+client logout
+echo "done"